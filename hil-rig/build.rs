@@ -0,0 +1,12 @@
+//! Link against the merger firmware's memory layout, the rig runs on the
+//! same Pico board
+
+use std::path::PathBuf;
+use std::{env, fs};
+
+fn main() {
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::copy("../software/memory.x", out.join("memory.x")).unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+    println!("cargo:rerun-if-changed=../software/memory.x");
+}
@@ -0,0 +1,324 @@
+//! Hardware-in-the-loop test rig for the merger
+//!
+//! Runs on a second Pico wired to a merger under test. The rig plays
+//! scripted traffic into both merger inputs at the same time and checks the
+//! merged output: every message of every input must arrive complete, in
+//! order and exactly once, with running status resolved correctly. The
+//! scenarios repeat forever, so a rig left running is a soak test.
+//!
+//! Wiring (rig → merger):
+//! - GPIO0 (UART0 TX) through a MIDI OUT circuit to MIDI IN 1
+//! - GPIO4 (UART1 TX) through a MIDI OUT circuit to MIDI IN 2
+//! - MIDI OUT through a MIDI IN circuit to GPIO1 (UART0 RX), or the merger's
+//!   GPIO12 straight to GPIO1 with the grounds connected
+//!
+//! Results go to RTT. The on-board LED (GPIO25) is on while every run has
+//! passed and blinks once any run failed.
+//!
+//! The merger must run with its defaults: velocity calibration, forced Note
+//! Off velocity and pacing change the output the rig expects.
+
+#![no_std]
+#![no_main]
+
+mod pattern;
+
+use defmt::{error, info, warn};
+use embassy_executor::Spawner;
+use embassy_futures::join::join3;
+use embassy_rp::bind_interrupts;
+use embassy_rp::gpio::{Level, Output};
+use embassy_rp::peripherals::{UART0, UART1};
+use embassy_rp::uart::{
+    BufferedInterruptHandler, BufferedUart, BufferedUartRx, BufferedUartTx, Config,
+};
+use embassy_time::{with_timeout, Duration, Instant, Timer};
+use embedded_io_async::Write;
+use midi_core::midi_uart::{MidiUart, UartChannel};
+use midi_core::parser::{MidiMessage, ShortMessage};
+use pattern::Pattern;
+use static_cell::ConstStaticCell;
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    UART0_IRQ => BufferedInterruptHandler<UART0>;
+    UART1_IRQ => BufferedInterruptHandler<UART1>;
+});
+
+/// Time for the merger to boot when both boards power up together
+const BOOT_DELAY: Duration = Duration::from_secs(2);
+
+/// Silence on the merger's output that ends a scenario
+const QUIET: Duration = Duration::from_millis(300);
+
+/// Pause between runs of all scenarios
+const RUN_INTERVAL: Duration = Duration::from_secs(5);
+
+static UART0_TX_BUF: ConstStaticCell<[u8; 64]> = ConstStaticCell::new([0; 64]);
+static UART0_RX_BUF: ConstStaticCell<[u8; 1024]> = ConstStaticCell::new([0; 1024]);
+static UART1_TX_BUF: ConstStaticCell<[u8; 64]> = ConstStaticCell::new([0; 64]);
+
+struct Scenario {
+    name: &'static str,
+    /// Traffic for MIDI IN 1 and MIDI IN 2
+    inputs: [Pattern; 2],
+}
+
+const SCENARIOS: &[Scenario] = &[
+    Scenario {
+        name: "notes",
+        inputs: [
+            Pattern::Notes {
+                channel: 0,
+                count: 200,
+                running_status: false,
+                interval_us: 5_000,
+            },
+            Pattern::Notes {
+                channel: 1,
+                count: 200,
+                running_status: false,
+                interval_us: 7_000,
+            },
+        ],
+    },
+    Scenario {
+        // The merger has to inject status bytes whenever the inputs alternate
+        name: "running status",
+        inputs: [
+            Pattern::Notes {
+                channel: 0,
+                count: 256,
+                running_status: true,
+                interval_us: 2_000,
+            },
+            Pattern::Notes {
+                channel: 1,
+                count: 256,
+                running_status: true,
+                interval_us: 3_000,
+            },
+        ],
+    },
+    Scenario {
+        // About 80% of the output's bandwidth
+        name: "dense controllers",
+        inputs: [
+            Pattern::ControlChanges {
+                channel: 0,
+                count: 500,
+                interval_us: 2_500,
+            },
+            Pattern::ControlChanges {
+                channel: 1,
+                count: 500,
+                interval_us: 2_500,
+            },
+        ],
+    },
+    Scenario {
+        name: "clock and notes",
+        inputs: [
+            Pattern::Clock {
+                count: 480,
+                interval_us: 5_000,
+            },
+            Pattern::Notes {
+                channel: 1,
+                count: 800,
+                running_status: true,
+                interval_us: 3_000,
+            },
+        ],
+    },
+    Scenario {
+        // Running status back to back, close to the line rate
+        name: "single input burst",
+        inputs: [
+            Pattern::Notes {
+                channel: 0,
+                count: 300,
+                running_status: true,
+                interval_us: 700,
+            },
+            Pattern::Idle,
+        ],
+    },
+];
+
+#[derive(Debug, Default, defmt::Format)]
+struct Outcome {
+    /// Messages attributed to each input
+    received: [u16; 2],
+    /// Messages that differ from what their input sent
+    mismatches: u16,
+    /// Messages no input sent, or more than an input sent
+    unexpected: u16,
+    /// Parse and UART errors on the output
+    errors: u16,
+}
+
+impl Outcome {
+    fn passed(&self, scenario: &Scenario) -> bool {
+        self.mismatches == 0
+            && self.unexpected == 0
+            && self.errors == 0
+            && self
+                .received
+                .iter()
+                .zip(&scenario.inputs)
+                .all(|(received, pattern)| *received == pattern.len())
+    }
+}
+
+/// Play a pattern into one merger input
+async fn send(tx: &mut impl Write, pattern: Pattern) {
+    let interval = Duration::from_micros(pattern.interval_us().into());
+    let mut next = Instant::now();
+    for n in 0..pattern.len() {
+        Timer::at(next).await;
+        if tx.write_all(&pattern.encode(n)).await.is_err() {
+            error!("Write of message {} of {} failed", n, pattern);
+        }
+        next += interval;
+    }
+}
+
+/// Check the merged output until it goes quiet
+async fn validate(
+    output: &mut MidiUart<BufferedUartRx<'static, UART0>>,
+    patterns: &[Pattern; 2],
+) -> Outcome {
+    let mut outcome = Outcome::default();
+    // Running status of the output stream
+    let mut status = None;
+    loop {
+        let message = match with_timeout(QUIET, output.read()).await {
+            Err(_) => return outcome,
+            Ok(Ok(message)) => message.message,
+            Ok(Err(_)) => {
+                outcome.errors += 1;
+                output.reset_parser();
+                status = None;
+                continue;
+            }
+        };
+
+        let bytes: ShortMessage = match message {
+            MidiMessage::Voice(bytes) => {
+                status = Some(bytes[0]);
+                bytes
+            }
+            MidiMessage::SystemCommon(bytes) => {
+                status = None;
+                bytes
+            }
+            MidiMessage::SystemRealtime(bytes) => bytes,
+            MidiMessage::RunningStatus(data) => match status {
+                Some(status) => ShortMessage::new(Some(status), &data),
+                None => {
+                    warn!("Running status data {:x} without a status", &data[..]);
+                    outcome.unexpected += 1;
+                    continue;
+                }
+            },
+            MidiMessage::SysEx(_) | MidiMessage::SysExChunk(_) => {
+                status = None;
+                outcome.unexpected += 1;
+                continue;
+            }
+        };
+
+        let Some(input) = patterns.iter().position(|pattern| pattern.owns(&bytes)) else {
+            warn!("Unexpected message {:x}", &bytes[..]);
+            outcome.unexpected += 1;
+            continue;
+        };
+        let n = outcome.received[input];
+        if n >= patterns[input].len() {
+            warn!("Extra message {:x} from input {}", &bytes[..], input);
+            outcome.unexpected += 1;
+            continue;
+        }
+        let expected = patterns[input].message(n);
+        if *bytes != *expected {
+            warn!(
+                "Message {} from input {}: expected {:x}, got {:x}",
+                n,
+                input,
+                expected.as_slice(),
+                &bytes[..]
+            );
+            outcome.mismatches += 1;
+        }
+        outcome.received[input] += 1;
+    }
+}
+
+/// Wait for the next run, showing the result on the LED
+async fn show_result(led: &mut Output<'static>, any_failed: bool) {
+    if !any_failed {
+        led.set_high();
+        Timer::after(RUN_INTERVAL).await;
+        return;
+    }
+    let end = Instant::now() + RUN_INTERVAL;
+    while Instant::now() < end {
+        led.toggle();
+        Timer::after_millis(100).await;
+    }
+}
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+
+    let mut config = Config::default();
+    config.baudrate = 31250;
+
+    let uart0 = BufferedUart::new(
+        p.UART0,
+        Irqs,
+        p.PIN_0,
+        p.PIN_1,
+        UART0_TX_BUF.take(),
+        UART0_RX_BUF.take(),
+        config,
+    );
+    let (mut tx0, rx) = uart0.split();
+    let mut tx1 = BufferedUartTx::new(p.UART1, Irqs, p.PIN_4, UART1_TX_BUF.take(), config);
+    // The channel tag is unused, the rig has a single output to read
+    let mut output = MidiUart::new(rx, UartChannel::Zero);
+    let mut led = Output::new(p.PIN_25, Level::Low);
+
+    Timer::after(BOOT_DELAY).await;
+    // Whatever the merger sent while booting
+    while with_timeout(QUIET, output.read()).await.is_ok() {}
+
+    let mut runs = 0u32;
+    let mut failed_runs = 0u32;
+    loop {
+        runs += 1;
+        let mut passed = true;
+        for scenario in SCENARIOS {
+            let [pattern0, pattern1] = scenario.inputs;
+            let (_, _, outcome) = join3(
+                send(&mut tx0, pattern0),
+                send(&mut tx1, pattern1),
+                validate(&mut output, &scenario.inputs),
+            )
+            .await;
+            if outcome.passed(scenario) {
+                info!("Run {}: {} passed", runs, scenario.name);
+            } else {
+                error!("Run {}: {} failed: {}", runs, scenario.name, outcome);
+                passed = false;
+            }
+        }
+        if !passed {
+            failed_runs += 1;
+        }
+        info!("Run {} done, {} of {} runs failed", runs, failed_runs, runs);
+        show_result(&mut led, failed_runs > 0).await;
+    }
+}
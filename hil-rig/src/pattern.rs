@@ -0,0 +1,103 @@
+//! Scripted traffic for one merger input
+//!
+//! A pattern is a numbered sequence of messages that can be regenerated from
+//! the index alone, so the validator needs no copy of what was sent: message
+//! `n` arriving on the output must equal `pattern.message(n)`.
+//!
+//! Inputs in a scenario use different MIDI channels, which is how output
+//! messages are attributed to the input they came from. At most one input
+//! per scenario sends realtime bytes.
+
+use heapless::Vec;
+
+/// Controller used by `ControlChanges`, a general purpose one outside the
+/// 14-bit pairs the merger may hold back and the range it may thin
+const CONTROLLER: u8 = 0x50;
+
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub enum Pattern {
+    /// Sends nothing
+    Idle,
+    /// Alternating Note On and Note Off over two octaves
+    Notes {
+        channel: u8,
+        count: u16,
+        /// Send the status byte with the first message only
+        running_status: bool,
+        interval_us: u32,
+    },
+    /// A sweep of one controller, with status bytes
+    ControlChanges {
+        channel: u8,
+        count: u16,
+        interval_us: u32,
+    },
+    /// Timing Clock ticks
+    Clock { count: u16, interval_us: u32 },
+}
+
+impl Pattern {
+    /// Number of messages in the pattern
+    pub fn len(&self) -> u16 {
+        match *self {
+            Pattern::Idle => 0,
+            Pattern::Notes { count, .. }
+            | Pattern::ControlChanges { count, .. }
+            | Pattern::Clock { count, .. } => count,
+        }
+    }
+
+    /// Time between the starts of two messages
+    pub fn interval_us(&self) -> u32 {
+        match *self {
+            Pattern::Idle => 0,
+            Pattern::Notes { interval_us, .. }
+            | Pattern::ControlChanges { interval_us, .. }
+            | Pattern::Clock { interval_us, .. } => interval_us,
+        }
+    }
+
+    /// Message `n` with its status byte, as it must come out of the merger
+    pub fn message(&self, n: u16) -> Vec<u8, 3> {
+        let bytes: &[u8] = match *self {
+            Pattern::Idle => &[],
+            Pattern::Notes { channel, .. } => {
+                let note = 0x30 + (n / 2 % 24) as u8;
+                if n.is_multiple_of(2) {
+                    &[0x90 | channel, note, 1 + (n % 127) as u8]
+                } else {
+                    // Note On with velocity 0, so running status holds
+                    &[0x90 | channel, note, 0]
+                }
+            }
+            Pattern::ControlChanges { channel, .. } => {
+                &[0xB0 | channel, CONTROLLER, (n % 128) as u8]
+            }
+            Pattern::Clock { .. } => &[0xF8],
+        };
+        Vec::from_slice(bytes).unwrap()
+    }
+
+    /// Message `n` as it goes on the wire
+    pub fn encode(&self, n: u16) -> Vec<u8, 3> {
+        let message = self.message(n);
+        match *self {
+            Pattern::Notes {
+                running_status: true,
+                ..
+            } if n > 0 => Vec::from_slice(&message[1..]).unwrap(),
+            _ => message,
+        }
+    }
+
+    /// Whether a complete output message belongs to this pattern
+    pub fn owns(&self, message: &[u8]) -> bool {
+        match *self {
+            Pattern::Idle => false,
+            Pattern::Notes { channel, .. } | Pattern::ControlChanges { channel, .. } => {
+                message[0] < 0xF0 && message[0] & 0x0F == channel
+            }
+            Pattern::Clock { .. } => message[0] == 0xF8,
+        }
+    }
+}
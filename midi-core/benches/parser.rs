@@ -0,0 +1,128 @@
+//! Host benchmarks of the parser and the merge path
+//!
+//! Absolute numbers say little about the RP2040, but relative changes show
+//! when a feature makes the per-byte or per-message path heavier.
+//!
+//! ```text
+//! cargo bench -p midi-core
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use embassy_futures::block_on;
+use midi_core::merge::Merger;
+use midi_core::midi_uart::{MidiUart, UartChannel};
+use midi_core::parser::{MidiMessage, MidiParser};
+
+/// Note On/Off pairs on one channel, using running status
+fn notes(messages: usize) -> Vec<u8> {
+    let mut bytes = vec![0x90];
+    for i in 0..messages {
+        let note = 0x30 + (i % 24) as u8;
+        let velocity = if i % 2 == 0 { 0x64 } else { 0x00 };
+        bytes.extend([note, velocity]);
+    }
+    bytes
+}
+
+/// Control changes with clock bytes in between, each with its status byte
+fn controllers_and_clock(messages: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for i in 0..messages {
+        if i % 4 == 0 {
+            bytes.push(0xF8);
+        } else {
+            bytes.extend([0xB0 | (i % 16) as u8, 0x01, (i % 128) as u8]);
+        }
+    }
+    bytes
+}
+
+/// Short SysEx messages the parser captures whole
+fn sysex(messages: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for _ in 0..messages {
+        bytes.extend([0xF0, 0x7D, 0x4D, 0x4D, 0x00, 0x01, 0xF7]);
+    }
+    bytes
+}
+
+fn streams() -> [(&'static str, Vec<u8>); 3] {
+    [
+        ("notes", notes(1000)),
+        ("controllers_and_clock", controllers_and_clock(1000)),
+        ("sysex", sysex(1000)),
+    ]
+}
+
+fn feed_byte(c: &mut Criterion) {
+    let mut group = c.benchmark_group("feed_byte");
+    for (name, bytes) in streams() {
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter_batched_ref(
+                MidiParser::default,
+                |parser| {
+                    for byte in &bytes {
+                        let _ = black_box(parser.feed_byte(*byte));
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// The firmware's read path: `MidiUart::read()` over a buffer
+fn midi_uart_read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("midi_uart_read");
+    for (name, bytes) in streams() {
+        let mut parser = MidiParser::default();
+        let messages = bytes
+            .iter()
+            .filter(|byte| matches!(parser.feed_byte(**byte), Ok(Some(_))))
+            .count();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut uart = MidiUart::new(bytes.as_slice(), UartChannel::Zero);
+                // An exhausted reader makes read() spin, so stop at the count
+                for _ in 0..messages {
+                    let _ = black_box(block_on(uart.read()));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Status injection as two inputs alternate on the output
+fn merge(c: &mut Criterion) {
+    let mut parser = MidiParser::default();
+    let messages: Vec<MidiMessage> = notes(1000)
+        .into_iter()
+        .filter_map(|byte| parser.feed_byte(byte).ok().flatten())
+        .collect();
+
+    let mut group = c.benchmark_group("merge");
+    group.throughput(Throughput::Elements(messages.len() as u64));
+    group.bench_function("alternating_inputs", |b| {
+        b.iter_batched_ref(
+            Merger::<2>::default,
+            |merger| {
+                for (i, message) in messages.iter().enumerate() {
+                    let input = i % 2;
+                    if let Some(bytes) = merger.prepare(input, message) {
+                        black_box(bytes);
+                        merger.sent(input, message);
+                    }
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, feed_byte, midi_uart_read, merge);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+//! MIDI parser and merge logic of the merger
+//!
+//! Shared by the firmware and the host tools, so both run the exact same
+//! state machines. The parser itself lives in the `midi-parser` crate and is
+//! re-exported here as `parser`, along with its clock and events; the
+//! runtime log level and `log::` macros shared with the firmware live here.
+//! The crate is `no_std`; the `defmt` feature routes its logging to defmt on
+//! the target. Host tools enable `std` instead, which brings the time driver
+//! the byte timeout needs and `std::error::Error` for the parser's errors.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use midi_parser as parser;
+pub use midi_parser::{clock, event};
+
+pub mod log;
+pub mod merge;
+pub mod midi_uart;
+pub mod parameter;
+pub mod queues;
+pub mod tempo;
+pub mod timecode;
@@ -0,0 +1,105 @@
+//! Runtime log level on top of defmt
+//!
+//! defmt filters log statements at compile time (`DEFMT_LOG`). To change the
+//! verbosity in the field without reflashing, the firmware is built with debug
+//! logs compiled in, and the `log_warn!`, `log_info!` and `log_debug!` macros
+//! below check the runtime level before handing off to defmt. A disabled
+//! statement costs one atomic load and sends nothing over RTT. Errors are
+//! always logged.
+//!
+//! Without the `defmt` feature (host builds) all of them compile to nothing.
+//!
+//! The parser has log macros of its own; `MidiUart` hands it `enabled` as its
+//! log filter, so the runtime level applies to the parser too.
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+pub use midi_parser::LogLevel;
+
+/// Level at boot: everything in development builds, errors only in release
+/// builds, where per-message logging would add RTT overhead during a show
+pub const DEFAULT_LEVEL: LogLevel = if cfg!(debug_assertions) {
+    LogLevel::Debug
+} else {
+    LogLevel::Error
+};
+
+static LEVEL: AtomicU8 = AtomicU8::new(DEFAULT_LEVEL as u8);
+
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn level() -> LogLevel {
+    LogLevel::from_u8(LEVEL.load(Ordering::Relaxed)).unwrap_or(DEFAULT_LEVEL)
+}
+
+pub fn enabled(level: LogLevel) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+#[cfg(feature = "defmt")]
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        defmt::error!($($arg)*);
+    };
+}
+
+#[cfg(feature = "defmt")]
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::LogLevel::Warn) {
+            defmt::warn!($($arg)*);
+        }
+    };
+}
+
+#[cfg(feature = "defmt")]
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::LogLevel::Info) {
+            defmt::info!($($arg)*);
+        }
+    };
+}
+
+#[cfg(feature = "defmt")]
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::LogLevel::Debug) {
+            defmt::debug!($($arg)*);
+        }
+    };
+}
+
+#[cfg(not(feature = "defmt"))]
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)*) => {};
+}
+
+#[cfg(not(feature = "defmt"))]
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)*) => {};
+}
+
+#[cfg(not(feature = "defmt"))]
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => {};
+}
+
+#[cfg(not(feature = "defmt"))]
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)*) => {};
+}
+
+// Exported under their short names; `warn` can't be re-exported directly as it
+// clashes with the built-in attribute
+pub use crate::{log_debug as debug, log_error as error, log_info as info, log_warn as warn};
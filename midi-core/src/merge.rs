@@ -0,0 +1,108 @@
+//! Running status bookkeeping of the merged output
+//!
+//! Each input may use running status, but on the merged output a data-only
+//! message is only valid if the message before it came from the same input.
+//! `Merger` keeps the last status of every input and which input was written
+//! last, and injects the cached status byte whenever the output's running
+//! status belongs to another input.
+//!
+//! Inputs are identified by their index, so the firmware and the host tools
+//! can use their own input types.
+
+use crate::parser::{MidiMessage, ShortMessage};
+
+#[derive(Debug)]
+pub struct Merger<const INPUTS: usize> {
+    /// Last status byte seen from each input
+    statuses: [Option<u8>; INPUTS],
+    /// Input whose running status the output carries, if any
+    last_tx_from: Option<usize>,
+}
+
+impl<const INPUTS: usize> Default for Merger<INPUTS> {
+    fn default() -> Self {
+        Self {
+            statuses: [None; INPUTS],
+            last_tx_from: None,
+        }
+    }
+}
+
+impl<const INPUTS: usize> Merger<INPUTS> {
+    /// Cached status of an input, resolves its running status messages
+    pub fn status(&self, input: usize) -> Option<u8> {
+        self.statuses[input]
+    }
+
+    /// Forget the status of an input, after its parser was reset
+    ///
+    /// Its next running status message is rejected until it sends a status
+    /// byte again, instead of going out with a stale status.
+    pub fn invalidate(&mut self, input: usize) {
+        self.statuses[input] = None;
+        if self.last_tx_from == Some(input) {
+            self.last_tx_from = None;
+        }
+    }
+
+    /// Forget all statuses, e.g. after the inputs were muted
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// The output's running status is unknown or cancelled, after SysEx or a
+    /// failed write, so the next message must start with a status byte
+    pub fn interrupt(&mut self) {
+        self.last_tx_from = None;
+    }
+
+    /// Account for a message that is dropped instead of written
+    ///
+    /// Later running status messages of the input build on a dropped Voice
+    /// message's status, which the receiver hasn't seen.
+    pub fn skip(&mut self, input: usize, message: &MidiMessage) {
+        if let MidiMessage::Voice(data) = message {
+            self.statuses[input] = Some(data[0]);
+            self.last_tx_from = None;
+        }
+    }
+
+    /// Complete message as it goes on the wire
+    ///
+    /// Returns `None` for a running status message from an input without a
+    /// cached status, which can't be sent, and for SysEx, which isn't merged.
+    /// Call `sent()` once the bytes are written.
+    pub fn prepare(&mut self, input: usize, message: &MidiMessage) -> Option<ShortMessage> {
+        match message {
+            MidiMessage::Voice(data) => {
+                self.statuses[input] = Some(data[0]);
+                Some(*data)
+            }
+            MidiMessage::SystemCommon(data) => {
+                // System Common cancels running status, the input has to
+                // send a status byte again
+                self.statuses[input] = None;
+                Some(*data)
+            }
+            MidiMessage::SystemRealtime(data) => Some(*data),
+            MidiMessage::RunningStatus(data) => {
+                let status = match self.last_tx_from {
+                    Some(last) if last == input => None,
+                    _ => Some(self.statuses[input]?),
+                };
+                Some(ShortMessage::new(status, data))
+            }
+            MidiMessage::SysEx(_) | MidiMessage::SysExChunk(_) => None,
+        }
+    }
+
+    /// Record a message written to the output
+    pub fn sent(&mut self, input: usize, message: &MidiMessage) {
+        self.last_tx_from = match message {
+            // It cancels running status on the receiver as well, so the next
+            // message must carry a status byte
+            MidiMessage::SystemCommon(_) => None,
+            _ => Some(input),
+        };
+    }
+}
@@ -0,0 +1,239 @@
+//! MIDI input from a buffered byte stream
+//!
+//! `MidiUart` reads from any `embedded_io_async::BufRead`: the firmware's
+//! buffered UARTs on the target, an in-memory reader in host tests.
+
+use crate::parser::{
+    Discarded, MidiMessage, MidiMessageError, MidiParser, ParserStats, ResyncPolicy,
+    UndefinedRealtime,
+};
+use embassy_time::Duration;
+use embedded_io_async::BufRead;
+use heapless::Deque;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UartChannel {
+    #[default]
+    Zero,
+    One,
+    /// I2C target port (see `midi_i2c`)
+    I2c,
+    /// Co-processor connected over SPI (see `spi_bridge`)
+    Spi,
+}
+
+impl UartChannel {
+    /// Number of input channels
+    pub const COUNT: usize = 4;
+
+    /// All input channels in index order
+    pub const ALL: [UartChannel; Self::COUNT] = [
+        UartChannel::Zero,
+        UartChannel::One,
+        UartChannel::I2c,
+        UartChannel::Spi,
+    ];
+
+    /// Position of this channel in per-input tables
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Most messages parsed from one `fill_buf()`, waiting to be read
+const READ_BURST: usize = 8;
+
+#[derive(Debug)]
+pub enum UartMidiError<E> {
+    /// The reader failed, e.g. a UART overrun or framing error
+    UartError(E),
+    MessageError(MidiMessageError),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UartMidiMessage {
+    // Wraps MidiMessage to record the UART channel where the message comes from
+    pub message: MidiMessage,
+    pub uart_channel: UartChannel,
+}
+
+/// MIDI UART wrapper that combines buffered UART reception with MIDI parsing
+///
+/// This struct wraps a buffered reader (a BufferedUartRx on the target) and
+/// feeds incoming bytes to a MidiParser, returning complete MIDI messages when
+/// they've been fully received.
+///
+/// The BufferedUart advantage:
+/// - Interrupt handler fills buffer in background (no CPU busy-waiting)
+/// - We can read however many bytes are available (1 to N)
+/// - Reduces risk of buffer overruns during burst MIDI traffic
+///
+/// A burst of messages in the buffer is parsed in one go and the bytes
+/// consumed at once; `read()` then hands the messages out one by one.
+pub struct MidiUart<R: BufRead> {
+    pub usart: R,
+    pub uart_channel: UartChannel,
+    parser: MidiParser,
+    /// Messages of the last burst not read yet
+    pending: Deque<MidiMessage, READ_BURST>,
+    /// Error that ended the last burst, read after its messages
+    pending_error: Option<MidiMessageError>,
+}
+
+impl<R: BufRead> MidiUart<R> {
+    /// Create a new MIDI UART reader
+    ///
+    /// # Arguments
+    /// * `usart` - Buffered reader, e.g. a BufferedUartRx (interrupt-driven receiver)
+    /// * `uart_channel` - Identifies which physical UART this is (Zero or One)
+    pub fn new(usart: R, uart_channel: UartChannel) -> Self {
+        let mut parser = MidiParser::default();
+        // The parser logs at the runtime level too
+        parser.set_log_filter(crate::log::enabled);
+
+        Self {
+            usart,
+            uart_channel,
+            parser,
+            pending: Deque::new(),
+            pending_error: None,
+        }
+    }
+
+    /// Reset the MIDI parser to clean state
+    ///
+    /// Call this after UART errors (Overrun, Framing, Break, Parity) to prevent
+    /// corrupted parser state from affecting subsequent messages.
+    pub fn reset_parser(&mut self) {
+        self.parser.reset();
+    }
+
+    /// The last unfinished message this input's parser dropped, if not taken yet
+    pub fn take_discarded(&mut self) -> Option<Discarded> {
+        self.parser.take_discarded()
+    }
+
+    /// Number of times this input's parser entered resync mode
+    pub fn resync_count(&self) -> u32 {
+        self.parser.resync_count()
+    }
+
+    /// Counters of this input's parser (see `MidiParser::stats`)
+    pub fn parser_stats(&self) -> ParserStats {
+        self.parser.stats()
+    }
+
+    /// Zero the counters of this input's parser
+    pub fn reset_parser_stats(&mut self) {
+        self.parser.reset_stats();
+    }
+
+    /// Change how this input's parser recovers from bad bytes
+    pub fn set_resync_policy(&mut self, policy: ResyncPolicy) {
+        self.parser.set_policy(policy);
+    }
+
+    /// Change what this input's parser does with 0xF9 and 0xFD
+    pub fn set_undefined_realtime(&mut self, handling: UndefinedRealtime) {
+        self.parser.set_undefined_realtime(handling);
+    }
+
+    /// Change the longest gap between the bytes of a message on this input
+    pub fn set_byte_timeout(&mut self, timeout: Duration) {
+        self.parser.set_byte_timeout(timeout);
+    }
+
+    /// Turn this input's velocity 0 Note Ons into Note Offs
+    pub fn set_normalize_note_off(&mut self, normalize: bool) {
+        self.parser.set_normalize_note_off(normalize);
+    }
+
+    /// Give this input's running status messages their status byte
+    pub fn set_expand_running_status(&mut self, expand: bool) {
+        self.parser.set_expand_running_status(expand);
+    }
+
+    /// Forget this input's running status after a silence, `None` keeps it
+    pub fn set_running_status_expiry(&mut self, expiry: Option<Duration>) {
+        self.parser.set_running_status_expiry(expiry);
+    }
+
+    /// Pass SysEx too long to capture on in chunks
+    pub fn set_sysex_streaming(&mut self, streaming: bool) {
+        self.parser.set_sysex_streaming(streaming);
+    }
+
+    /// Read the next complete MIDI message from the UART
+    ///
+    /// This method uses BufferedUartRx's fill_buf() which leverages the
+    /// interrupt-driven background buffering for efficient I/O.
+    ///
+    /// How it works:
+    /// 1. Messages left from the last burst are returned first, without
+    ///    touching the UART
+    /// 2. fill_buf() returns a slice of bytes already in the buffer
+    ///    - If buffer is empty, it waits for interrupts to fill it
+    ///    - If buffer has data, it returns immediately (no waiting!)
+    /// 3. The whole slice goes through `MidiParser::feed_bytes`, collecting
+    ///    up to `READ_BURST` messages; an error ends the burst, so the
+    ///    caller can reset the parser before it sees more bytes
+    /// 4. One consume() tells the buffer how many bytes we've processed
+    ///
+    /// Performance characteristics:
+    /// - No busy-waiting for individual bytes
+    /// - A burst of messages costs one fill_buf() and one consume()
+    /// - Interrupt handler fills buffer in background
+    /// - Approximately 30x fewer context switches than DMA single-byte reads
+    ///
+    /// Cancel-safe: it only awaits fill_buf(), and parsing and consuming
+    /// the bytes happen together after it.
+    ///
+    /// # Returns
+    /// * `Ok(UartMidiMessage)` - A complete MIDI message with channel info
+    /// * `Err(UartMidiError)` - UART error or invalid MIDI data
+    pub async fn read(&mut self) -> Result<UartMidiMessage, UartMidiError<R::Error>> {
+        loop {
+            if let Some(message) = self.pending.pop_front() {
+                return Ok(UartMidiMessage {
+                    message,
+                    uart_channel: self.uart_channel,
+                });
+            }
+            if let Some(err) = self.pending_error.take() {
+                return Err(UartMidiError::MessageError(err));
+            }
+
+            // Get a view into the buffered data without consuming it
+            let buf = self
+                .usart
+                .fill_buf()
+                .await
+                .map_err(UartMidiError::UartError)?;
+
+            // If buffer is empty, the UART is idle. Loop and wait for more data.
+            if buf.is_empty() {
+                continue;
+            }
+
+            let mut messages = self.parser.feed_bytes(buf);
+            while !self.pending.is_full() {
+                match messages.next() {
+                    // Room was checked above
+                    Some(Ok(message)) => {
+                        let _ = self.pending.push_back(message);
+                    }
+                    Some(Err(err)) => {
+                        self.pending_error = Some(err);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+            // Bytes past a full burst or an error stay in the buffer
+            let consumed = messages.consumed();
+            self.usart.consume(consumed);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! RPN and NRPN parameter changes
+//!
+//! A registered (RPN) or non-registered (NRPN) parameter is selected with
+//! two Control Changes, 101/100 or 99/98 (number MSB, then LSB), and set
+//! with Data Entry, CC 6 (value MSB) optionally followed by CC 38 (value
+//! LSB). `ParameterAssembler` follows these sequences on each MIDI channel
+//! of one input and turns them into `ParameterChange`s. It also tells when a
+//! sequence is under way, so the merger can keep other inputs from cutting
+//! into it.
+
+use crate::event::Event;
+use serde::{Deserialize, Serialize};
+
+/// Controller numbers of the parameter sequences
+const NRPN_MSB: u8 = 99;
+const NRPN_LSB: u8 = 98;
+const RPN_MSB: u8 = 101;
+const RPN_LSB: u8 = 100;
+const DATA_ENTRY_MSB: u8 = 6;
+const DATA_ENTRY_LSB: u8 = 38;
+
+/// RPN 127/127 deselects the parameter
+const RPN_NULL: u16 = 0x3FFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ParameterKind {
+    /// RPN, selected with CC 101/100
+    Registered,
+    /// NRPN, selected with CC 99/98
+    NonRegistered,
+}
+
+/// A parameter set by Data Entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParameterChange {
+    /// MIDI channel, 0-15
+    pub channel: u8,
+    pub kind: ParameterKind,
+    /// 14-bit parameter number
+    pub parameter: u16,
+    /// 14-bit value; LSB 0 until CC 38 refines it
+    pub value: u16,
+}
+
+/// Parameter selected on one MIDI channel
+#[derive(Debug, Default, Clone, Copy)]
+struct Selection {
+    kind: Option<ParameterKind>,
+    number_msb: Option<u8>,
+    number_lsb: Option<u8>,
+    value_msb: Option<u8>,
+}
+
+impl Selection {
+    fn parameter(&self) -> Option<(ParameterKind, u16)> {
+        let number = u16::from(self.number_msb?) << 7 | u16::from(self.number_lsb?);
+        match (self.kind?, number) {
+            (ParameterKind::Registered, RPN_NULL) => None,
+            (kind, number) => Some((kind, number)),
+        }
+    }
+
+    fn select(&mut self, kind: ParameterKind) {
+        if self.kind != Some(kind) {
+            *self = Self {
+                kind: Some(kind),
+                ..Self::default()
+            };
+        }
+        self.value_msb = None;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ParameterAssembler {
+    selections: [Selection; 16],
+    /// The last event started or continued a sequence that may go on
+    in_progress: bool,
+}
+
+impl ParameterAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the next event of the input
+    ///
+    /// Returns the parameter change a Data Entry completes: one for CC 6,
+    /// and again with the full value when CC 38 follows. Realtime events
+    /// are ignored.
+    pub fn feed(&mut self, event: &Event) -> Option<ParameterChange> {
+        let Event::ControlChange {
+            channel,
+            controller,
+            value,
+        } = *event
+        else {
+            if !matches!(
+                event,
+                Event::Clock
+                    | Event::Start
+                    | Event::Continue
+                    | Event::Stop
+                    | Event::ActiveSensing
+                    | Event::Reset
+            ) {
+                self.in_progress = false;
+            }
+            return None;
+        };
+        let selection = &mut self.selections[usize::from(channel)];
+        let (in_progress, value_lsb) = match controller {
+            NRPN_MSB | RPN_MSB => {
+                selection.select(kind_of(controller));
+                selection.number_msb = Some(value);
+                (true, None)
+            }
+            NRPN_LSB | RPN_LSB => {
+                selection.select(kind_of(controller));
+                selection.number_lsb = Some(value);
+                // Only the RPN null ends the selection here
+                (
+                    selection.number_msb.is_none() || selection.parameter().is_some(),
+                    None,
+                )
+            }
+            DATA_ENTRY_MSB => {
+                selection.value_msb = Some(value);
+                (selection.parameter().is_some(), Some(0))
+            }
+            DATA_ENTRY_LSB => (false, Some(value)),
+            _ => (false, None),
+        };
+        self.in_progress = in_progress;
+        let (kind, parameter) = selection.parameter()?;
+        Some(ParameterChange {
+            channel,
+            kind,
+            parameter,
+            value: u16::from(selection.value_msb?) << 7 | u16::from(value_lsb?),
+        })
+    }
+
+    /// A sequence is under way: the last event selected a parameter or set
+    /// its value MSB, so more of it may follow
+    pub fn in_progress(&self) -> bool {
+        self.in_progress
+    }
+
+    /// Forget all selections, e.g. after the input's parser was reset
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+fn kind_of(controller: u8) -> ParameterKind {
+    match controller {
+        RPN_MSB | RPN_LSB => ParameterKind::Registered,
+        _ => ParameterKind::NonRegistered,
+    }
+}
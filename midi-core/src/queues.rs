@@ -0,0 +1,232 @@
+//! Per-input queues between the read tasks and the output task
+//!
+//! Each input has its own single-producer single-consumer queue: the task
+//! reading the input pushes, the output task pops and takes the inputs in
+//! turn, so one flooding input can't hold the others back behind its
+//! backlog. A full queue makes its reader wait.
+//!
+//! While the output streams one input's SysEx (`Receivers::receive_from`)
+//! it only drains that input, so a reader waiting for room on another queue
+//! would wait until the stream ends. If that reader also serves the
+//! streaming input, as the firmware's shared UART reader does, the stream
+//! stalls with it. Readers of the other inputs therefore never wait while a
+//! stream is on: a message that doesn't fit is dropped, and the next one
+//! queued is preceded by `Queued::lost()`, so the output doesn't resolve
+//! running status across the gap.
+//!
+//! Generic over the message type; the firmware keeps the queues in statics
+//! and wraps both ends in its `queues.rs`.
+
+use crate::midi_uart::UartChannel;
+use core::sync::atomic::{AtomicU8, Ordering};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use heapless::spsc::{Consumer, Producer, Queue};
+
+/// What the queues need to know about the messages they carry
+pub trait Queued {
+    /// Realtime messages of the other inputs may go between the chunks of a
+    /// streamed SysEx
+    fn is_realtime(&self) -> bool;
+
+    /// Message telling the output that messages of `channel` were dropped
+    fn lost(channel: UartChannel) -> Self;
+}
+
+/// `Flow::streaming` when no SysEx is being streamed
+const NOT_STREAMING: u8 = u8::MAX;
+
+/// State shared by both ends of the queues
+pub struct Flow {
+    /// Something was queued on one of the inputs
+    queued: Signal<CriticalSectionRawMutex, ()>,
+    /// Room was made in an input's queue, or its reader must stop waiting
+    freed: [Signal<CriticalSectionRawMutex, ()>; UartChannel::COUNT],
+    /// Index of the input whose SysEx is being streamed
+    streaming: AtomicU8,
+}
+
+impl Flow {
+    pub const fn new() -> Self {
+        Self {
+            queued: Signal::new(),
+            freed: [const { Signal::new() }; UartChannel::COUNT],
+            streaming: AtomicU8::new(NOT_STREAMING),
+        }
+    }
+
+    /// Another input than `channel` is being streamed
+    fn streaming_other(&self, channel: UartChannel) -> bool {
+        let streaming = self.streaming.load(Ordering::Relaxed);
+        streaming != NOT_STREAMING && usize::from(streaming) != channel.index()
+    }
+}
+
+impl Default for Flow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sending end of an input's queue, owned by the task reading the input
+pub struct InputSender<'a, T, const N: usize> {
+    channel: UartChannel,
+    producer: Producer<'a, T, N>,
+    flow: &'a Flow,
+    /// Messages were dropped since the last one queued
+    lost: bool,
+}
+
+impl<T: Queued, const N: usize> InputSender<'_, T, N> {
+    /// Queue a message, waiting while the queue is full
+    ///
+    /// While another input is being streamed, a message that doesn't fit is
+    /// returned in `Err` instead.
+    pub async fn send(&mut self, message: T) -> Result<(), T> {
+        if self.lost {
+            if self.enqueue(T::lost(self.channel)).await.is_err() {
+                return Err(message);
+            }
+            self.lost = false;
+        }
+        let result = self.enqueue(message).await;
+        self.lost = result.is_err();
+        result
+    }
+
+    async fn enqueue(&mut self, mut message: T) -> Result<(), T> {
+        loop {
+            match self.producer.enqueue(message) {
+                Ok(()) => {
+                    self.flow.queued.signal(());
+                    return Ok(());
+                }
+                Err(rejected) if self.flow.streaming_other(self.channel) => return Err(rejected),
+                Err(rejected) => {
+                    message = rejected;
+                    self.flow.freed[self.channel.index()].wait().await;
+                }
+            }
+        }
+    }
+}
+
+/// Receiving end of all input queues, owned by the output task
+pub struct Receivers<'a, T, const N: usize> {
+    consumers: [Consumer<'a, T, N>; UartChannel::COUNT],
+    flow: &'a Flow,
+    /// Input to look at first next time
+    next: usize,
+}
+
+impl<T: Queued, const N: usize> Receivers<'_, T, N> {
+    /// Next message, one from each input in turn
+    ///
+    /// Ends a stream started by `receive_from`.
+    pub fn try_receive(&mut self) -> Option<T> {
+        self.end_stream();
+        for offset in 0..UartChannel::COUNT {
+            let index = (self.next + offset) % UartChannel::COUNT;
+            if let Some(message) = self.consumers[index].dequeue() {
+                self.flow.freed[index].signal(());
+                self.next = (index + 1) % UartChannel::COUNT;
+                return Some(message);
+            }
+        }
+        None
+    }
+
+    /// Let the readers of all inputs wait for room again
+    pub fn end_stream(&self) {
+        self.flow.streaming.store(NOT_STREAMING, Ordering::Relaxed);
+    }
+
+    /// Wait until a message is queued on any input
+    ///
+    /// A signal left from a message already taken returns at once, which
+    /// only costs the caller a lap.
+    pub async fn queued(&self) {
+        self.flow.queued.wait().await;
+    }
+
+    fn try_receive_from(&mut self, index: usize) -> Option<T> {
+        if let Some(message) = self.consumers[index].dequeue() {
+            self.flow.freed[index].signal(());
+            return Some(message);
+        }
+        // Realtime may go between the bytes of a SysEx, so clocks from the
+        // other inputs keep going
+        for (other, consumer) in self.consumers.iter_mut().enumerate() {
+            if consumer.peek().is_some_and(Queued::is_realtime) {
+                let message = consumer.dequeue();
+                self.flow.freed[other].signal(());
+                return message;
+            }
+        }
+        None
+    }
+
+    /// Next message of one input, or a realtime message from another
+    ///
+    /// Used while a SysEx of that input is being streamed to the output.
+    /// The rest of the other inputs waits, or is dropped when their queues
+    /// are full, until `end_stream` or `try_receive` is called.
+    pub async fn receive_from(&mut self, channel: UartChannel) -> T {
+        if self.flow.streaming.load(Ordering::Relaxed) != channel as u8 {
+            self.flow.streaming.store(channel as u8, Ordering::Relaxed);
+            // Readers waiting for room on the other inputs drop instead
+            for (index, freed) in self.flow.freed.iter().enumerate() {
+                if index != channel.index() {
+                    freed.signal(());
+                }
+            }
+        }
+        loop {
+            if let Some(message) = self.try_receive_from(channel.index()) {
+                return message;
+            }
+            self.queued().await;
+        }
+    }
+
+    /// Messages waiting in all queues
+    pub fn len(&self) -> usize {
+        self.consumers.iter().map(Consumer::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Split the queues of all inputs into their two ends
+pub fn split<'a, T, const N: usize>(
+    queues: [&'a mut Queue<T, N>; UartChannel::COUNT],
+    flow: &'a Flow,
+) -> (
+    Receivers<'a, T, N>,
+    [InputSender<'a, T, N>; UartChannel::COUNT],
+) {
+    let mut senders = [const { None }; UartChannel::COUNT];
+    let mut channels = UartChannel::ALL.into_iter();
+    let consumers = queues.map(|queue| {
+        let (producer, consumer) = queue.split();
+        // One channel for each queue
+        let channel = channels.next().unwrap_or_default();
+        senders[channel.index()] = Some(InputSender {
+            channel,
+            producer,
+            flow,
+            lost: false,
+        });
+        consumer
+    });
+    (
+        Receivers {
+            consumers,
+            flow,
+            next: 0,
+        },
+        senders.map(Option::unwrap),
+    )
+}
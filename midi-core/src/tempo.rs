@@ -0,0 +1,69 @@
+//! Tempo of an incoming MIDI Clock
+//!
+//! `TempoMeter` smooths the intervals between clocks into a tempo, for the
+//! firmware's logs and status report, and for `ClockToMtc`, which needs it to
+//! turn song positions into time.
+
+/// Microseconds of one MIDI clock at 1 BPM (24 clocks per quarter note)
+pub const CLOCK_US_AT_1_BPM: u64 = 60_000_000 / 24;
+
+/// Gap between clocks after which the clock counts as stopped
+pub const CLOCK_TIMEOUT_US: u64 = 250_000;
+
+/// Fixed point scale of the smoothed interval
+const SCALE: u64 = 16;
+
+/// Weight of a new interval in the average, as a power of two: 1/8, so the
+/// reading settles within a beat
+const SMOOTHING_SHIFT: u32 = 3;
+
+#[derive(Debug, Default)]
+pub struct TempoMeter {
+    last_clock_us: Option<u64>,
+    /// Smoothed clock interval, in 1/`SCALE` µs
+    interval: Option<u64>,
+}
+
+impl TempoMeter {
+    pub const fn new() -> Self {
+        Self {
+            last_clock_us: None,
+            interval: None,
+        }
+    }
+
+    /// Take a clock that arrived at `now_us`
+    ///
+    /// A gap of `CLOCK_TIMEOUT_US` or more is a stopped clock, not a slow
+    /// one, and doesn't count.
+    pub fn clock(&mut self, now_us: u64) {
+        if let Some(last) = self.last_clock_us {
+            let interval = now_us.saturating_sub(last);
+            if interval > 0 && interval < CLOCK_TIMEOUT_US {
+                let sample = interval * SCALE;
+                self.interval = Some(match self.interval {
+                    Some(average) => {
+                        average - (average >> SMOOTHING_SHIFT) + (sample >> SMOOTHING_SHIFT)
+                    }
+                    None => sample,
+                });
+            }
+        }
+        self.last_clock_us = Some(now_us);
+    }
+
+    /// Smoothed clock period in µs, also while the clock is stopped
+    pub fn clock_us(&self) -> Option<u64> {
+        Some(self.interval? / SCALE)
+    }
+
+    /// Current tempo in hundredths of a BPM, `None` while the clock is stopped
+    pub fn bpm_hundredths(&self, now_us: u64) -> Option<u32> {
+        let interval = self.interval?;
+        if now_us.saturating_sub(self.last_clock_us?) >= CLOCK_TIMEOUT_US {
+            return None;
+        }
+        let scaled = CLOCK_US_AT_1_BPM * 100 * SCALE;
+        Some(((scaled + interval / 2) / interval) as u32)
+    }
+}
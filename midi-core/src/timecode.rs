@@ -0,0 +1,306 @@
+//! MIDI Time Code
+//!
+//! The conversions between MTC and MIDI Clock, on top of the timecode
+//! arithmetic and quarter frame decoding of `midi_parser::timecode`:
+//! `MtcToClock` derives clock and transport from incoming quarter frames at a
+//! fixed tempo, for clock-only devices following a DAW that only sends
+//! timecode; `ClockToMtc` generates quarter frames while the clock runs, for
+//! devices that chase timecode.
+//!
+//! Times are plain microseconds, so the firmware feeds `Instant`s and the
+//! host tests feed made-up timestamps.
+
+use crate::tempo::{TempoMeter, CLOCK_TIMEOUT_US, CLOCK_US_AT_1_BPM};
+
+pub use midi_parser::timecode::{quarter_frame_data, FrameRate, QuarterFrameDecoder, Timecode};
+
+/// Gap between quarter frames after which the timecode counts as stopped
+pub const MTC_TIMEOUT_US: u64 = 100_000;
+
+/// Clock period assumed until one was measured, 120 BPM
+const DEFAULT_CLOCK_US: u64 = CLOCK_US_AT_1_BPM / 120;
+
+/// Highest song position pointer, in sixteenth notes
+const SONG_POSITION_MAX: u64 = 0x3FFF;
+
+/// What `MtcToClock` sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ClockOutput {
+    Clock,
+    Start,
+    /// Song position pointer to this sixteenth note, then Continue
+    Continue(u16),
+    Stop,
+}
+
+/// Song Position Pointer to a sixteenth note
+pub fn song_position_pointer(position: u16) -> [u8; 3] {
+    [
+        0xF2,
+        (position & 0x7F) as u8,
+        ((position >> 7) & 0x7F) as u8,
+    ]
+}
+
+/// Derives MIDI Clock and transport from MTC
+///
+/// The tempo mapping is fixed: `offset` is the timecode of the first beat,
+/// and from there the clock runs at `bpm`. When the timecode starts past the
+/// offset, or jumps, the transport stops and continues from the sixteenth
+/// note at or after the new position.
+///
+/// Clocks are scheduled between quarter frames by extrapolating from the
+/// last one, but never more than two quarter frames ahead of it, so a
+/// stopping DAW is followed within one clock. Quarter frames that stop for
+/// `MTC_TIMEOUT_US` stop the transport.
+#[derive(Debug)]
+pub struct MtcToClock {
+    decoder: QuarterFrameDecoder,
+    bpm: u16,
+    offset: Timecode,
+    /// Timecode position and arrival time of the last quarter frame, in µs
+    anchor: Option<(u64, u64)>,
+    /// Number of the next clock, counted from the offset
+    next_clock: u64,
+    started: bool,
+}
+
+impl MtcToClock {
+    /// # Arguments
+    /// * `bpm` - Tempo of the clock, at least 1
+    /// * `offset` - Timecode of the first beat
+    pub fn new(bpm: u16, offset: Timecode) -> Self {
+        Self {
+            decoder: QuarterFrameDecoder::default(),
+            bpm: bpm.max(1),
+            offset,
+            anchor: None,
+            next_clock: 0,
+            started: false,
+        }
+    }
+
+    fn offset_us(&self) -> u64 {
+        let rate = self.decoder.rate();
+        rate.quarter_frames_to_us(self.offset.to_frames(rate) * 4)
+    }
+
+    /// Timecode position of a clock
+    fn clock_position_us(&self, clock: u64) -> u64 {
+        self.offset_us() + clock * CLOCK_US_AT_1_BPM / self.bpm as u64
+    }
+
+    /// When the next clock is due, if it's within reach of the timecode
+    fn clock_due_us(&self) -> Option<u64> {
+        let (position_us, at_us) = self.anchor?;
+        let due = at_us
+            + self
+                .clock_position_us(self.next_clock)
+                .saturating_sub(position_us);
+        let reach = self.decoder.rate().quarter_frames_to_us(2);
+        (due <= at_us + reach).then_some(due)
+    }
+
+    /// Take the data byte of a quarter frame that arrived at `now_us`
+    pub fn quarter_frame(&mut self, data: u8, now_us: u64) -> Option<ClockOutput> {
+        let previous = self.decoder.position();
+        let position = self.decoder.feed(data)?;
+        let position_us = self.decoder.rate().quarter_frames_to_us(position);
+        self.anchor = Some((position_us, now_us));
+
+        if self.started {
+            if previous.map(|previous| previous + 1) == Some(position) {
+                return None;
+            }
+            // Jumped, stop now and continue from the new position with the
+            // next quarter frame
+            self.started = false;
+            return Some(ClockOutput::Stop);
+        }
+
+        let offset_us = self.offset_us();
+        if position_us < offset_us {
+            return None;
+        }
+        let beat_us = position_us - offset_us;
+        let clocks = (beat_us * self.bpm as u64).div_ceil(CLOCK_US_AT_1_BPM);
+        self.started = true;
+        if clocks == 0 {
+            self.next_clock = 0;
+            Some(ClockOutput::Start)
+        } else {
+            let sixteenths = clocks.div_ceil(6).min(SONG_POSITION_MAX);
+            self.next_clock = sixteenths * 6;
+            Some(ClockOutput::Continue(sixteenths as u16))
+        }
+    }
+
+    /// When `poll` next needs to run, if anything is pending
+    pub fn deadline_us(&self) -> Option<u64> {
+        let (_, at_us) = self.anchor?;
+        let timeout = at_us + MTC_TIMEOUT_US;
+        match self.clock_due_us() {
+            Some(due) if self.started => Some(due.min(timeout)),
+            _ => Some(timeout),
+        }
+    }
+
+    /// Return what is due at `now_us`, call until it returns `None`
+    pub fn poll(&mut self, now_us: u64) -> Option<ClockOutput> {
+        let (_, at_us) = self.anchor?;
+        if now_us >= at_us + MTC_TIMEOUT_US {
+            self.anchor = None;
+            self.decoder.reset();
+            let started = core::mem::replace(&mut self.started, false);
+            return started.then_some(ClockOutput::Stop);
+        }
+        if self.started && self.clock_due_us().is_some_and(|due| due <= now_us) {
+            self.next_clock += 1;
+            return Some(ClockOutput::Clock);
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transport {
+    Stopped,
+    /// Start or Continue received, runs from the next clock
+    Armed,
+    /// Timecode position and time of the clock it started on, in µs
+    Running {
+        anchor_us: u64,
+        at_us: u64,
+    },
+}
+
+/// Generates MTC quarter frames from the running clock
+///
+/// The timecode starts at `offset` on the first clock after Start and runs in
+/// real time while clocks keep coming. Stop, or clocks pausing for
+/// `CLOCK_TIMEOUT_US`, hold it; Continue resumes it on the next clock.
+/// A Song Position Pointer locates it using the measured clock tempo.
+///
+/// Every run starts with piece 0 on the next even frame, so a chasing device
+/// locks within two frames.
+#[derive(Debug)]
+pub struct ClockToMtc {
+    rate: FrameRate,
+    offset: Timecode,
+    transport: Transport,
+    /// Position from the offset while not running, in µs
+    position_us: u64,
+    tempo: TempoMeter,
+    last_clock_us: Option<u64>,
+    /// Next quarter frame, counted from 00:00:00:00
+    next_quarter: u64,
+}
+
+impl ClockToMtc {
+    pub fn new(rate: FrameRate, offset: Timecode) -> Self {
+        Self {
+            rate,
+            offset,
+            transport: Transport::Stopped,
+            position_us: 0,
+            tempo: TempoMeter::default(),
+            last_clock_us: None,
+            next_quarter: 0,
+        }
+    }
+
+    fn offset_us(&self) -> u64 {
+        self.rate
+            .quarter_frames_to_us(self.offset.to_frames(self.rate) * 4)
+    }
+
+    /// Current timecode position from the offset
+    fn position_at(&self, now_us: u64) -> u64 {
+        match self.transport {
+            Transport::Running { anchor_us, at_us } => {
+                anchor_us + now_us.saturating_sub(at_us) - self.offset_us()
+            }
+            _ => self.position_us,
+        }
+    }
+
+    fn quarter_due_us(&self) -> Option<u64> {
+        let Transport::Running { anchor_us, at_us } = self.transport else {
+            return None;
+        };
+        Some(at_us + self.rate.quarter_frames_to_us(self.next_quarter) - anchor_us)
+    }
+
+    /// Take a message from the clock source: Clock, Start, Continue, Stop
+    /// or Song Position Pointer; others are ignored
+    pub fn message(&mut self, bytes: &[u8], now_us: u64) {
+        match *bytes {
+            [0xF8] => self.clock(now_us),
+            [0xFA] => {
+                self.position_us = 0;
+                self.transport = Transport::Armed;
+            }
+            [0xFB] if !matches!(self.transport, Transport::Running { .. }) => {
+                self.transport = Transport::Armed;
+            }
+            [0xFC] => {
+                self.position_us = self.position_at(now_us);
+                self.transport = Transport::Stopped;
+            }
+            [0xF2, lsb, msb] if !matches!(self.transport, Transport::Running { .. }) => {
+                let sixteenths = (msb as u64) << 7 | lsb as u64;
+                let clock_us = self.tempo.clock_us().unwrap_or(DEFAULT_CLOCK_US);
+                self.position_us = sixteenths * 6 * clock_us;
+            }
+            _ => {}
+        }
+    }
+
+    fn clock(&mut self, now_us: u64) {
+        self.tempo.clock(now_us);
+        self.last_clock_us = Some(now_us);
+
+        if self.transport == Transport::Armed {
+            let anchor_us = self.offset_us() + self.position_us;
+            let mut quarter = self.rate.us_to_quarter_frames(anchor_us);
+            if self.rate.quarter_frames_to_us(quarter) < anchor_us {
+                quarter += 1;
+            }
+            self.next_quarter = quarter.next_multiple_of(8);
+            self.transport = Transport::Running {
+                anchor_us,
+                at_us: now_us,
+            };
+        }
+    }
+
+    /// When `poll` next needs to run, if the timecode is running
+    pub fn deadline_us(&self) -> Option<u64> {
+        let due = self.quarter_due_us()?;
+        let timeout = self.last_clock_us? + CLOCK_TIMEOUT_US;
+        Some(due.min(timeout))
+    }
+
+    /// Return the data byte of the quarter frame due at `now_us`, call until
+    /// it returns `None`
+    pub fn poll(&mut self, now_us: u64) -> Option<u8> {
+        let last_clock_us = self.last_clock_us?;
+        if now_us >= last_clock_us + CLOCK_TIMEOUT_US {
+            if let Transport::Running { .. } = self.transport {
+                // Paused, resume on the next clock
+                self.position_us = self.position_at(last_clock_us);
+                self.transport = Transport::Armed;
+            }
+            return None;
+        }
+        if self.quarter_due_us()? > now_us {
+            return None;
+        }
+        let quarter = self.next_quarter;
+        let piece = (quarter % 8) as u8;
+        let timecode = Timecode::from_frames((quarter - piece as u64) / 4, self.rate);
+        self.next_quarter += 1;
+        Some(quarter_frame_data(timecode, self.rate, piece))
+    }
+}
@@ -0,0 +1,249 @@
+//! `MidiUart` driven by an in-memory reader
+//!
+//! The reader hands out scripted chunks one `fill_buf()` at a time, the way a
+//! buffered UART returns whatever arrived since the last read, and can fail
+//! in between like a UART reporting an overrun.
+
+use embassy_futures::block_on;
+use embedded_io_async::{BufRead, ErrorKind, ErrorType};
+use midi_core::midi_uart::{MidiUart, UartChannel, UartMidiError, UartMidiMessage};
+use midi_core::parser::{MidiMessage, MidiMessageError};
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FakeError {
+    /// Injected by the script
+    Overrun,
+    /// The script ran out, a real UART would wait instead
+    EndOfScript,
+}
+
+impl embedded_io_async::Error for FakeError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+enum Step {
+    Bytes(Vec<u8>),
+    Error(FakeError),
+}
+
+struct FakeReader {
+    steps: VecDeque<Step>,
+}
+
+impl FakeReader {
+    fn new(steps: impl IntoIterator<Item = Step>) -> Self {
+        Self {
+            steps: steps.into_iter().collect(),
+        }
+    }
+
+    /// Bytes that haven't been consumed yet
+    fn remaining(&self) -> usize {
+        self.steps
+            .iter()
+            .map(|step| match step {
+                Step::Bytes(bytes) => bytes.len(),
+                Step::Error(_) => 0,
+            })
+            .sum()
+    }
+}
+
+impl ErrorType for FakeReader {
+    type Error = FakeError;
+}
+
+impl BufRead for FakeReader {
+    async fn fill_buf(&mut self) -> Result<&[u8], FakeError> {
+        // Drop chunks that were consumed completely
+        while let Some(Step::Bytes(bytes)) = self.steps.front() {
+            if !bytes.is_empty() {
+                break;
+            }
+            self.steps.pop_front();
+        }
+        if let Some(Step::Error(_)) = self.steps.front() {
+            let Some(Step::Error(error)) = self.steps.pop_front() else {
+                unreachable!()
+            };
+            return Err(error);
+        }
+        match self.steps.front() {
+            Some(Step::Bytes(bytes)) => Ok(bytes),
+            _ => Err(FakeError::EndOfScript),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let Some(Step::Bytes(bytes)) = self.steps.front_mut() else {
+            panic!("consume() without fill_buf()");
+        };
+        bytes.drain(..amt);
+    }
+}
+
+fn uart(steps: impl IntoIterator<Item = Step>) -> MidiUart<FakeReader> {
+    MidiUart::new(FakeReader::new(steps), UartChannel::One)
+}
+
+fn bytes(bytes: &[u8]) -> Step {
+    Step::Bytes(bytes.to_vec())
+}
+
+fn read(uart: &mut MidiUart<FakeReader>) -> Result<UartMidiMessage, UartMidiError<FakeError>> {
+    block_on(uart.read())
+}
+
+/// Read the next message and return its bytes
+fn read_bytes(uart: &mut MidiUart<FakeReader>) -> Vec<u8> {
+    match read(uart) {
+        Ok(message) => {
+            assert_eq!(message.uart_channel, UartChannel::One);
+            message.message.bytes().to_vec()
+        }
+        Err(err) => panic!("expected a message, got {:?}", err),
+    }
+}
+
+fn assert_end(uart: &mut MidiUart<FakeReader>) {
+    assert!(matches!(
+        read(uart),
+        Err(UartMidiError::UartError(FakeError::EndOfScript))
+    ));
+}
+
+#[test]
+fn whole_messages() {
+    let mut uart = uart([bytes(&[0x90, 0x3C, 0x64]), bytes(&[0xB0, 0x07, 0x7F])]);
+    assert_eq!(read_bytes(&mut uart), [0x90, 0x3C, 0x64]);
+    assert_eq!(read_bytes(&mut uart), [0xB0, 0x07, 0x7F]);
+    assert_end(&mut uart);
+}
+
+#[test]
+fn message_split_across_reads() {
+    let mut uart = uart([bytes(&[0x90]), bytes(&[0x3C]), bytes(&[0x64, 0x80])]);
+    assert_eq!(read_bytes(&mut uart), [0x90, 0x3C, 0x64]);
+    // The next status byte is parsed along with it, the parser holds it
+    assert_eq!(uart.usart.remaining(), 0);
+}
+
+#[test]
+fn several_messages_in_one_read() {
+    let mut uart = uart([bytes(&[0x90, 0x3C, 0x64, 0x3E, 0x64, 0xF8, 0xC0, 0x05])]);
+    assert_eq!(read_bytes(&mut uart), [0x90, 0x3C, 0x64]);
+    // The whole burst is consumed at once
+    assert_eq!(uart.usart.remaining(), 0);
+    // Running status comes out data-only, the merger injects the status
+    assert_eq!(read_bytes(&mut uart), [0x3E, 0x64]);
+    assert_eq!(read_bytes(&mut uart), [0xF8]);
+    assert_eq!(read_bytes(&mut uart), [0xC0, 0x05]);
+    assert_end(&mut uart);
+}
+
+#[test]
+fn running_status_message_type() {
+    let mut uart = uart([bytes(&[0x90, 0x3C, 0x64, 0x3C, 0x00])]);
+    read_bytes(&mut uart);
+    let message = read(&mut uart).unwrap().message;
+    assert!(matches!(message, MidiMessage::RunningStatus(_)));
+}
+
+#[test]
+fn realtime_inside_a_message() {
+    let mut uart = uart([bytes(&[0x90, 0x3C]), bytes(&[0xF8, 0x64])]);
+    assert_eq!(read_bytes(&mut uart), [0xF8]);
+    assert_eq!(read_bytes(&mut uart), [0x90, 0x3C, 0x64]);
+}
+
+#[test]
+fn reader_error() {
+    let mut uart = uart([
+        bytes(&[0x90, 0x3C, 0x64]),
+        Step::Error(FakeError::Overrun),
+        bytes(&[0x80, 0x3C, 0x00]),
+    ]);
+    assert_eq!(read_bytes(&mut uart), [0x90, 0x3C, 0x64]);
+    assert!(matches!(
+        read(&mut uart),
+        Err(UartMidiError::UartError(FakeError::Overrun))
+    ));
+    assert_eq!(read_bytes(&mut uart), [0x80, 0x3C, 0x00]);
+    assert_end(&mut uart);
+}
+
+#[test]
+fn reader_error_mid_message() {
+    // The bytes after the error belong to a message whose start was lost
+    let mut uart = uart([
+        bytes(&[0x90, 0x3C]),
+        Step::Error(FakeError::Overrun),
+        bytes(&[0x64, 0x90, 0x3E, 0x64]),
+    ]);
+    assert!(matches!(
+        read(&mut uart),
+        Err(UartMidiError::UartError(FakeError::Overrun))
+    ));
+    // As the firmware does after an error: the parser skips the stray data
+    // byte while it hunts for the next status byte
+    uart.reset_parser();
+    assert_eq!(read_bytes(&mut uart), [0x90, 0x3E, 0x64]);
+    assert_eq!(uart.resync_count(), 1);
+}
+
+#[test]
+fn parse_error_and_recovery() {
+    let mut uart = uart([
+        bytes(&[0x90, 0x3C, 0x91, 0x40, 0x64]),
+        bytes(&[0x90, 0x3C, 0x64]),
+    ]);
+    assert!(matches!(
+        read(&mut uart),
+        Err(UartMidiError::MessageError(
+            MidiMessageError::DuplicateStatus
+        ))
+    ));
+    // The error consumes up to and including the offending byte
+    assert_eq!(uart.usart.remaining(), 5);
+    assert_eq!(uart.resync_count(), 1);
+    // Already resyncing, the reset isn't counted again
+    uart.reset_parser();
+    assert_eq!(uart.resync_count(), 1);
+    // The data bytes of the broken message are skipped
+    assert_eq!(read_bytes(&mut uart), [0x90, 0x3C, 0x64]);
+    assert_end(&mut uart);
+}
+
+#[test]
+fn long_burst_is_read_in_parts() {
+    // Twenty clocks, more than one read takes from the buffer
+    let mut uart = uart([bytes(&[0xF8; 20])]);
+    assert_eq!(read_bytes(&mut uart), [0xF8]);
+    let remaining = uart.usart.remaining();
+    assert!(remaining > 0 && remaining < 20);
+    for _ in 1..20 {
+        assert_eq!(read_bytes(&mut uart), [0xF8]);
+    }
+    assert_end(&mut uart);
+}
+
+#[test]
+fn error_after_messages_of_a_burst() {
+    let mut uart = uart([bytes(&[0xF8, 0x90, 0x3C, 0x64, 0xF4, 0x92, 0x3C, 0x64])]);
+    assert_eq!(read_bytes(&mut uart), [0xF8]);
+    assert_eq!(read_bytes(&mut uart), [0x90, 0x3C, 0x64]);
+    assert!(matches!(
+        read(&mut uart),
+        Err(UartMidiError::MessageError(
+            MidiMessageError::InvalidStatusByte
+        ))
+    ));
+    // The bytes after the error wait for the reset
+    assert_eq!(uart.usart.remaining(), 3);
+    uart.reset_parser();
+    assert_eq!(read_bytes(&mut uart), [0x92, 0x3C, 0x64]);
+    assert_end(&mut uart);
+}
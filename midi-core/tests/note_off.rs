@@ -0,0 +1,25 @@
+//! Note Offs normalized by the parser, through the merger (the parser's own
+//! tests are in `midi-parser/tests/note_off.rs`)
+
+use midi_core::merge::Merger;
+use midi_core::parser::MidiParser;
+
+#[test]
+fn merged_output_keeps_the_notes() {
+    // Through the merger, running status resolves to the same notes
+    let mut parser = MidiParser::default();
+    parser.set_normalize_note_off(true);
+    let mut merger = Merger::<1>::default();
+    let mut out = Vec::new();
+    for byte in [0x90, 0x3C, 0x64, 0x3C, 0x00, 0x3E, 0x64, 0x3E, 0x00] {
+        let Some(message) = parser.feed_byte(byte).unwrap() else {
+            continue;
+        };
+        out.extend_from_slice(&merger.prepare(0, &message).unwrap());
+        merger.sent(0, &message);
+    }
+    assert_eq!(
+        out,
+        [0x90, 0x3C, 0x64, 0x80, 0x3C, 0x00, 0x90, 0x3E, 0x64, 0x80, 0x3E, 0x00]
+    );
+}
@@ -0,0 +1,118 @@
+//! RPN and NRPN sequences assembled into parameter changes
+
+use midi_core::event::Event;
+use midi_core::parameter::{ParameterAssembler, ParameterChange, ParameterKind};
+
+fn cc(channel: u8, controller: u8, value: u8) -> Event {
+    Event::ControlChange {
+        channel,
+        controller,
+        value,
+    }
+}
+
+/// Changes completed by a sequence of CCs on channel 0
+fn changes(assembler: &mut ParameterAssembler, ccs: &[(u8, u8)]) -> Vec<ParameterChange> {
+    ccs.iter()
+        .filter_map(|&(controller, value)| assembler.feed(&cc(0, controller, value)))
+        .collect()
+}
+
+fn nrpn(parameter: u16, value: u16) -> ParameterChange {
+    ParameterChange {
+        channel: 0,
+        kind: ParameterKind::NonRegistered,
+        parameter,
+        value,
+    }
+}
+
+#[test]
+fn nrpn_with_fine_value() {
+    let mut assembler = ParameterAssembler::new();
+    assert_eq!(
+        changes(
+            &mut assembler,
+            &[(99, 0x01), (98, 0x02), (6, 0x40), (38, 0x10)]
+        ),
+        [nrpn(0x82, 0x40 << 7), nrpn(0x82, 0x40 << 7 | 0x10)]
+    );
+}
+
+#[test]
+fn rpn_pitch_bend_range() {
+    let mut assembler = ParameterAssembler::new();
+    assert_eq!(
+        changes(&mut assembler, &[(101, 0), (100, 0), (6, 12)]),
+        [ParameterChange {
+            channel: 0,
+            kind: ParameterKind::Registered,
+            parameter: 0,
+            value: 12 << 7,
+        }]
+    );
+}
+
+#[test]
+fn data_entry_without_a_parameter_is_ignored() {
+    let mut assembler = ParameterAssembler::new();
+    assert_eq!(changes(&mut assembler, &[(6, 0x40), (38, 0x10)]), []);
+    // RPN null deselects
+    assert_eq!(
+        changes(
+            &mut assembler,
+            &[(101, 0), (100, 0), (101, 127), (100, 127), (6, 1)]
+        ),
+        []
+    );
+}
+
+#[test]
+fn selection_stays_for_later_data_entry() {
+    let mut assembler = ParameterAssembler::new();
+    changes(&mut assembler, &[(99, 0), (98, 5), (6, 1)]);
+    assert_eq!(
+        changes(&mut assembler, &[(7, 100), (6, 2)]),
+        [nrpn(5, 2 << 7)]
+    );
+}
+
+#[test]
+fn channels_are_separate() {
+    let mut assembler = ParameterAssembler::new();
+    assembler.feed(&cc(0, 99, 0));
+    assembler.feed(&cc(0, 98, 1));
+    assert_eq!(assembler.feed(&cc(1, 6, 0x40)), None);
+    assert_eq!(assembler.feed(&cc(0, 6, 0x40)), Some(nrpn(1, 0x40 << 7)));
+}
+
+#[test]
+fn in_progress_until_the_sequence_ends() {
+    let mut assembler = ParameterAssembler::new();
+    let mut steps = Vec::new();
+    for (controller, value) in [(99, 0), (98, 1), (6, 0x40), (38, 0)] {
+        assembler.feed(&cc(0, controller, value));
+        steps.push(assembler.in_progress());
+    }
+    assert_eq!(steps, [true, true, true, false]);
+
+    // Realtime goes between the parts, anything else ends the sequence
+    assembler.feed(&cc(0, 99, 0));
+    assembler.feed(&Event::Clock);
+    assert!(assembler.in_progress());
+    assembler.feed(&Event::NoteOn {
+        channel: 0,
+        note: 60,
+        velocity: 100,
+    });
+    assert!(!assembler.in_progress());
+}
+
+#[test]
+fn reset_forgets_the_selection() {
+    let mut assembler = ParameterAssembler::new();
+    changes(&mut assembler, &[(99, 0), (98, 1)]);
+    assembler.reset();
+    assert!(!assembler.in_progress());
+    assert_eq!(changes(&mut assembler, &[(6, 1)]), []);
+}
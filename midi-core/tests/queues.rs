@@ -0,0 +1,185 @@
+//! Per-input queues, with one task reading two inputs like the firmware's
+//! shared UART reader
+//!
+//! The tasks are polled a bounded number of times: nothing here waits for
+//! time, so tasks that stop making progress fail the test instead of
+//! hanging it.
+
+use embassy_futures::{block_on, join::join};
+use heapless::spsc::Queue;
+use midi_core::midi_uart::UartChannel;
+use midi_core::queues::{self, Flow, Queued};
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Message {
+    /// Piece of a streamed SysEx, numbered, the last one closes it
+    Chunk(u16, bool),
+    Note(u16),
+    Clock,
+    Lost(UartChannel),
+}
+
+impl Queued for Message {
+    fn is_realtime(&self) -> bool {
+        *self == Message::Clock
+    }
+
+    fn lost(channel: UartChannel) -> Self {
+        Message::Lost(channel)
+    }
+}
+
+/// Four messages per input
+const LEN: usize = 5;
+
+fn queues() -> [Queue<Message, LEN>; UartChannel::COUNT] {
+    core::array::from_fn(|_| Queue::new())
+}
+
+/// Poll the tasks until they are done, `None` if they stall
+fn run<F: Future>(future: F) -> Option<F::Output> {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    (0..10_000).find_map(|_| match future.as_mut().poll(&mut context) {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    })
+}
+
+#[test]
+fn long_sysex_while_another_input_floods() {
+    const CHUNKS: u16 = 200;
+    let mut storage = queues();
+    let flow = Flow::new();
+    let [a, b, c, d] = storage.each_mut();
+    let (mut receivers, [mut input0, mut input1, _, _]) = queues::split([a, b, c, d], &flow);
+
+    let mut dropped = 0;
+    let reader = async {
+        for chunk in 0..CHUNKS {
+            let _ = input0
+                .send(Message::Chunk(chunk, chunk == CHUNKS - 1))
+                .await;
+            for note in 0..3 {
+                if input1.send(Message::Note(3 * chunk + note)).await.is_err() {
+                    dropped += 1;
+                }
+            }
+        }
+    };
+    let writer = async {
+        // The first chunk starts the stream
+        let first = loop {
+            match receivers.try_receive() {
+                Some(message @ Message::Chunk(..)) => break message,
+                Some(_) => {}
+                None => receivers.queued().await,
+            }
+        };
+        let mut chunks = vec![first];
+        while !matches!(chunks.last(), Some(Message::Chunk(_, true))) {
+            chunks.push(receivers.receive_from(UartChannel::Zero).await);
+        }
+        chunks
+    };
+    let (_, chunks) = run(join(reader, writer)).expect("the stream stalled behind the other input");
+
+    let expected: Vec<_> = (0..CHUNKS)
+        .map(|chunk| Message::Chunk(chunk, chunk == CHUNKS - 1))
+        .collect();
+    assert_eq!(chunks, expected);
+    assert!(dropped > 0);
+
+    // The other input's running status is invalidated before its next message
+    receivers.end_stream();
+    while receivers.try_receive().is_some() {}
+    block_on(input1.send(Message::Note(1000))).unwrap();
+    assert_eq!(
+        receivers.try_receive(),
+        Some(Message::Lost(UartChannel::One))
+    );
+    assert_eq!(receivers.try_receive(), Some(Message::Note(1000)));
+}
+
+#[test]
+fn full_queue_waits_without_a_stream() {
+    let mut storage = queues();
+    let flow = Flow::new();
+    let [a, b, c, d] = storage.each_mut();
+    let (mut receivers, [_, mut input1, _, _]) = queues::split([a, b, c, d], &flow);
+
+    let reader = async {
+        for note in 0..20 {
+            input1.send(Message::Note(note)).await.unwrap();
+        }
+    };
+    let writer = async {
+        let mut received = Vec::new();
+        while received.len() < 20 {
+            match receivers.try_receive() {
+                Some(message) => received.push(message),
+                None => receivers.queued().await,
+            }
+        }
+        received
+    };
+    let (_, received) = run(join(reader, writer)).unwrap();
+    let expected: Vec<_> = (0..20).map(Message::Note).collect();
+    assert_eq!(received, expected);
+}
+
+#[test]
+fn realtime_passes_the_stream() {
+    let mut storage = queues();
+    let flow = Flow::new();
+    let [a, b, c, d] = storage.each_mut();
+    let (mut receivers, [mut input0, mut input1, _, _]) = queues::split([a, b, c, d], &flow);
+
+    block_on(async {
+        input1.send(Message::Clock).await.unwrap();
+        input1.send(Message::Note(0)).await.unwrap();
+        input0.send(Message::Chunk(0, false)).await.unwrap();
+    });
+    let received = run(async {
+        [
+            receivers.receive_from(UartChannel::Zero).await,
+            receivers.receive_from(UartChannel::Zero).await,
+        ]
+    })
+    .unwrap();
+    assert_eq!(received, [Message::Chunk(0, false), Message::Clock]);
+    // The note waits for the end of the stream
+    assert_eq!(receivers.len(), 1);
+}
+
+#[test]
+fn streaming_input_itself_waits() {
+    let mut storage = queues();
+    let flow = Flow::new();
+    let [a, b, c, d] = storage.each_mut();
+    let (mut receivers, [mut input0, _, _, _]) = queues::split([a, b, c, d], &flow);
+
+    let reader = async {
+        for chunk in 0..20 {
+            input0
+                .send(Message::Chunk(chunk, chunk == 19))
+                .await
+                .unwrap();
+        }
+    };
+    let writer = async {
+        let mut chunks = 0;
+        while !matches!(
+            receivers.receive_from(UartChannel::Zero).await,
+            Message::Chunk(_, true)
+        ) {
+            chunks += 1;
+        }
+        chunks + 1
+    };
+    let (_, chunks) = run(join(reader, writer)).unwrap();
+    assert_eq!(chunks, 20);
+}
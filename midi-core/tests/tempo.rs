@@ -0,0 +1,71 @@
+//! Tempo measurement from MIDI Clock
+
+use midi_core::tempo::{TempoMeter, CLOCK_TIMEOUT_US};
+
+/// Feed `count` clocks from `start_us`, each `interval_us` apart, and return
+/// the time of the last
+fn feed(meter: &mut TempoMeter, start_us: u64, interval_us: u64, count: u64) -> u64 {
+    let mut time_us = start_us;
+    for i in 0..count {
+        time_us = start_us + i * interval_us;
+        meter.clock(time_us);
+    }
+    time_us
+}
+
+#[test]
+fn steady_clock_reads_its_tempo() {
+    let mut meter = TempoMeter::default();
+    // 120 BPM: 20833.33 µs per clock
+    let mut time_us = 0;
+    for i in 0..96u64 {
+        time_us = i * 62_500 / 3;
+        meter.clock(time_us);
+    }
+    assert_eq!(meter.bpm_hundredths(time_us), Some(12_000));
+}
+
+#[test]
+fn jitter_is_smoothed() {
+    let mut meter = TempoMeter::default();
+    let mut time_us = 0;
+    // 125 BPM (20000 µs) with ±1 ms of jitter on alternate clocks
+    for i in 0..96u64 {
+        time_us = i * 20_000 + if i % 2 == 0 { 0 } else { 1_000 };
+        meter.clock(time_us);
+    }
+    let bpm = meter.bpm_hundredths(time_us).unwrap();
+    assert!((12_300..=12_700).contains(&bpm), "{bpm}");
+}
+
+#[test]
+fn follows_a_tempo_change_within_a_couple_of_beats() {
+    let mut meter = TempoMeter::default();
+    let time_us = feed(&mut meter, 0, 25_000, 48);
+    assert_eq!(meter.bpm_hundredths(time_us), Some(10_000));
+    // Two beats at 125 BPM, within 0.1 BPM
+    let time_us = feed(&mut meter, time_us + 20_000, 20_000, 48);
+    let bpm = meter.bpm_hundredths(time_us).unwrap();
+    assert!((12_490..=12_510).contains(&bpm), "{bpm}");
+}
+
+#[test]
+fn no_tempo_without_a_running_clock() {
+    let mut meter = TempoMeter::default();
+    assert_eq!(meter.bpm_hundredths(0), None);
+    meter.clock(0);
+    assert_eq!(meter.bpm_hundredths(0), None);
+    let time_us = feed(&mut meter, 20_000, 20_000, 24);
+    assert!(meter.bpm_hundredths(time_us).is_some());
+    assert_eq!(meter.bpm_hundredths(time_us + CLOCK_TIMEOUT_US), None);
+    // The period is kept for locating while stopped
+    assert_eq!(meter.clock_us(), Some(20_000));
+}
+
+#[test]
+fn a_pause_does_not_count_as_a_slow_clock() {
+    let mut meter = TempoMeter::default();
+    let time_us = feed(&mut meter, 0, 20_000, 24);
+    let time_us = feed(&mut meter, time_us + 2_000_000, 20_000, 2);
+    assert_eq!(meter.bpm_hundredths(time_us), Some(12_500));
+}
@@ -0,0 +1,314 @@
+//! Timecode arithmetic, quarter frame decoding and the MTC/clock conversions
+
+use midi_core::tempo::CLOCK_TIMEOUT_US;
+use midi_core::timecode::{
+    quarter_frame_data, ClockOutput, ClockToMtc, FrameRate, MtcToClock, QuarterFrameDecoder,
+    Timecode, MTC_TIMEOUT_US,
+};
+
+/// Quarter frames running from `start`, timestamped at their timecode
+/// position in µs, as a DAW playing from `start` sends them
+fn stream(start: Timecode, rate: FrameRate, count: u64) -> Vec<(u64, u8)> {
+    let start_quarters = start.to_frames(rate) * 4;
+    (0..count)
+        .map(|i| {
+            // Each cycle of eight carries the frame at its piece 0
+            let timecode = Timecode::from_frames(start.to_frames(rate) + i / 8 * 2, rate);
+            let time_us = rate.quarter_frames_to_us(start_quarters + i);
+            (time_us, quarter_frame_data(timecode, rate, (i % 8) as u8))
+        })
+        .collect()
+}
+
+/// Everything due up to `until_us`, at the time it's due
+fn drain(converter: &mut MtcToClock, until_us: u64, out: &mut Vec<(u64, ClockOutput)>) {
+    while let Some(deadline) = converter.deadline_us() {
+        if deadline > until_us {
+            break;
+        }
+        match converter.poll(deadline) {
+            Some(output) => out.push((deadline, output)),
+            None => break,
+        }
+    }
+}
+
+fn run(converter: &mut MtcToClock, frames: &[(u64, u8)], until_us: u64) -> Vec<(u64, ClockOutput)> {
+    let mut out = Vec::new();
+    for &(time_us, data) in frames {
+        drain(converter, time_us, &mut out);
+        if let Some(output) = converter.quarter_frame(data, time_us) {
+            out.push((time_us, output));
+        }
+    }
+    drain(converter, until_us, &mut out);
+    out
+}
+
+fn clock_us(offset_us: u64, clock: u64, bpm: u64) -> u64 {
+    offset_us + clock * 2_500_000 / bpm
+}
+
+#[test]
+fn drop_frame_numbering_round_trips() {
+    let rate = FrameRate::Fps30Drop;
+    // 00:01:00;00 and ;01 don't exist
+    assert_eq!(Timecode::new(0, 1, 0, 2).to_frames(rate), 1800);
+    assert_eq!(Timecode::from_frames(1800, rate), Timecode::new(0, 1, 0, 2));
+    // Every tenth minute keeps them
+    assert_eq!(
+        Timecode::from_frames(17982, rate),
+        Timecode::new(0, 10, 0, 0)
+    );
+    for frames in 0..40_000 {
+        let timecode = Timecode::from_frames(frames, rate);
+        assert_eq!(timecode.to_frames(rate), frames, "{timecode:?}");
+    }
+}
+
+#[test]
+fn non_drop_rates_round_trip() {
+    for rate in [FrameRate::Fps24, FrameRate::Fps25, FrameRate::Fps30] {
+        let timecode = Timecode::new(1, 2, 3, 4);
+        assert_eq!(
+            Timecode::from_frames(timecode.to_frames(rate), rate),
+            timecode
+        );
+        assert_eq!(FrameRate::from_code(rate.code()), rate);
+    }
+}
+
+#[test]
+fn decoder_locks_after_a_full_cycle() {
+    let rate = FrameRate::Fps25;
+    let start = Timecode::new(1, 0, 0, 0);
+    let mut decoder = QuarterFrameDecoder::default();
+    let positions: Vec<_> = stream(start, rate, 10)
+        .into_iter()
+        .map(|(_, data)| decoder.feed(data))
+        .collect();
+    let locked = start.to_frames(rate) * 4 + 7;
+    assert!(positions[..7].iter().all(Option::is_none));
+    assert_eq!(
+        positions[7..],
+        [Some(locked), Some(locked + 1), Some(locked + 2)]
+    );
+    assert_eq!(decoder.rate(), rate);
+}
+
+#[test]
+fn decoder_drops_the_lock_on_a_missing_piece() {
+    let rate = FrameRate::Fps30;
+    let frames = stream(Timecode::new(0, 0, 5, 0), rate, 24);
+    let mut decoder = QuarterFrameDecoder::default();
+    for (_, data) in &frames[..12] {
+        decoder.feed(*data);
+    }
+    assert!(decoder.position().is_some());
+    // Piece 4 of the second cycle is lost
+    assert_eq!(decoder.feed(frames[13].1), None);
+    // Relocked at the end of the next full cycle
+    for (_, data) in &frames[14..23] {
+        assert_eq!(decoder.feed(*data), None);
+    }
+    assert!(decoder.feed(frames[23].1).is_some());
+}
+
+#[test]
+fn starts_on_the_offset_and_clocks_at_tempo() {
+    let rate = FrameRate::Fps25;
+    let mut converter = MtcToClock::new(120, Timecode::new(0, 0, 1, 0));
+    // Two seconds from 00:00:00:00
+    let out = run(
+        &mut converter,
+        &stream(Timecode::default(), rate, 200),
+        2_000_000,
+    );
+    assert_eq!(out[0], (1_000_000, ClockOutput::Start));
+    let clocks: Vec<u64> = out
+        .iter()
+        .filter(|(_, output)| *output == ClockOutput::Clock)
+        .map(|(time_us, _)| *time_us)
+        .take_while(|time_us| *time_us < 2_000_000)
+        .collect();
+    let expected: Vec<u64> = (0..48).map(|k| clock_us(1_000_000, k, 120)).collect();
+    assert_eq!(clocks, expected);
+}
+
+#[test]
+fn continues_from_the_next_sixteenth_when_started_late() {
+    let rate = FrameRate::Fps25;
+    let mut converter = MtcToClock::new(120, Timecode::default());
+    let out = run(
+        &mut converter,
+        &stream(Timecode::new(0, 0, 10, 0), rate, 40),
+        10_400_000,
+    );
+    // Locked at 10.07 s, clock 484; the next sixteenth is 81, clock 486
+    assert_eq!(out[0], (10_070_000, ClockOutput::Continue(81)));
+    assert_eq!(out[1], (clock_us(0, 486, 120), ClockOutput::Clock));
+}
+
+#[test]
+fn stops_when_the_timecode_stops() {
+    let rate = FrameRate::Fps25;
+    let mut converter = MtcToClock::new(120, Timecode::default());
+    let frames = stream(Timecode::new(0, 0, 10, 0), rate, 40);
+    let last_us = frames.last().unwrap().0;
+    let out = run(&mut converter, &frames, last_us + 1_000_000);
+    let (stop_us, stop) = *out.last().unwrap();
+    assert_eq!(
+        (stop_us, stop),
+        (last_us + MTC_TIMEOUT_US, ClockOutput::Stop)
+    );
+    // No clock runs more than two quarter frames past the last one
+    let last_clock_us = out
+        .iter()
+        .filter(|(_, output)| *output == ClockOutput::Clock)
+        .map(|(time_us, _)| *time_us)
+        .max()
+        .unwrap();
+    assert!(last_clock_us <= last_us + rate.quarter_frames_to_us(2));
+}
+
+#[test]
+fn a_jump_stops_and_continues_from_the_new_position() {
+    let rate = FrameRate::Fps25;
+    let mut converter = MtcToClock::new(120, Timecode::default());
+    let mut frames = stream(Timecode::new(0, 0, 1, 0), rate, 48);
+    // The DAW locates to 20 s, sending the next cycle with the new timecode
+    frames.extend(
+        stream(Timecode::new(0, 0, 20, 0), rate, 16)
+            .into_iter()
+            .map(|(time_us, data)| (time_us - 19_000_000 + 480_000, data)),
+    );
+    let out: Vec<ClockOutput> = run(&mut converter, &frames, 1_600_000)
+        .into_iter()
+        .map(|(_, output)| output)
+        .filter(|output| *output != ClockOutput::Clock)
+        .collect();
+    // Locked at 1.07 s, clock 52, the next sixteenth is 9. Relocked at
+    // 20.07 s and continued one quarter frame later at 20.08 s: clock 964,
+    // the next sixteenth is 161
+    assert_eq!(
+        out,
+        [
+            ClockOutput::Continue(9),
+            ClockOutput::Stop,
+            ClockOutput::Continue(161)
+        ]
+    );
+}
+
+/// 120 BPM
+const CLOCK_US: u64 = 20_833;
+
+/// Clocks at 120 BPM from `start_us`, until before `end_us`
+fn clocks(start_us: u64, end_us: u64) -> impl Iterator<Item = (u64, Vec<u8>)> {
+    (start_us..end_us)
+        .step_by(CLOCK_US as usize)
+        .map(|time_us| (time_us, vec![0xF8]))
+}
+
+/// Quarter frames generated for a clock stream, with their time
+fn generate(
+    generator: &mut ClockToMtc,
+    messages: impl IntoIterator<Item = (u64, Vec<u8>)>,
+    until_us: u64,
+) -> Vec<(u64, u8)> {
+    let mut out = Vec::new();
+    let mut drain = |generator: &mut ClockToMtc, until_us: u64| {
+        while let Some(deadline) = generator.deadline_us() {
+            if deadline > until_us {
+                break;
+            }
+            match generator.poll(deadline) {
+                Some(data) => out.push((deadline, data)),
+                None => break,
+            }
+        }
+    };
+    for (time_us, bytes) in messages {
+        drain(generator, time_us);
+        generator.message(&bytes, time_us);
+    }
+    drain(generator, until_us);
+    out
+}
+
+/// Position the decoder reads from generated quarter frames, in quarters
+fn decoded(frames: &[(u64, u8)]) -> Option<u64> {
+    let mut decoder = QuarterFrameDecoder::default();
+    frames.iter().map(|(_, data)| decoder.feed(*data)).last()?
+}
+
+#[test]
+fn generates_timecode_from_the_offset_on_start() {
+    let rate = FrameRate::Fps25;
+    let offset = Timecode::new(1, 0, 0, 0);
+    let mut generator = ClockToMtc::new(rate, offset);
+    let messages = core::iter::once((0, vec![0xFA])).chain(clocks(0, 1_000_000));
+    let frames = generate(&mut generator, messages, 999_999);
+    // One quarter frame every 10 ms, starting with piece 0 on the first clock
+    assert_eq!(frames.len(), 100);
+    for (i, (time_us, data)) in frames.iter().enumerate() {
+        assert_eq!(*time_us, i as u64 * 10_000);
+        assert_eq!(data >> 4, (i % 8) as u8);
+    }
+    assert_eq!(
+        frames[..8]
+            .iter()
+            .map(|(_, data)| data & 0x0F)
+            .collect::<Vec<_>>(),
+        [0, 0, 0, 0, 0, 0, 1, rate.code() << 1]
+    );
+    assert_eq!(decoded(&frames), Some(offset.to_frames(rate) * 4 + 99));
+}
+
+#[test]
+fn stop_holds_and_continue_resumes() {
+    let rate = FrameRate::Fps30;
+    let mut generator = ClockToMtc::new(rate, Timecode::default());
+    let messages = core::iter::once((0, vec![0xFA]))
+        .chain(clocks(0, 1_000_000))
+        .chain([(1_000_000, vec![0xFC]), (3_000_000, vec![0xFB])])
+        .chain(clocks(3_000_000, 3_500_000));
+    let frames = generate(&mut generator, messages, 3_500_000);
+    assert!(frames
+        .iter()
+        .all(|(time_us, _)| *time_us <= 1_000_000 || *time_us >= 3_000_000));
+    // Resumed on the next even frame after 1 s: frame 30
+    let resumed: Vec<_> = frames
+        .into_iter()
+        .filter(|(time_us, _)| *time_us >= 3_000_000)
+        .collect();
+    assert_eq!(resumed[0].0, 3_000_000);
+    assert_eq!(decoded(&resumed[..8]), Some(30 * 4 + 7));
+}
+
+#[test]
+fn song_position_locates_at_the_measured_tempo() {
+    let rate = FrameRate::Fps25;
+    let mut generator = ClockToMtc::new(rate, Timecode::default());
+    // The master clocks while stopped, then locates to bar 2 (sixteenth 16)
+    let messages = clocks(0, 1_000_000)
+        .chain([(1_000_000, vec![0xF2, 16, 0]), (1_000_000, vec![0xFB])])
+        .chain(clocks(1_000_000, 1_200_000));
+    let frames = generate(&mut generator, messages, 1_200_000);
+    // Two seconds at 120 BPM, frame 50, give or take the clock rounding
+    let position = decoded(&frames[..8]).unwrap();
+    assert!((50 * 4 + 7..=52 * 4 + 7).contains(&position), "{position}");
+}
+
+#[test]
+fn pausing_clocks_pause_the_timecode() {
+    let rate = FrameRate::Fps25;
+    let mut generator = ClockToMtc::new(rate, Timecode::default());
+    let messages = core::iter::once((0, vec![0xFA])).chain(clocks(0, 500_000));
+    let frames = generate(&mut generator, messages, 2_000_000);
+    let last_clock_us = (500_000 - 1) / CLOCK_US * CLOCK_US;
+    assert!(frames
+        .iter()
+        .all(|(time_us, _)| *time_us < last_clock_us + CLOCK_TIMEOUT_US));
+    assert_eq!(generator.deadline_us(), None);
+}
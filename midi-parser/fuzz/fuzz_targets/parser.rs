@@ -0,0 +1,82 @@
+//! Arbitrary byte streams, with time gaps, through `MidiParser::feed_byte`
+//!
+//! Besides panics, fails on messages that aren't encoded the way their
+//! variant says, and on a parser that doesn't recover: after any input and a
+//! reset, a complete Note On must come out again.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use embassy_time::{Duration, MockDriver};
+use libfuzzer_sys::fuzz_target;
+use midi_parser::{MidiMessage, MidiParser};
+
+#[derive(Debug, Arbitrary)]
+enum Step {
+    Byte(u8),
+    /// Silence on the line, in milliseconds; long gaps trip the byte timeout
+    Gap(u16),
+}
+
+fn check(message: &MidiMessage) {
+    let bytes = message.bytes();
+    let data = |bytes: &[u8]| bytes.iter().all(|byte| *byte < 0x80);
+    let valid = match message {
+        MidiMessage::SystemRealtime(_) => {
+            bytes.len() == 1 && bytes[0] >= 0xF8 && bytes[0] != 0xF9 && bytes[0] != 0xFD
+        }
+        MidiMessage::Voice(_) => {
+            let len = if (0xC0..0xE0).contains(&bytes[0]) {
+                2
+            } else {
+                3
+            };
+            (0x80..0xF0).contains(&bytes[0]) && bytes.len() == len && data(&bytes[1..])
+        }
+        MidiMessage::SystemCommon(_) => {
+            let len = match bytes[0] {
+                0xF1 | 0xF3 => 2,
+                0xF2 => 3,
+                0xF6 => 1,
+                _ => 0,
+            };
+            bytes.len() == len && data(&bytes[1..])
+        }
+        MidiMessage::RunningStatus(_) => (1..=2).contains(&bytes.len()) && data(bytes),
+        MidiMessage::SysEx(_) => {
+            bytes.len() >= 2
+                && bytes[0] == 0xF0
+                && bytes[bytes.len() - 1] == 0xF7
+                && data(&bytes[1..bytes.len() - 1])
+        }
+        MidiMessage::SysExChunk(_) => {
+            let start = usize::from(bytes.first() == Some(&0xF0));
+            let end = bytes.len() - usize::from(bytes.last() == Some(&0xF7));
+            !bytes.is_empty() && start <= end && data(&bytes[start..end])
+        }
+    };
+    assert!(valid, "malformed message {:02x?}", bytes);
+}
+
+fuzz_target!(|steps: Vec<Step>| {
+    MockDriver::get().reset();
+    let mut parser = MidiParser::default();
+
+    for step in steps {
+        match step {
+            Step::Byte(byte) => match parser.feed_byte(byte) {
+                Ok(Some(message)) => check(&message),
+                Ok(None) => {}
+                Err(_) => parser.reset(),
+            },
+            Step::Gap(ms) => MockDriver::get().advance(Duration::from_millis(ms.into())),
+        }
+    }
+
+    parser.reset();
+    let mut messages = [0x90, 0x3C, 0x64]
+        .into_iter()
+        .filter_map(|byte| parser.feed_byte(byte).expect("error after reset"));
+    let message = messages.next().expect("no message after reset");
+    assert_eq!(message.bytes(), [0x90, 0x3C, 0x64]);
+});
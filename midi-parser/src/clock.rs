@@ -0,0 +1,28 @@
+//! Time source of the parser's byte timeout
+//!
+//! The firmware and the host tools read `embassy_time` (`SystemClock`, with
+//! the `system-clock` feature). Host tests drive a clock of their own
+//! instead, so timeouts can be tested without waiting for them, and other
+//! projects can bring theirs without an embassy-time driver.
+
+use embassy_time::Instant;
+
+/// Where the current time comes from
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The `embassy_time` clock, the default of `MidiParser`
+///
+/// Needs the `system-clock` feature to tell the time, which links the
+/// embassy-time driver.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+#[cfg(feature = "system-clock")]
+impl Clock for SystemClock {
+    #[inline(always)]
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
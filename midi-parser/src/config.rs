@@ -0,0 +1,35 @@
+//! Build-time sizes of the parser
+//!
+//! Like the firmware's own sizes, these can be overridden with an environment
+//! variable at build time, see the firmware's `config.rs`.
+
+/// Longest SysEx message captured by the parser, including 0xF0 and 0xF7
+pub const SYSEX_CAPTURE_LEN: usize = env_or(option_env!("MIDI_SYSEX_CAPTURE_LEN"), 16);
+
+const _: () = {
+    // The longest merger request is F0 7D 4D 4D <dev> <cmd> <arg> F7
+    assert!(
+        SYSEX_CAPTURE_LEN >= 8,
+        "MIDI_SYSEX_CAPTURE_LEN must be at least 8"
+    );
+};
+
+/// Parse a decimal size at compile time, or fall back to the default
+const fn env_or(value: Option<&str>, default: usize) -> usize {
+    let Some(value) = value else {
+        return default;
+    };
+    let bytes = value.as_bytes();
+    assert!(!bytes.is_empty(), "empty size in build environment");
+    let mut result: usize = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(
+            bytes[i].is_ascii_digit(),
+            "size in build environment is not a number"
+        );
+        result = result * 10 + (bytes[i] - b'0') as usize;
+        i += 1;
+    }
+    result
+}
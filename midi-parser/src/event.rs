@@ -0,0 +1,179 @@
+//! MIDI messages decoded into what they mean
+//!
+//! `MidiMessage` keeps the bytes as they arrived, which is what the merger
+//! forwards. Features that filter or route on the content of a message take
+//! its `Event` instead of picking the bytes apart again. Running status is
+//! resolved with the input's cached status, as the merger tracks it.
+
+use crate::parser::MidiMessage;
+use serde::{Deserialize, Serialize};
+
+/// Type of a message, by its status byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Kind {
+    NoteOff,
+    NoteOn,
+    PolyPressure,
+    ControlChange,
+    ProgramChange,
+    ChannelPressure,
+    PitchBend,
+    SystemCommon,
+    SystemRealtime,
+}
+
+impl Kind {
+    /// Type of the messages with a status byte, `None` for data bytes,
+    /// SysEx and undefined status bytes
+    pub fn of(status: u8) -> Option<Self> {
+        let kind = match status {
+            0x80..=0x8F => Kind::NoteOff,
+            0x90..=0x9F => Kind::NoteOn,
+            0xA0..=0xAF => Kind::PolyPressure,
+            0xB0..=0xBF => Kind::ControlChange,
+            0xC0..=0xCF => Kind::ProgramChange,
+            0xD0..=0xDF => Kind::ChannelPressure,
+            0xE0..=0xEF => Kind::PitchBend,
+            0xF1..=0xF3 | 0xF6 => Kind::SystemCommon,
+            0xF8 | 0xFA..=0xFC | 0xFE | 0xFF => Kind::SystemRealtime,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+/// A complete MIDI message, decoded
+///
+/// MIDI channels are 0-15, as on the wire. A Note On with velocity 0 stays a
+/// `NoteOn`, as it was sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    NoteOff {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    PolyPressure {
+        channel: u8,
+        note: u8,
+        pressure: u8,
+    },
+    ControlChange {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+    ProgramChange {
+        channel: u8,
+        program: u8,
+    },
+    ChannelPressure {
+        channel: u8,
+        pressure: u8,
+    },
+    /// 14-bit value, 8192 is the center
+    PitchBend {
+        channel: u8,
+        value: u16,
+    },
+    /// MTC quarter frame, the piece number in the high nibble
+    QuarterFrame(u8),
+    /// Song position in sixteenth notes
+    SongPosition(u16),
+    SongSelect(u8),
+    TuneRequest,
+    Clock,
+    Start,
+    Continue,
+    Stop,
+    ActiveSensing,
+    Reset,
+}
+
+/// A 14-bit controller: a Control Change 0-31 carrying the MSB, paired
+/// with the one 32 higher carrying the LSB
+///
+/// The parser pairs them with `set_pair_cc14` (see `take_cc14`); the
+/// messages themselves still go out as they came.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ControlChange14 {
+    pub channel: u8,
+    /// Controller number of the MSB, 0-31
+    pub controller: u8,
+    /// 14-bit value, MSB first
+    pub value: u16,
+}
+
+impl Event {
+    /// Decode a status byte and its data bytes
+    ///
+    /// Returns `None` for SysEx, undefined status bytes and a wrong number
+    /// of data bytes.
+    pub fn decode(status: u8, data: &[u8]) -> Option<Self> {
+        let channel = status & 0x0F;
+        let event = match (status & 0xF0, data) {
+            (0x80, &[note, velocity]) => Event::NoteOff {
+                channel,
+                note,
+                velocity,
+            },
+            (0x90, &[note, velocity]) => Event::NoteOn {
+                channel,
+                note,
+                velocity,
+            },
+            (0xA0, &[note, pressure]) => Event::PolyPressure {
+                channel,
+                note,
+                pressure,
+            },
+            (0xB0, &[controller, value]) => Event::ControlChange {
+                channel,
+                controller,
+                value,
+            },
+            (0xC0, &[program]) => Event::ProgramChange { channel, program },
+            (0xD0, &[pressure]) => Event::ChannelPressure { channel, pressure },
+            (0xE0, &[lsb, msb]) => Event::PitchBend {
+                channel,
+                value: (msb as u16) << 7 | lsb as u16,
+            },
+            (0xF0, _) => match (status, data) {
+                (0xF1, &[data]) => Event::QuarterFrame(data),
+                (0xF2, &[lsb, msb]) => Event::SongPosition((msb as u16) << 7 | lsb as u16),
+                (0xF3, &[song]) => Event::SongSelect(song),
+                (0xF6, []) => Event::TuneRequest,
+                (0xF8, []) => Event::Clock,
+                (0xFA, []) => Event::Start,
+                (0xFB, []) => Event::Continue,
+                (0xFC, []) => Event::Stop,
+                (0xFE, []) => Event::ActiveSensing,
+                (0xFF, []) => Event::Reset,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(event)
+    }
+}
+
+impl MidiMessage {
+    /// The message decoded
+    ///
+    /// Returns `None` for SysEx, and for running status without a cached
+    /// status.
+    ///
+    /// # Arguments
+    /// * `cached_status` - The input's last status, resolves running status
+    pub fn event(&self, cached_status: Option<u8>) -> Option<Event> {
+        Event::decode(self.status_byte(cached_status)?, self.data())
+    }
+}
@@ -0,0 +1,29 @@
+//! MIDI 1.0 byte stream parser
+//!
+//! `MidiParser` turns the bytes of one MIDI input into complete messages:
+//! running status, realtime bytes inside other messages, SysEx and recovery
+//! from bad bytes and timeouts included. It depends on nothing but a clock,
+//! so any transport can feed it: the merger's UART, I2C and SPI inputs, the
+//! host tools, and other embedded MIDI projects.
+//!
+//! The crate is `no_std`; the `defmt` feature routes its logging to defmt on
+//! the target. The byte timeout reads a `clock::Clock`: `SystemClock`, the
+//! `embassy_time` clock behind the default `system-clock` feature, needs an
+//! embassy-time driver, a parser made `with_clock` needs nothing but the
+//! `Instant` type. Host builds enable `std`, which brings the host time driver
+//! and `std::error::Error` for the parser's errors.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod clock;
+mod config;
+pub mod event;
+mod log;
+mod parser;
+pub mod timecode;
+
+pub use log::LogLevel;
+pub use parser::{
+    DiscardCause, Discarded, FeedBytes, MidiMessage, MidiMessageError, MidiParser, ParserStats,
+    ResyncPolicy, ShortMessage, UndefinedRealtime, SYSEX_CAPTURE_LEN,
+};
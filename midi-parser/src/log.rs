@@ -0,0 +1,94 @@
+//! Logging of the parser
+//!
+//! With the `defmt` feature the `log::` macros hand to defmt, without them
+//! they compile to nothing. Warnings, infos and debugs first ask the
+//! parser's log filter (`MidiParser::set_log_filter`), so a firmware can
+//! apply its runtime log level to them; errors are always logged. A filter
+//! can only narrow what `DEFMT_LOG` compiled in.
+
+/// Level of a log statement, most severe first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum LogLevel {
+    Error = 0,
+    Warn = 1,
+    Info = 2,
+    Debug = 3,
+}
+
+impl LogLevel {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(LogLevel::Error),
+            1 => Some(LogLevel::Warn),
+            2 => Some(LogLevel::Info),
+            3 => Some(LogLevel::Debug),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        defmt::error!($($arg)*)
+    };
+}
+
+#[cfg(feature = "defmt")]
+macro_rules! log_warn {
+    ($filter:expr, $($arg:tt)*) => {
+        if ($filter)($crate::log::LogLevel::Warn) {
+            defmt::warn!($($arg)*);
+        }
+    };
+}
+
+#[cfg(feature = "defmt")]
+macro_rules! log_info {
+    ($filter:expr, $($arg:tt)*) => {
+        if ($filter)($crate::log::LogLevel::Info) {
+            defmt::info!($($arg)*);
+        }
+    };
+}
+
+#[cfg(feature = "defmt")]
+macro_rules! log_debug {
+    ($filter:expr, $($arg:tt)*) => {
+        if ($filter)($crate::log::LogLevel::Debug) {
+            defmt::debug!($($arg)*);
+        }
+    };
+}
+
+#[cfg(not(feature = "defmt"))]
+macro_rules! log_error {
+    ($($arg:tt)*) => {};
+}
+
+#[cfg(not(feature = "defmt"))]
+macro_rules! log_warn {
+    ($filter:expr, $($arg:tt)*) => {
+        let _ = $filter;
+    };
+}
+
+#[cfg(not(feature = "defmt"))]
+macro_rules! log_info {
+    ($filter:expr, $($arg:tt)*) => {
+        let _ = $filter;
+    };
+}
+
+#[cfg(not(feature = "defmt"))]
+macro_rules! log_debug {
+    ($filter:expr, $($arg:tt)*) => {
+        let _ = $filter;
+    };
+}
+
+// `warn` can't be imported under its own name, it clashes with the built-in
+// attribute
+pub(crate) use {log_debug as debug, log_error as error, log_info as info, log_warn as warn};
@@ -0,0 +1,1264 @@
+//! Stateful MIDI 1.0 byte stream parser
+
+use crate::clock::{Clock, SystemClock};
+use crate::event::{ControlChange14, Event, Kind};
+use crate::log::{self, LogLevel};
+use crate::timecode::{MtcTimecode, QuarterFrameDecoder};
+use embassy_time::{Duration, Instant};
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+
+/// Parser state machine states
+///
+/// The parser operates in one of three modes:
+/// - `Reading`: Normal message parsing, accumulating status and data bytes
+/// - `Resyncing`: Error recovery mode, hunting for the next valid status byte
+/// - `InSysEx`: Inside a System Exclusive message, capturing data bytes until 0xF7
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParserState {
+    Reading,
+    Resyncing,
+    InSysEx,
+}
+
+/// A parsed MIDI message with its associated data bytes
+///
+/// MIDI messages are categorized into four types based on their status byte:
+/// - Voice: Channel-specific messages (Note On/Off, Control Change, etc.) (0x80-0xEF)
+/// - SystemCommon: System-wide messages (Song Position, Tune Request, etc.) (0xF1-0xF6)
+/// - SystemRealtime: Timing and synchronization messages (Clock, Start/Stop, etc.) (0xF8-0xFF)
+/// - RunningStatus: Data bytes without a status byte (reuses previous status)
+///
+/// Each of them holds its bytes in a `ShortMessage`.
+///
+/// Short System Exclusive messages (up to `SYSEX_CAPTURE_LEN` bytes including
+/// 0xF0 and 0xF7) are captured whole as `SysEx`, so the merger can answer
+/// SysEx requests addressed to it. Longer SysEx messages are discarded, or,
+/// with SysEx streaming on, passed on as `SysExChunk`s of up to
+/// `SYSEX_CAPTURE_LEN` bytes: the first starts with 0xF0, the last ends with
+/// 0xF7.
+///
+/// The SysEx variants hold the capture buffer inline, so every message is
+/// as large as that buffer, the short ones included.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MidiMessage {
+    SystemRealtime(ShortMessage),
+    RunningStatus(ShortMessage),
+    Voice(ShortMessage),
+    SystemCommon(ShortMessage),
+    SysEx(Vec<u8, SYSEX_CAPTURE_LEN>),
+    SysExChunk(Vec<u8, SYSEX_CAPTURE_LEN>),
+}
+
+/// The bytes of a message other than SysEx, at most three, stored inline
+///
+/// Derefs to the bytes as a slice. Unlike a `heapless::Vec` it's `Copy` and
+/// takes four bytes, and building one can't fail once the length is known.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShortMessage {
+    bytes: [u8; 3],
+    len: u8,
+}
+
+impl ShortMessage {
+    /// A message of these bytes, `None` if there are more than three
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        let mut message = Self::default();
+        message.bytes.get_mut(..bytes.len())?.copy_from_slice(bytes);
+        message.len = bytes.len() as u8;
+        Some(message)
+    }
+
+    /// An optional status byte followed by data bytes
+    ///
+    /// # Panics
+    /// With more than two data bytes.
+    pub fn new(status: Option<u8>, data: &[u8]) -> Self {
+        let mut message = Self::default();
+        let start = usize::from(status.is_some());
+        message.bytes[0] = status.unwrap_or(0);
+        message.bytes[start..start + data.len()].copy_from_slice(data);
+        message.len = (start + data.len()) as u8;
+        message
+    }
+}
+
+impl core::ops::Deref for ShortMessage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes[..usize::from(self.len)]
+    }
+}
+
+impl core::ops::DerefMut for ShortMessage {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes[..usize::from(self.len)]
+    }
+}
+
+impl<const N: usize> PartialEq<[u8; N]> for ShortMessage {
+    fn eq(&self, other: &[u8; N]) -> bool {
+        **self == *other
+    }
+}
+
+impl PartialEq<[u8]> for ShortMessage {
+    fn eq(&self, other: &[u8]) -> bool {
+        **self == *other
+    }
+}
+
+impl core::fmt::Debug for ShortMessage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ShortMessage {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "{=[u8]:x}", &self[..])
+    }
+}
+
+/// Serialized as a sequence of bytes, as a `heapless::Vec` is
+impl Serialize for ShortMessage {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for ShortMessage {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Vec::<u8, 3>::deserialize(deserializer)?;
+        Ok(Self::new(None, &bytes))
+    }
+}
+
+/// Maximum length of a captured SysEx message, including 0xF0 and 0xF7
+pub const SYSEX_CAPTURE_LEN: usize = crate::config::SYSEX_CAPTURE_LEN;
+
+/// Errors that can occur during MIDI message parsing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MidiMessageError {
+    /// Received an invalid or undefined MIDI status byte
+    UnknownStatus,
+    /// Received a status byte while still processing a previous message
+    DuplicateStatus,
+    /// Received more data bytes than expected for the current message type,
+    /// or a data byte with no running status to apply it to
+    UnexpectedDataByte,
+    /// Received an undefined status byte (0xF4, 0xF5, 0xF9-0xFD)
+    InvalidStatusByte,
+}
+
+impl core::fmt::Display for MidiMessageError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            MidiMessageError::UnknownStatus => "unknown status byte",
+            MidiMessageError::DuplicateStatus => "status byte inside an incomplete message",
+            MidiMessageError::UnexpectedDataByte => "unexpected data byte",
+            MidiMessageError::InvalidStatusByte => "undefined status byte",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MidiMessageError {}
+
+/// What the parser does with a bad byte
+///
+/// Bad bytes are undefined status bytes, data bytes with no status to apply
+/// them to, and a status byte arriving while a message is still incomplete.
+/// Byte timeouts always resync, whatever the policy.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ResyncPolicy {
+    /// Report an error and resync on every bad byte
+    #[default]
+    Strict,
+    /// Skip bad bytes and keep the message in progress
+    ///
+    /// An interrupting status byte still starts a new message, as the
+    /// MIDI spec has it; only the incomplete message before it is lost.
+    Permissive,
+    /// Skip up to this many bad bytes between two complete messages, then
+    /// resync as `Strict` does
+    Budget(u8),
+}
+
+/// What the parser does with the undefined realtime bytes 0xF9 and 0xFD
+///
+/// Some gear sends them anyway, e.g. as a tick on 0xF9. They carry no data
+/// and can't break the message they interrupt.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UndefinedRealtime {
+    /// Treat them as any bad byte, by the `ResyncPolicy`
+    #[default]
+    Reject,
+    /// Discard them quietly, the message in progress and running status
+    /// carry on
+    Drop,
+    /// Pass them on as `SystemRealtime` messages
+    Forward,
+}
+
+/// Why the parser dropped a message it had started
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DiscardCause {
+    /// The next byte came after the byte timeout
+    Timeout,
+    /// A bad byte sent the parser into resync
+    Error,
+    /// `reset()` was called, e.g. after a UART error
+    Reset,
+}
+
+/// An unfinished message the parser dropped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Discarded {
+    /// The bytes received of it: status and data bytes, only data bytes
+    /// under running status, or 0xF0 for SysEx
+    pub bytes: ShortMessage,
+    /// Bytes dropped in all; for SysEx, those still held by the parser
+    pub len: usize,
+    pub cause: DiscardCause,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Discarded {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "{} ({} bytes, {})", self.bytes, self.len, self.cause)
+    }
+}
+
+/// What a parser has seen since it was made or its counters were reset
+///
+/// Counters wrap around on overflow.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParserStats {
+    /// Voice messages with their own status byte
+    pub voice: u32,
+    pub running_status: u32,
+    pub system_common: u32,
+    pub realtime: u32,
+    /// SysEx messages, whole or the last chunk of a stream
+    pub sysex: u32,
+    /// Entries into resync mode, from errors, timeouts and resets
+    pub resyncs: u32,
+    /// Messages dropped by the byte timeout
+    pub timeouts: u32,
+    /// Bad bytes the `ResyncPolicy` skipped
+    pub skipped: u32,
+    pub unknown_status: u32,
+    pub duplicate_status: u32,
+    pub unexpected_data_byte: u32,
+    pub invalid_status_byte: u32,
+}
+
+impl ParserStats {
+    fn record(&mut self, result: &Result<Option<MidiMessage>, MidiMessageError>) {
+        let count = match result {
+            Ok(None) => return,
+            Ok(Some(MidiMessage::Voice(_))) => &mut self.voice,
+            Ok(Some(MidiMessage::RunningStatus(_))) => &mut self.running_status,
+            Ok(Some(MidiMessage::SystemCommon(_))) => &mut self.system_common,
+            Ok(Some(MidiMessage::SystemRealtime(_))) => &mut self.realtime,
+            Ok(Some(MidiMessage::SysEx(_))) => &mut self.sysex,
+            Ok(Some(MidiMessage::SysExChunk(data))) if data.last() == Some(&0xF7) => {
+                &mut self.sysex
+            }
+            Ok(Some(MidiMessage::SysExChunk(_))) => return,
+            Err(MidiMessageError::UnknownStatus) => &mut self.unknown_status,
+            Err(MidiMessageError::DuplicateStatus) => &mut self.duplicate_status,
+            Err(MidiMessageError::UnexpectedDataByte) => &mut self.unexpected_data_byte,
+            Err(MidiMessageError::InvalidStatusByte) => &mut self.invalid_status_byte,
+        };
+        *count = count.wrapping_add(1);
+    }
+}
+
+/// A diagnostic entry in the circular buffer
+///
+/// Stores a received byte along with a sequence number for ordering
+#[cfg(all(debug_assertions, feature = "defmt"))]
+#[derive(Debug, Clone, Copy)]
+struct DiagnosticEntry {
+    byte: u8,
+    sequence: u32,
+}
+
+/// Fixed-size circular buffer for diagnostic purposes
+///
+/// Stores the last N bytes received by the parser to aid in debugging
+/// parser errors. Uses a simple ring buffer with sequence numbers.
+///
+/// This buffer is only compiled in debug builds to avoid wasting RAM
+/// in release builds where diagnostic logging is disabled, and in builds
+/// without defmt, which log nothing.
+#[cfg(all(debug_assertions, feature = "defmt"))]
+#[derive(Debug)]
+struct DiagnosticBuffer<const N: usize> {
+    buffer: [DiagnosticEntry; N],
+    head: usize,
+    sequence: u32,
+}
+
+#[cfg(all(debug_assertions, feature = "defmt"))]
+impl<const N: usize> DiagnosticBuffer<N> {
+    const fn new() -> Self {
+        Self {
+            buffer: [DiagnosticEntry {
+                byte: 0,
+                sequence: 0,
+            }; N],
+            head: 0,
+            sequence: 0,
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.buffer[self.head] = DiagnosticEntry {
+            byte,
+            sequence: self.sequence,
+        };
+        self.head = (self.head + 1) % N;
+        self.sequence = self.sequence.wrapping_add(1);
+    }
+
+    /// Log the buffer contents in chronological order
+    fn log(&self, filter: fn(LogLevel) -> bool) {
+        // Find the oldest entry (the one after head, or 0 if we haven't wrapped)
+        let oldest_idx = if (self.sequence as usize) < N {
+            0
+        } else {
+            self.head
+        };
+
+        log::debug!(filter, "Last {} bytes received (chronological order):", N);
+
+        let count = core::cmp::min(self.sequence as usize, N);
+        for i in 0..count {
+            let idx = (oldest_idx + i) % N;
+            let entry = self.buffer[idx];
+            log::debug!(filter, "  seq={}: {:#04x}", entry.sequence, entry.byte);
+        }
+    }
+}
+
+/// No-op diagnostic buffer for release and host builds
+#[cfg(not(all(debug_assertions, feature = "defmt")))]
+#[derive(Debug)]
+struct DiagnosticBuffer<const N: usize>;
+
+#[cfg(not(all(debug_assertions, feature = "defmt")))]
+impl<const N: usize> DiagnosticBuffer<N> {
+    const fn new() -> Self {
+        Self
+    }
+
+    #[inline(always)]
+    fn push(&mut self, _byte: u8) {}
+
+    #[inline(always)]
+    fn log(&self, _filter: fn(LogLevel) -> bool) {}
+}
+
+impl MidiMessage {
+    fn from_status_and_data(
+        status_byte: Option<u8>,
+        data_bytes: &[u8],
+    ) -> Result<Self, MidiMessageError> {
+        let data = ShortMessage::new(status_byte, data_bytes);
+
+        let message: MidiMessage;
+
+        if status_byte.is_none() {
+            message = MidiMessage::RunningStatus(data)
+        } else if (0xF8..=0xFF).contains(&data[0]) {
+            message = MidiMessage::SystemRealtime(data)
+        } else if (0x80..=0xEF).contains(&data[0]) {
+            message = MidiMessage::Voice(data)
+        } else if (0xF1..=0xF3).contains(&data[0]) || data[0] == 0xF6 {
+            message = MidiMessage::SystemCommon(data)
+        } else {
+            return Err(MidiMessageError::UnknownStatus);
+        }
+
+        Ok(message)
+    }
+
+    /// The message bytes as received
+    pub fn bytes(&self) -> &[u8] {
+        match self {
+            MidiMessage::Voice(d)
+            | MidiMessage::RunningStatus(d)
+            | MidiMessage::SystemCommon(d)
+            | MidiMessage::SystemRealtime(d) => d,
+            MidiMessage::SysEx(d) | MidiMessage::SysExChunk(d) => d,
+        }
+    }
+
+    /// Status byte the message goes by
+    ///
+    /// Returns `None` for SysEx, which is framed by 0xF0 and 0xF7 rather
+    /// than sent under a status, and for running status without a cached
+    /// status.
+    ///
+    /// # Arguments
+    /// * `cached_status` - The input's last status, resolves running status
+    pub fn status_byte(&self, cached_status: Option<u8>) -> Option<u8> {
+        match self {
+            MidiMessage::Voice(d)
+            | MidiMessage::SystemCommon(d)
+            | MidiMessage::SystemRealtime(d) => Some(d[0]),
+            MidiMessage::RunningStatus(_) => cached_status,
+            MidiMessage::SysEx(_) | MidiMessage::SysExChunk(_) => None,
+        }
+    }
+
+    /// MIDI channel of a channel message, 0-15
+    ///
+    /// # Arguments
+    /// * `cached_status` - The input's last status, resolves running status
+    pub fn midi_channel(&self, cached_status: Option<u8>) -> Option<u8> {
+        self.status_byte(cached_status)
+            .filter(|status| *status < 0xF0)
+            .map(|status| status & 0x0F)
+    }
+
+    /// Type of the message, `None` for SysEx
+    ///
+    /// # Arguments
+    /// * `cached_status` - The input's last status, resolves running status
+    pub fn kind(&self, cached_status: Option<u8>) -> Option<Kind> {
+        Kind::of(self.status_byte(cached_status)?)
+    }
+
+    /// The data bytes, without status byte, 0xF0 or 0xF7
+    pub fn data(&self) -> &[u8] {
+        match self {
+            MidiMessage::Voice(d)
+            | MidiMessage::SystemCommon(d)
+            | MidiMessage::SystemRealtime(d) => &d[1..],
+            MidiMessage::RunningStatus(d) => d,
+            MidiMessage::SysEx(d) | MidiMessage::SysExChunk(d) => {
+                let start = usize::from(d.first() == Some(&0xF0));
+                let end = d.len() - usize::from(d.last() == Some(&0xF7));
+                &d[start..end.max(start)]
+            }
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for MidiMessage {
+    fn format(&self, fmt: defmt::Formatter) {
+        for byte in self.bytes() {
+            defmt::write!(fmt, " {=u8:x}", byte)
+        }
+    }
+}
+
+/// Stateful MIDI 1.0 protocol parser
+///
+/// This parser implements the MIDI 1.0 specification, handling:
+/// - Running status (omitted status bytes)
+/// - System Realtime messages (can interrupt any message)
+/// - System Exclusive (SysEx) messages (0xF0...0xF7)
+/// - Variable-length messages (0-2 data bytes depending on status)
+/// - Resynchronization after errors (hunting for valid status bytes)
+///
+/// Feed bytes one at a time using `feed_byte()`. The parser maintains internal
+/// state and returns `Some(MidiMessage)` when a complete message is assembled.
+///
+/// After errors, the parser enters resync mode where it discards all bytes until
+/// a valid status byte is found, allowing recovery from corrupted byte streams.
+/// A `ResyncPolicy` other than `Strict` skips some bad bytes instead.
+///
+/// The parser includes a diagnostic buffer that stores the last 32 bytes received
+/// for debugging purposes. This buffer is logged when errors occur to help diagnose
+/// what byte sequence led to the error.
+///
+/// The byte timeout reads the time from `C`, the `embassy_time` clock unless
+/// the parser is made `with_clock`.
+#[derive(Debug)]
+pub struct MidiParser<C = SystemClock> {
+    status: Option<u8>,
+    data: [u8; 2],
+    data_len: usize,
+    expected_data_bytes: usize,
+    /// Status that data bytes without a status byte refer to
+    ///
+    /// Set by Voice messages, cancelled by System Common and SysEx messages
+    /// and by errors. Realtime messages leave it untouched.
+    running_status: Option<u8>,
+    state: ParserState,
+    last_byte_time: Option<Instant>,
+    byte_timeout: Duration,
+    sysex: Vec<u8, SYSEX_CAPTURE_LEN>,
+    sysex_overflow: bool,
+    /// Pass long SysEx on in chunks instead of discarding it
+    sysex_streaming: bool,
+    /// Chunks of the current SysEx already passed on
+    sysex_streamed: bool,
+    /// Turn Note On with velocity 0 into Note Off
+    normalize_note_off: bool,
+    /// The last message went out as Note Off in place of a Note On, so the
+    /// next running status message needs the real status byte
+    status_rewritten: bool,
+    /// Emit running status messages with their status byte
+    expand_running_status: bool,
+    /// Silence after which running status is forgotten
+    running_status_expiry: Option<Duration>,
+    /// Last byte other than realtime, for `running_status_expiry`
+    last_activity: Option<Instant>,
+    /// Pair CC 0-31 with CC 32-63 into 14-bit controllers
+    pair_cc14: bool,
+    /// Last MSB controller and value seen on each MIDI channel
+    cc14_msb: [Option<(u8, u8)>; 16],
+    /// 14-bit controller completed by the last LSB, until taken
+    cc14: Option<ControlChange14>,
+    /// Assemble quarter frames into timecodes
+    assemble_timecode: bool,
+    quarter_frames: QuarterFrameDecoder,
+    /// Timecode completed by the last quarter frame, until taken
+    timecode: Option<MtcTimecode>,
+    resyncs: u32,
+    policy: ResyncPolicy,
+    undefined_realtime: UndefinedRealtime,
+    /// Bad bytes skipped since the last complete message
+    bad_bytes: u8,
+    skipped: u32,
+    stats: ParserStats,
+    /// Last unfinished message dropped, until taken
+    discarded: Option<Discarded>,
+    diagnostic_buffer: DiagnosticBuffer<32>,
+    /// Which levels other than errors are logged
+    log_filter: fn(LogLevel) -> bool,
+    clock: C,
+}
+
+#[cfg(feature = "system-clock")]
+impl Default for MidiParser {
+    fn default() -> Self {
+        Self::with_clock(SystemClock)
+    }
+}
+
+impl MidiParser {
+    /// Default maximum time between MIDI bytes before the parser resets
+    ///
+    /// MIDI bytes at 31,250 baud arrive in ~0.32ms each. A complete 3-byte message
+    /// (status + 2 data bytes) transmits in ~0.96ms at most. This generous 300ms
+    /// timeout allows for device processing delays while protecting against stuck
+    /// parser state from hardware glitches, cable disconnects, or electrical noise.
+    pub const DEFAULT_BYTE_TIMEOUT: Duration = Duration::from_millis(300);
+
+    /// Create a parser with the given error recovery policy
+    #[cfg(feature = "system-clock")]
+    pub fn with_policy(policy: ResyncPolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+}
+
+impl<C: Clock> MidiParser<C> {
+    /// Create a parser reading the time from `clock`
+    pub fn with_clock(clock: C) -> Self {
+        Self {
+            status: Default::default(),
+            data: Default::default(),
+            data_len: 0,
+            expected_data_bytes: 2,
+            running_status: None,
+            state: ParserState::Reading,
+            last_byte_time: None,
+            byte_timeout: MidiParser::DEFAULT_BYTE_TIMEOUT,
+            sysex: Default::default(),
+            sysex_overflow: false,
+            sysex_streaming: false,
+            sysex_streamed: false,
+            normalize_note_off: false,
+            pair_cc14: false,
+            cc14_msb: [None; 16],
+            cc14: None,
+            assemble_timecode: false,
+            quarter_frames: QuarterFrameDecoder::default(),
+            timecode: None,
+            status_rewritten: false,
+            expand_running_status: false,
+            running_status_expiry: None,
+            last_activity: None,
+            resyncs: 0,
+            policy: ResyncPolicy::Strict,
+            undefined_realtime: UndefinedRealtime::Reject,
+            bad_bytes: 0,
+            skipped: 0,
+            stats: ParserStats::default(),
+            discarded: None,
+            diagnostic_buffer: DiagnosticBuffer::new(),
+            log_filter: |_| true,
+            clock,
+        }
+    }
+
+    /// Change the error recovery policy
+    pub fn set_policy(&mut self, policy: ResyncPolicy) {
+        self.policy = policy;
+        self.bad_bytes = 0;
+    }
+
+    pub fn policy(&self) -> ResyncPolicy {
+        self.policy
+    }
+
+    /// Change what happens to the undefined realtime bytes 0xF9 and 0xFD
+    pub fn set_undefined_realtime(&mut self, handling: UndefinedRealtime) {
+        self.undefined_realtime = handling;
+    }
+
+    pub fn undefined_realtime(&self) -> UndefinedRealtime {
+        self.undefined_realtime
+    }
+
+    /// Log warnings, infos and debugs only at the levels `filter` passes
+    ///
+    /// Logs everything by default. Errors are always logged.
+    pub fn set_log_filter(&mut self, filter: fn(LogLevel) -> bool) {
+        self.log_filter = filter;
+    }
+
+    /// Emit Note On with velocity 0 as Note Off (0x8n, velocity 0)
+    ///
+    /// Most devices end notes with a velocity 0 Note On, so they can stay in
+    /// running status. A rewritten message always carries its Note Off
+    /// status byte, and the running status message after it gets the Note
+    /// On status back, so merging stays correct.
+    pub fn set_normalize_note_off(&mut self, normalize: bool) {
+        self.normalize_note_off = normalize;
+        self.status_rewritten = false;
+    }
+
+    /// Emit running status messages as full `Voice` messages
+    ///
+    /// The parser already knows the status that data bytes without one
+    /// refer to, so with this on it never returns `RunningStatus` and
+    /// consumers don't need a status cache of their own. Running status
+    /// after an error or a reset is rejected as before, until the input
+    /// sends a status byte again.
+    pub fn set_expand_running_status(&mut self, expand: bool) {
+        self.expand_running_status = expand;
+    }
+
+    /// Forget running status after `expiry` without a byte, `None` keeps it
+    ///
+    /// Data bytes after the pause are rejected like any without running
+    /// status, until the input sends a status byte again, instead of being
+    /// read against a status from before it. Realtime bytes don't count as
+    /// activity. Applies whether or not running status is expanded.
+    pub fn set_running_status_expiry(&mut self, expiry: Option<Duration>) {
+        self.running_status_expiry = expiry;
+        self.last_activity = None;
+    }
+
+    /// Pair Control Change 0-31 (MSB) with the one 32 higher (LSB)
+    ///
+    /// When an LSB follows the MSB of its controller on the same MIDI
+    /// channel, `take_cc14()` returns the 14-bit value. Only the last MSB of
+    /// each channel is remembered, as controllers send the pair back to
+    /// back; an LSB after another controller's MSB pairs with nothing. The
+    /// messages are emitted unchanged either way.
+    pub fn set_pair_cc14(&mut self, pair: bool) {
+        self.pair_cc14 = pair;
+        self.cc14_msb = [None; 16];
+        self.cc14 = None;
+    }
+
+    /// The 14-bit controller the last LSB completed, if not taken yet
+    pub fn take_cc14(&mut self) -> Option<ControlChange14> {
+        self.cc14.take()
+    }
+
+    /// Remember an MSB, or pair an LSB with the MSB before it
+    fn pair_cc14(&mut self, message: &MidiMessage) {
+        let Some(Event::ControlChange {
+            channel,
+            controller,
+            value,
+        }) = message.event(self.running_status)
+        else {
+            return;
+        };
+        let msb = &mut self.cc14_msb[usize::from(channel)];
+        match (controller, *msb) {
+            (0..=31, _) => *msb = Some((controller, value)),
+            (32..=63, Some((msb_controller, msb_value))) if msb_controller == controller - 32 => {
+                self.cc14 = Some(ControlChange14 {
+                    channel,
+                    controller: msb_controller,
+                    value: u16::from(msb_value) << 7 | u16::from(value),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Assemble MTC quarter frames (`F1 <data>`) into timecodes
+    ///
+    /// Once all eight pieces of a timecode have arrived in order,
+    /// `take_timecode()` returns it after every eighth quarter frame, that
+    /// is every two frames. The quarter frames are emitted unchanged.
+    pub fn set_assemble_timecode(&mut self, assemble: bool) {
+        self.assemble_timecode = assemble;
+        self.quarter_frames.reset();
+        self.timecode = None;
+    }
+
+    /// The timecode the last quarter frame completed, if not taken yet
+    pub fn take_timecode(&mut self) -> Option<MtcTimecode> {
+        self.timecode.take()
+    }
+
+    /// Follow a quarter frame, keeping the timecode when a cycle completes
+    fn decode_quarter_frame(&mut self, message: &MidiMessage) {
+        let MidiMessage::SystemCommon(bytes) = message else {
+            return;
+        };
+        let &[0xF1, data] = &bytes[..] else {
+            return;
+        };
+        self.quarter_frames.feed(data);
+        if data >> 4 == 7 {
+            if let Some(timecode) = self.quarter_frames.timecode() {
+                self.timecode = Some(MtcTimecode {
+                    timecode,
+                    rate: self.quarter_frames.rate(),
+                });
+            }
+        }
+    }
+
+    /// Change the longest gap between the bytes of a message
+    ///
+    /// A message not completed within it is dropped and the parser resyncs.
+    /// Slow legacy gear may need longer than `DEFAULT_BYTE_TIMEOUT`; a
+    /// shorter timeout recovers sooner from a cable pulled mid-message.
+    pub fn set_byte_timeout(&mut self, timeout: Duration) {
+        self.byte_timeout = timeout;
+    }
+
+    pub fn byte_timeout(&self) -> Duration {
+        self.byte_timeout
+    }
+
+    /// Pass SysEx too long to capture on in `SysExChunk`s
+    ///
+    /// A chunk goes out as soon as the capture buffer fills, so memory stays
+    /// bounded however long the message. SysEx short enough to capture is
+    /// still returned whole. A stream cut short by a status byte, a timeout
+    /// or a reset just stops; its last chunk never comes.
+    pub fn set_sysex_streaming(&mut self, streaming: bool) {
+        self.sysex_streaming = streaming;
+    }
+
+    /// Drop the message in progress and return to normal reading
+    ///
+    /// The diagnostic history and the resync counter are kept.
+    fn clear(&mut self) {
+        self.status = None;
+        self.data_len = 0;
+        self.expected_data_bytes = 2;
+        self.state = ParserState::Reading;
+        self.last_byte_time = None;
+        self.sysex.clear();
+        self.sysex_overflow = false;
+        self.sysex_streamed = false;
+    }
+
+    /// Forget running status after a silence longer than `expiry`
+    ///
+    /// Only between messages; a message in progress is the byte timeout's.
+    fn expire_running_status(&mut self, expiry: Duration) {
+        let now = self.clock.now();
+        let idle = self
+            .last_activity
+            .map(|last| now.saturating_duration_since(last));
+        self.last_activity = Some(now);
+        if idle.is_some_and(|idle| idle > expiry)
+            && self.status.is_none()
+            && self.data_len == 0
+            && self.running_status.take().is_some()
+        {
+            log::debug!(self.log_filter, "Running status expired");
+            self.status_rewritten = false;
+        }
+    }
+
+    /// Note the message in progress as discarded, if there is one
+    fn discard(&mut self, cause: DiscardCause) {
+        let (bytes, len) = match self.state {
+            ParserState::InSysEx => (ShortMessage::new(Some(0xF0), &[]), self.sysex.len()),
+            ParserState::Reading if self.status.is_some() || self.data_len > 0 => {
+                let bytes = ShortMessage::new(self.status, &self.data[..self.data_len]);
+                let len = bytes.len();
+                (bytes, len)
+            }
+            _ => return,
+        };
+        self.discarded = Some(Discarded { bytes, len, cause });
+    }
+
+    /// The last unfinished message dropped since the previous call
+    ///
+    /// Lets the caller see which bytes a timeout, a bad byte or `reset()`
+    /// cost, e.g. to log them with the input they came from.
+    pub fn take_discarded(&mut self) -> Option<Discarded> {
+        self.discarded.take()
+    }
+
+    /// Drop the message in progress and hunt for the next status byte
+    fn resync(&mut self, cause: DiscardCause) {
+        self.discard(cause);
+        self.clear();
+        self.running_status = None;
+        self.state = ParserState::Resyncing;
+        self.resyncs = self.resyncs.wrapping_add(1);
+        self.stats.resyncs = self.stats.resyncs.wrapping_add(1);
+        self.bad_bytes = 0;
+    }
+
+    /// Whether the policy lets this bad byte be skipped
+    ///
+    /// Counts the byte as skipped if so.
+    #[cfg_attr(not(feature = "defmt"), allow(unused_variables))]
+    fn tolerate(&mut self, byte: u8) -> bool {
+        let tolerated = match self.policy {
+            ResyncPolicy::Strict => false,
+            ResyncPolicy::Permissive => true,
+            ResyncPolicy::Budget(budget) => {
+                if self.bad_bytes < budget {
+                    self.bad_bytes += 1;
+                    true
+                } else {
+                    false
+                }
+            }
+        };
+        if tolerated {
+            log::warn!(self.log_filter, "Skipping bad byte {:#04x}", byte);
+            self.skipped = self.skipped.wrapping_add(1);
+            self.stats.skipped = self.stats.skipped.wrapping_add(1);
+        }
+        tolerated
+    }
+
+    /// Number of bad bytes skipped instead of resyncing
+    pub fn skipped_count(&self) -> u32 {
+        self.skipped
+    }
+
+    /// Number of times the parser entered resync mode
+    ///
+    /// Counts protocol errors, byte timeouts and external resets alike.
+    pub fn resync_count(&self) -> u32 {
+        self.resyncs
+    }
+
+    /// Counters since the parser was made or `reset_stats()`
+    pub fn stats(&self) -> ParserStats {
+        self.stats
+    }
+
+    /// Zero the counters of `stats()`
+    ///
+    /// `resync_count()` and `skipped_count()` keep counting.
+    pub fn reset_stats(&mut self) {
+        self.stats = ParserStats::default();
+    }
+
+    /// Reset the parser to its initial state and enter resynchronization mode
+    ///
+    /// This should be called after UART errors (Overrun, Framing, Break, Parity)
+    /// or MIDI protocol errors to prevent corrupted parser state from affecting
+    /// subsequent messages.
+    ///
+    /// Example scenario requiring reset:
+    /// 1. Parser receives 0x90 (Note On status byte)
+    /// 2. Expecting 2 data bytes next
+    /// 3. UART Overrun error occurs (bytes lost)
+    /// 4. Without reset, parser still expects data bytes
+    /// 5. Next message's status byte would be misinterpreted as data!
+    ///
+    /// After calling reset(), the parser enters resync mode where it hunts for
+    /// the next valid status byte, discarding any garbage bytes in the stream.
+    /// This allows robust recovery from corrupted byte streams. A message
+    /// cut short by the reset is left for `take_discarded()`.
+    pub fn reset(&mut self) {
+        if self.state == ParserState::Resyncing {
+            // Already resyncing after a protocol error, don't count it twice
+            self.clear();
+            self.running_status = None;
+            self.state = ParserState::Resyncing;
+        } else {
+            self.resync(DiscardCause::Reset);
+        }
+    }
+
+    /// A voice message with velocity 0 Note On turned into Note Off
+    fn normalized(&mut self, message: MidiMessage) -> MidiMessage {
+        let (status, data) = match &message {
+            MidiMessage::Voice(bytes) => (bytes[0], &bytes[1..]),
+            MidiMessage::RunningStatus(bytes) => match self.running_status {
+                Some(status) => (status, &bytes[..]),
+                None => return message,
+            },
+            _ => return message,
+        };
+        let status = match (status & 0xF0, data) {
+            (0x90, [_, 0]) => 0x80 | (status & 0x0F),
+            _ if self.status_rewritten => status,
+            _ => {
+                self.status_rewritten = false;
+                return message;
+            }
+        };
+        self.status_rewritten = status != self.running_status.unwrap_or(status);
+        MidiMessage::Voice(ShortMessage::new(Some(status), data))
+    }
+
+    /// A running status message with the status it refers to
+    fn expanded(&self, message: MidiMessage) -> MidiMessage {
+        match (&message, self.running_status) {
+            (MidiMessage::RunningStatus(data), Some(status)) => {
+                MidiMessage::Voice(ShortMessage::new(Some(status), data))
+            }
+            _ => message,
+        }
+    }
+
+    /// Number of data bytes following a status byte
+    fn data_bytes_for(status: u8) -> usize {
+        if status & 0xF0 == 0xC0 || status & 0xF0 == 0xD0 || status == 0xF1 || status == 0xF3 {
+            // 0xCx: Program change
+            // 0xDx: Channel Pressure
+            // 0xF1: MTC Quarter Frame Message
+            // 0xF3: Song Select
+            1
+        } else if status == 0xF6 {
+            // 0xF6: Tune Request
+            0
+        } else {
+            // everything else has two databytes
+            2
+        }
+    }
+
+    /// Feed a slice of received bytes
+    ///
+    /// The iterator feeds bytes as it is advanced and yields each message and
+    /// error they complete, in order. Bytes past the point where it is
+    /// dropped are not fed, `FeedBytes::consumed()` tells how far it got.
+    pub fn feed_bytes<'a>(&'a mut self, bytes: &'a [u8]) -> FeedBytes<'a, C> {
+        FeedBytes {
+            parser: self,
+            bytes,
+            consumed: 0,
+        }
+    }
+
+    /// Feed one received byte
+    ///
+    /// Returns the message it completes, if any, and counts it in `stats()`.
+    pub fn feed_byte(&mut self, byte: u8) -> Result<Option<MidiMessage>, MidiMessageError> {
+        let result = self.parse_byte(byte);
+        self.stats.record(&result);
+        result
+    }
+
+    fn parse_byte(&mut self, byte: u8) -> Result<Option<MidiMessage>, MidiMessageError> {
+        // Add byte to diagnostic buffer before any processing
+        self.diagnostic_buffer.push(byte);
+
+        // SystemRealtime messages (0xF8-0xFF) can interrupt any message without affecting
+        // parser state. They are processed immediately even during resync mode, and do NOT
+        // update the timestamp, ensuring the timeout only measures time between actual
+        // message bytes (status/data).
+        if (0xF8..=0xFF).contains(&byte) {
+            // Validate it's a defined SystemRealtime byte (not 0xF9 or 0xFD)
+            if byte == 0xF9 || byte == 0xFD {
+                match self.undefined_realtime {
+                    UndefinedRealtime::Reject => {}
+                    UndefinedRealtime::Drop => return Ok(None),
+                    UndefinedRealtime::Forward => {
+                        return Ok(Some(MidiMessage::SystemRealtime(ShortMessage::new(
+                            Some(byte),
+                            &[],
+                        ))));
+                    }
+                }
+                if self.tolerate(byte) {
+                    return Ok(None);
+                }
+                log::error!("Invalid SystemRealtime byte {:#04x}", byte);
+                self.diagnostic_buffer.log(self.log_filter);
+                self.resync(DiscardCause::Error);
+                return Err(MidiMessageError::InvalidStatusByte);
+            }
+
+            let message = MidiMessage::from_status_and_data(Some(byte), &[])?;
+            return Ok(Some(message));
+        }
+
+        if let Some(expiry) = self.running_status_expiry {
+            self.expire_running_status(expiry);
+        }
+
+        // Check if too much time elapsed since last byte (message timeout)
+        // On the first byte after startup/reset, last_byte_time is None, so no timeout
+        // is checked (correct behavior - we need at least one byte to start timing).
+        if let Some(last_time) = self.last_byte_time {
+            if self.clock.now().saturating_duration_since(last_time) > self.byte_timeout {
+                log::warn!(
+                    self.log_filter,
+                    "MIDI message timeout - entering resync mode"
+                );
+                self.stats.timeouts = self.stats.timeouts.wrapping_add(1);
+                self.diagnostic_buffer.log(self.log_filter);
+                self.resync(DiscardCause::Timeout);
+            }
+        }
+
+        // State machine: handle different parser modes
+        match self.state {
+            ParserState::Resyncing => {
+                // Resynchronization mode: hunt for a valid status byte
+                //
+                // After errors, the byte stream may be corrupted. Instead of blindly processing
+                // bytes based on corrupt expectations, actively search for the next valid status
+                // byte (synchronization point) and discard any garbage in between.
+                //
+                // This prevents cascading errors where:
+                // - Data bytes are misinterpreted as status bytes
+                // - Status bytes are misinterpreted as data bytes
+                // - Phantom messages are created from garbage bytes
+                if (byte & 0x80) == 0x80 {
+                    // Found a status byte - validate it's in legal range
+                    if byte == 0xF4 || byte == 0xF5 || (0xF9..=0xFD).contains(&byte) {
+                        // Invalid/undefined status byte, keep hunting
+                        log::debug!(
+                            self.log_filter,
+                            "Resync: discarding invalid status byte {:#x}",
+                            byte
+                        );
+                        return Ok(None);
+                    }
+
+                    // Valid status byte found - exit resync mode and process normally
+                    log::info!(
+                        self.log_filter,
+                        "Resync complete on status byte {:#x}",
+                        byte
+                    );
+                    self.state = ParserState::Reading;
+                    // Fall through to Reading state processing below
+                } else {
+                    // Still hunting for status byte, discard this data byte
+                    log::debug!(self.log_filter, "Resync: discarding data byte {:#x}", byte);
+                    return Ok(None);
+                }
+            }
+            ParserState::InSysEx => {
+                // Inside SysEx - capture data bytes until 0xF7
+                if byte == 0xF7 {
+                    let message = if self.sysex_overflow {
+                        // Too long to capture, the message is discarded
+                        None
+                    } else if self.sysex.push(byte).is_err() {
+                        None
+                    } else if self.sysex_streamed {
+                        Some(MidiMessage::SysExChunk(self.sysex.clone()))
+                    } else {
+                        Some(MidiMessage::SysEx(self.sysex.clone()))
+                    };
+                    self.clear();
+                    self.bad_bytes = 0;
+                    return Ok(message);
+                }
+
+                if (byte & 0x80) == 0 {
+                    if self.sysex.push(byte).is_err() {
+                        self.sysex_overflow = true;
+                    }
+                    self.last_byte_time = Some(self.clock.now());
+                    if self.sysex_streaming && self.sysex.is_full() {
+                        // Too long to capture whole. Filled only by data
+                        // bytes, so there is always room for 0xF7 later.
+                        let chunk = MidiMessage::SysExChunk(self.sysex.clone());
+                        self.sysex.clear();
+                        self.sysex_streamed = true;
+                        return Ok(Some(chunk));
+                    }
+                    return Ok(None);
+                }
+
+                // Any other status byte terminates an unfinished SysEx. Drop
+                // what we have and process the status byte normally below.
+                log::debug!(
+                    self.log_filter,
+                    "SysEx terminated by status byte {:#x}",
+                    byte
+                );
+                self.clear();
+            }
+            ParserState::Reading => {
+                // Normal parsing mode - continue below
+            }
+        }
+
+        // Update timestamp for this byte
+        self.last_byte_time = Some(self.clock.now());
+
+        // Handle SysEx start (0xF0)
+        if byte == 0xF0 {
+            // Discard any partial message and start capturing the SysEx.
+            // SysEx cancels running status.
+            self.clear();
+            self.running_status = None;
+            self.state = ParserState::InSysEx;
+            self.last_byte_time = Some(self.clock.now());
+            // Capacity is never exceeded right after clear()
+            let _ = self.sysex.push(byte);
+            return Ok(None);
+        }
+
+        // Handle stray SysEx end (0xF7) outside of a SysEx
+        if byte == 0xF7 {
+            self.clear();
+            self.running_status = None;
+            return Ok(None);
+        }
+
+        if (byte & 0x80) == 0x80 {
+            // status byte - validate it's in legal range
+            // Undefined status bytes: 0xF4, 0xF5, 0xF9-0xFD
+            if byte == 0xF4 || byte == 0xF5 || (0xF9..=0xFD).contains(&byte) {
+                if self.tolerate(byte) {
+                    return Ok(None);
+                }
+                log::error!("Invalid status byte {:#04x}", byte);
+                self.diagnostic_buffer.log(self.log_filter);
+                self.resync(DiscardCause::Error);
+                return Err(MidiMessageError::InvalidStatusByte);
+            }
+
+            if self.status.is_some() {
+                // A message is still in progress
+                if self.tolerate(byte) {
+                    // Abandon it and start over with this status
+                    self.clear();
+                    self.last_byte_time = Some(self.clock.now());
+                } else {
+                    log::error!("Duplicate status byte {:#04x}", byte);
+                    self.diagnostic_buffer.log(self.log_filter);
+                    self.resync(DiscardCause::Error);
+                    return Err(MidiMessageError::DuplicateStatus);
+                }
+            }
+            self.status = Some(byte);
+
+            // Voice messages set running status, System Common cancels it
+            self.running_status = (byte < 0xF0).then_some(byte);
+            self.expected_data_bytes = Self::data_bytes_for(byte);
+        } else {
+            // data byte - bit 7 is guaranteed to be 0 by the if/else structure
+            if self.status.is_none() && self.data_len == 0 {
+                // First data byte of a running status message
+                match self.running_status {
+                    Some(status) => self.expected_data_bytes = Self::data_bytes_for(status),
+                    None => {
+                        if self.tolerate(byte) {
+                            return Ok(None);
+                        }
+                        log::error!("Data byte {:#04x} without running status", byte);
+                        self.diagnostic_buffer.log(self.log_filter);
+                        self.resync(DiscardCause::Error);
+                        return Err(MidiMessageError::UnexpectedDataByte);
+                    }
+                }
+            }
+
+            if self.data_len == self.data.len() {
+                // We got more data bytes than expected, raise error
+                if self.tolerate(byte) {
+                    return Ok(None);
+                }
+                log::error!("Unexpected data byte {:#04x}", byte);
+                self.diagnostic_buffer.log(self.log_filter);
+                self.resync(DiscardCause::Error);
+                return Err(MidiMessageError::UnexpectedDataByte);
+            }
+            self.data[self.data_len] = byte;
+            self.data_len += 1;
+        }
+
+        if self.data_len == self.expected_data_bytes {
+            // we got all data bytes we expected, let's create a message and clear buffers
+            let mut message =
+                MidiMessage::from_status_and_data(self.status, &self.data[..self.data_len])?;
+            if self.normalize_note_off {
+                message = self.normalized(message);
+            }
+            if self.expand_running_status {
+                message = self.expanded(message);
+            }
+            if self.pair_cc14 {
+                self.pair_cc14(&message);
+            }
+            if self.assemble_timecode {
+                self.decode_quarter_frame(&message);
+            }
+            self.clear();
+            self.bad_bytes = 0;
+            Ok(Some(message))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Messages completed by a slice of bytes, see `MidiParser::feed_bytes`
+pub struct FeedBytes<'a, C = SystemClock> {
+    parser: &'a mut MidiParser<C>,
+    bytes: &'a [u8],
+    consumed: usize,
+}
+
+impl<C: Clock> FeedBytes<'_, C> {
+    /// Number of bytes fed to the parser so far
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+}
+
+impl<C: Clock> Iterator for FeedBytes<'_, C> {
+    type Item = Result<MidiMessage, MidiMessageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(&byte) = self.bytes.get(self.consumed) {
+            self.consumed += 1;
+            match self.parser.feed_byte(byte) {
+                Ok(Some(message)) => return Some(Ok(message)),
+                Ok(None) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,209 @@
+//! MIDI Time Code values and quarter frame decoding
+//!
+//! `Timecode` and `FrameRate` with the arithmetic between timecodes and frame
+//! counts (drop frame included), and `QuarterFrameDecoder`, which reassembles
+//! the running position from quarter frames (`F1 <data>`). The parser uses it
+//! to report complete timecodes (`MidiParser::set_assemble_timecode`); the
+//! MTC and MIDI Clock conversions in `midi_core::timecode` build on it.
+
+/// MTC frame rate, as coded in the last quarter frame
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrameRate {
+    Fps24,
+    #[default]
+    Fps25,
+    /// 29.97 frames per second, drop frame numbering
+    Fps30Drop,
+    Fps30,
+}
+
+impl FrameRate {
+    pub fn from_code(code: u8) -> Self {
+        match code & 0x03 {
+            0 => FrameRate::Fps24,
+            1 => FrameRate::Fps25,
+            2 => FrameRate::Fps30Drop,
+            _ => FrameRate::Fps30,
+        }
+    }
+
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Frame numbers per second
+    pub fn nominal(self) -> u64 {
+        match self {
+            FrameRate::Fps24 => 24,
+            FrameRate::Fps25 => 25,
+            FrameRate::Fps30Drop | FrameRate::Fps30 => 30,
+        }
+    }
+
+    /// Real time of a number of quarter frames
+    pub fn quarter_frames_to_us(self, quarters: u64) -> u64 {
+        match self {
+            // A frame lasts 1001/30000 s
+            FrameRate::Fps30Drop => quarters * 1_001_000 / 120,
+            _ => quarters * 1_000_000 / (4 * self.nominal()),
+        }
+    }
+
+    /// Quarter frames completed in a span of real time
+    pub fn us_to_quarter_frames(self, us: u64) -> u64 {
+        match self {
+            FrameRate::Fps30Drop => us * 120 / 1_001_000,
+            _ => us * 4 * self.nominal() / 1_000_000,
+        }
+    }
+}
+
+/// A timecode, hh:mm:ss:ff
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timecode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+}
+
+impl Timecode {
+    pub const fn new(hours: u8, minutes: u8, seconds: u8, frames: u8) -> Self {
+        Self {
+            hours,
+            minutes,
+            seconds,
+            frames,
+        }
+    }
+
+    /// Frames since 00:00:00:00
+    ///
+    /// With drop frame numbering, the frame numbers 00 and 01 skipped at the
+    /// start of every minute but each tenth are not counted.
+    pub fn to_frames(self, rate: FrameRate) -> u64 {
+        let seconds = self.hours as u64 * 3600 + self.minutes as u64 * 60 + self.seconds as u64;
+        let frames = seconds * rate.nominal() + self.frames as u64;
+        if rate == FrameRate::Fps30Drop {
+            let minutes = self.hours as u64 * 60 + self.minutes as u64;
+            frames - 2 * (minutes - minutes / 10)
+        } else {
+            frames
+        }
+    }
+
+    /// The timecode of a frame count, wrapping after 24 hours
+    pub fn from_frames(frames: u64, rate: FrameRate) -> Self {
+        let mut frames = frames;
+        if rate == FrameRate::Fps30Drop {
+            // 17982 frames per ten minutes, 1798 per minute after the first
+            let tens = frames / 17982;
+            let rest = frames % 17982;
+            frames += 18 * tens + if rest > 1 { 2 * ((rest - 2) / 1798) } else { 0 };
+        }
+        let fps = rate.nominal();
+        Self {
+            hours: (frames / (fps * 3600) % 24) as u8,
+            minutes: (frames / (fps * 60) % 60) as u8,
+            seconds: (frames / fps % 60) as u8,
+            frames: (frames % fps) as u8,
+        }
+    }
+}
+
+/// A complete timecode assembled from quarter frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MtcTimecode {
+    /// The frame current when the last piece arrived
+    pub timecode: Timecode,
+    pub rate: FrameRate,
+}
+
+/// Data byte of one piece of a timecode's quarter frames (`F1 <data>`)
+pub fn quarter_frame_data(timecode: Timecode, rate: FrameRate, piece: u8) -> u8 {
+    let value = match piece & 0x07 {
+        0 => timecode.frames & 0x0F,
+        1 => timecode.frames >> 4,
+        2 => timecode.seconds & 0x0F,
+        3 => timecode.seconds >> 4,
+        4 => timecode.minutes & 0x0F,
+        5 => timecode.minutes >> 4,
+        6 => timecode.hours & 0x0F,
+        _ => (timecode.hours >> 4) & 0x01 | rate.code() << 1,
+    };
+    (piece & 0x07) << 4 | value
+}
+
+/// Reassembles the timecode from running quarter frames
+///
+/// The eight pieces of a timecode take two frames to arrive. Once all eight
+/// have come in order, the decoder is locked and follows the position one
+/// quarter frame at a time; every complete cycle re-reads it, so jumps are
+/// picked up within two frames. A piece out of order drops the lock.
+#[derive(Debug, Default)]
+pub struct QuarterFrameDecoder {
+    pieces: [u8; 8],
+    /// Bit per piece received in the current cycle
+    received: u8,
+    last_piece: Option<u8>,
+    /// Position in quarter frames since 00:00:00:00
+    position: Option<u64>,
+    rate: FrameRate,
+}
+
+impl QuarterFrameDecoder {
+    /// Take the data byte of a quarter frame (`F1 <data>`)
+    ///
+    /// Returns the position in quarter frames when locked.
+    pub fn feed(&mut self, data: u8) -> Option<u64> {
+        let piece = (data >> 4) & 0x07;
+        if self.last_piece != Some((piece + 7) % 8) {
+            self.received = 0;
+            self.position = None;
+        }
+        self.last_piece = Some(piece);
+        if piece == 0 {
+            self.received = 0;
+        }
+        self.pieces[piece as usize] = data & 0x0F;
+        self.received |= 1 << piece;
+        if let Some(position) = &mut self.position {
+            *position += 1;
+        }
+
+        if piece == 7 && self.received == 0xFF {
+            let pieces = &self.pieces;
+            self.rate = FrameRate::from_code(pieces[7] >> 1);
+            let timecode = Timecode {
+                hours: ((pieces[7] & 0x01) << 4) | pieces[6],
+                minutes: (pieces[5] << 4) | pieces[4],
+                seconds: (pieces[3] << 4) | pieces[2],
+                frames: (pieces[1] << 4) | pieces[0],
+            };
+            // The timecode was current at piece 0, seven quarters ago
+            self.position = Some(timecode.to_frames(self.rate) * 4 + 7);
+        }
+        self.position
+    }
+
+    pub fn position(&self) -> Option<u64> {
+        self.position
+    }
+
+    /// Timecode of the current frame, when locked
+    pub fn timecode(&self) -> Option<Timecode> {
+        Some(Timecode::from_frames(self.position? / 4, self.rate))
+    }
+
+    /// Frame rate of the last complete timecode
+    pub fn rate(&self) -> FrameRate {
+        self.rate
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
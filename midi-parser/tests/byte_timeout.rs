@@ -0,0 +1,107 @@
+//! The byte timeout, on a clock the tests move by hand
+
+mod common;
+
+use common::{paced, ManualClock};
+use embassy_time::Duration;
+use midi_parser::MidiParser;
+
+#[test]
+fn default_timeout() {
+    assert_eq!(
+        MidiParser::default().byte_timeout(),
+        MidiParser::DEFAULT_BYTE_TIMEOUT
+    );
+}
+
+#[test]
+fn pause_within_the_timeout_completes_the_message() {
+    let clock = ManualClock::default();
+    let mut parser = MidiParser::with_clock(clock.clone());
+    assert_eq!(
+        paced(&mut parser, &clock, &[(0, 0x90), (300, 0x3C), (300, 0x64)]),
+        [vec![0x90, 0x3C, 0x64]]
+    );
+    assert_eq!(parser.resync_count(), 0);
+}
+
+#[test]
+fn pause_past_the_timeout_drops_the_message() {
+    let clock = ManualClock::default();
+    let mut parser = MidiParser::with_clock(clock.clone());
+    // The data bytes are discarded while resyncing, the next status recovers
+    assert_eq!(
+        paced(
+            &mut parser,
+            &clock,
+            &[
+                (0, 0x90),
+                (301, 0x3C),
+                (0, 0x64),
+                (0, 0x90),
+                (0, 0x3E),
+                (0, 0x64)
+            ]
+        ),
+        [vec![0x90, 0x3E, 0x64]]
+    );
+    assert_eq!(parser.resync_count(), 1);
+    assert_eq!(parser.stats().timeouts, 1);
+}
+
+#[test]
+fn realtime_does_not_restart_the_timeout() {
+    let clock = ManualClock::default();
+    let mut parser = MidiParser::with_clock(clock.clone());
+    assert_eq!(
+        paced(
+            &mut parser,
+            &clock,
+            &[(0, 0x90), (200, 0xF8), (200, 0x3C), (0, 0x64)]
+        ),
+        [vec![0xF8]]
+    );
+}
+
+#[test]
+fn configured_timeout() {
+    let clock = ManualClock::default();
+    let mut parser = MidiParser::with_clock(clock.clone());
+    parser.set_byte_timeout(Duration::from_secs(2));
+    assert_eq!(
+        paced(
+            &mut parser,
+            &clock,
+            &[(0, 0x90), (1500, 0x3C), (1500, 0x64)]
+        ),
+        [vec![0x90, 0x3C, 0x64]]
+    );
+
+    parser.set_byte_timeout(Duration::from_millis(5));
+    assert_eq!(
+        paced(&mut parser, &clock, &[(0, 0x90), (10, 0x3C), (0, 0x64)]),
+        Vec::<Vec<u8>>::new()
+    );
+    assert_eq!(parser.resync_count(), 1);
+}
+
+#[test]
+fn silence_between_messages_is_not_a_timeout() {
+    let clock = ManualClock::default();
+    let mut parser = MidiParser::with_clock(clock.clone());
+    assert_eq!(
+        paced(
+            &mut parser,
+            &clock,
+            &[
+                (0, 0x90),
+                (0, 0x3C),
+                (0, 0x64),
+                (60_000, 0x90),
+                (0, 0x3E),
+                (0, 0x64)
+            ]
+        ),
+        [vec![0x90, 0x3C, 0x64], vec![0x90, 0x3E, 0x64]]
+    );
+}
@@ -0,0 +1,88 @@
+//! 14-bit controllers from paired Control Change messages
+
+mod common;
+
+use common::{bytes, each_message};
+use midi_parser::event::ControlChange14;
+use midi_parser::MidiParser;
+
+/// 14-bit controllers taken after each message of the bytes
+fn cc14(parser: &mut MidiParser, bytes: &[u8]) -> Vec<ControlChange14> {
+    let mut cc14 = Vec::new();
+    each_message(parser, bytes, |parser, _| cc14.extend(parser.take_cc14()));
+    cc14
+}
+
+#[test]
+fn msb_then_lsb() {
+    let stream = [0xB3, 0x02, 0x40, 0xB3, 0x22, 0x10];
+    let mut parser = MidiParser::default();
+    // Off by default
+    assert_eq!(cc14(&mut parser, &stream), []);
+
+    parser.set_pair_cc14(true);
+    let mut paired = Vec::new();
+    let mut messages = Vec::new();
+    each_message(&mut parser, &stream, |parser, message| {
+        paired.extend(parser.take_cc14());
+        messages.push(message);
+    });
+    // The bytes pass unchanged
+    assert_eq!(
+        bytes(&messages),
+        [vec![0xB3, 0x02, 0x40], vec![0xB3, 0x22, 0x10]]
+    );
+    assert_eq!(
+        paired,
+        [ControlChange14 {
+            channel: 3,
+            controller: 2,
+            value: 0x40 << 7 | 0x10
+        }]
+    );
+}
+
+#[test]
+fn pairs_under_running_status() {
+    let mut parser = MidiParser::default();
+    parser.set_pair_cc14(true);
+    assert_eq!(
+        cc14(&mut parser, &[0xB0, 0x0B, 0x7F, 0x2B, 0x7F]),
+        [ControlChange14 {
+            channel: 0,
+            controller: 11,
+            value: 0x3FFF
+        }]
+    );
+}
+
+#[test]
+fn lsb_updates_keep_the_msb() {
+    let mut parser = MidiParser::default();
+    parser.set_pair_cc14(true);
+    let values: Vec<u16> = cc14(&mut parser, &[0xB0, 0x02, 0x01, 0x22, 0x00, 0x22, 0x7F])
+        .iter()
+        .map(|cc| cc.value)
+        .collect();
+    assert_eq!(values, [0x80, 0xFF]);
+}
+
+#[test]
+fn lsb_of_another_controller_or_channel_pairs_with_nothing() {
+    let mut parser = MidiParser::default();
+    parser.set_pair_cc14(true);
+    assert_eq!(
+        cc14(
+            &mut parser,
+            &[0xB0, 0x02, 0x40, 0xB0, 0x27, 0x10, 0xB1, 0x22, 0x10]
+        ),
+        []
+    );
+}
+
+#[test]
+fn controllers_above_63_are_ignored() {
+    let mut parser = MidiParser::default();
+    parser.set_pair_cc14(true);
+    assert_eq!(cc14(&mut parser, &[0xB0, 0x07, 0x40, 0xB0, 0x47, 0x10]), []);
+}
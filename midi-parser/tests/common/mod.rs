@@ -0,0 +1,80 @@
+//! Helpers shared by the parser tests
+//!
+//! Each test file uses its own subset of them.
+#![allow(dead_code)]
+
+use embassy_time::{Duration, Instant};
+use midi_parser::clock::Clock;
+use midi_parser::{MidiMessage, MidiParser};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Clock the tests move by hand, shared by its clones
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock(Rc<Cell<u64>>);
+
+impl ManualClock {
+    pub fn advance(&self, duration: Duration) {
+        self.0.set(self.0.get() + duration.as_ticks());
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        Instant::from_ticks(self.0.get())
+    }
+}
+
+/// Feed the bytes, calling `after` with each message completed
+///
+/// Panics on a parse error.
+pub fn each_message<C: Clock>(
+    parser: &mut MidiParser<C>,
+    bytes: &[u8],
+    mut after: impl FnMut(&mut MidiParser<C>, MidiMessage),
+) {
+    for byte in bytes {
+        if let Some(message) = parser.feed_byte(*byte).unwrap() {
+            after(parser, message);
+        }
+    }
+}
+
+/// Messages completed by the bytes, panics on a parse error
+pub fn messages<C: Clock>(parser: &mut MidiParser<C>, bytes: &[u8]) -> Vec<MidiMessage> {
+    let mut messages = Vec::new();
+    each_message(parser, bytes, |_, message| messages.push(message));
+    messages
+}
+
+/// Feed the bytes, whatever they complete or fail
+pub fn feed<C: Clock>(parser: &mut MidiParser<C>, bytes: &[u8]) {
+    for byte in bytes {
+        let _ = parser.feed_byte(*byte);
+    }
+}
+
+/// Bytes fed with a pause in milliseconds before each, and the messages they
+/// completed; errors are skipped
+pub fn paced(
+    parser: &mut MidiParser<ManualClock>,
+    clock: &ManualClock,
+    bytes: &[(u64, u8)],
+) -> Vec<Vec<u8>> {
+    bytes
+        .iter()
+        .filter_map(|&(pause_ms, byte)| {
+            clock.advance(Duration::from_millis(pause_ms));
+            parser.feed_byte(byte).ok().flatten()
+        })
+        .map(|message| message.bytes().to_vec())
+        .collect()
+}
+
+/// Bytes of each message
+pub fn bytes(messages: &[MidiMessage]) -> Vec<Vec<u8>> {
+    messages
+        .iter()
+        .map(|message| message.bytes().to_vec())
+        .collect()
+}
@@ -0,0 +1,486 @@
+//! MIDI 1.0 conformance corpus for `MidiParser`
+//!
+//! Pins down the parser's behavior for every status byte and message length
+//! of the spec, running status, interleaved realtime, undefined bytes and
+//! SysEx edge cases, instead of leaving it implied by the implementation.
+
+use midi_parser::{MidiMessage, MidiMessageError, MidiParser, SYSEX_CAPTURE_LEN};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Voice,
+    RunningStatus,
+    SystemCommon,
+    SystemRealtime,
+    SysEx,
+    SysExChunk,
+}
+
+/// What one byte of a stream produced
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Out {
+    Message(Kind, Vec<u8>),
+    Error(MidiMessageError),
+}
+
+fn kind(message: &MidiMessage) -> Kind {
+    match message {
+        MidiMessage::Voice(_) => Kind::Voice,
+        MidiMessage::RunningStatus(_) => Kind::RunningStatus,
+        MidiMessage::SystemCommon(_) => Kind::SystemCommon,
+        MidiMessage::SystemRealtime(_) => Kind::SystemRealtime,
+        MidiMessage::SysEx(_) => Kind::SysEx,
+        MidiMessage::SysExChunk(_) => Kind::SysExChunk,
+    }
+}
+
+/// Everything a fresh parser produces for a stream, without resetting it
+/// after errors
+fn run(bytes: &[u8]) -> Vec<Out> {
+    run_with(MidiParser::default(), bytes)
+}
+
+fn run_with(mut parser: MidiParser, bytes: &[u8]) -> Vec<Out> {
+    bytes
+        .iter()
+        .filter_map(|byte| match parser.feed_byte(*byte) {
+            Ok(Some(message)) => Some(Out::Message(kind(&message), message.bytes().to_vec())),
+            Ok(None) => None,
+            Err(err) => Some(Out::Error(err)),
+        })
+        .collect()
+}
+
+fn message(kind: Kind, bytes: &[u8]) -> Out {
+    Out::Message(kind, bytes.to_vec())
+}
+
+/// Data bytes that follow a status byte in the spec, `None` for undefined
+/// status bytes and for SysEx, whose length is open
+fn spec_length(status: u8) -> Option<usize> {
+    match status {
+        0x80..=0xBF | 0xE0..=0xEF => Some(2),
+        0xC0..=0xDF => Some(1),
+        0xF1 | 0xF3 => Some(1),
+        0xF2 => Some(2),
+        0xF6 => Some(0),
+        0xF8 | 0xFA..=0xFC | 0xFE | 0xFF => Some(0),
+        _ => None,
+    }
+}
+
+fn spec_kind(status: u8) -> Kind {
+    match status {
+        0x80..=0xEF => Kind::Voice,
+        0xF0 => Kind::SysEx,
+        0xF1..=0xF7 => Kind::SystemCommon,
+        _ => Kind::SystemRealtime,
+    }
+}
+
+// Status bytes and lengths
+
+#[test]
+fn every_defined_status_with_its_length() {
+    for status in 0x80..=0xFFu8 {
+        let Some(length) = spec_length(status) else {
+            continue;
+        };
+        let bytes: Vec<u8> = [status]
+            .into_iter()
+            .chain(0x10..0x10 + length as u8)
+            .collect();
+        let out = run(&bytes);
+        assert_eq!(
+            out,
+            [message(spec_kind(status), &bytes)],
+            "status {:#04x}",
+            status
+        );
+    }
+}
+
+#[test]
+fn incomplete_messages_emit_nothing() {
+    for status in 0x80..=0xF6u8 {
+        let Some(length) = spec_length(status) else {
+            continue;
+        };
+        for received in 0..length {
+            let bytes: Vec<u8> = [status]
+                .into_iter()
+                .chain(0x10..0x10 + received as u8)
+                .collect();
+            assert_eq!(run(&bytes), [], "{:02x?}", bytes);
+        }
+    }
+}
+
+#[test]
+fn undefined_status_bytes_are_errors() {
+    for status in [0xF4, 0xF5, 0xF9, 0xFD] {
+        assert_eq!(
+            run(&[status]),
+            [Out::Error(MidiMessageError::InvalidStatusByte)],
+            "status {:#04x}",
+            status
+        );
+    }
+}
+
+#[test]
+fn undefined_status_bytes_are_errors_inside_messages() {
+    for status in [0xF4, 0xF5, 0xF9, 0xFD] {
+        assert_eq!(
+            run(&[0x90, 0x3C, status, 0x64]),
+            [Out::Error(MidiMessageError::InvalidStatusByte)],
+            "status {:#04x}",
+            status
+        );
+    }
+}
+
+#[test]
+fn status_byte_before_message_completes() {
+    assert_eq!(
+        run(&[0x90, 0x3C, 0x80, 0x3C, 0x00]),
+        [Out::Error(MidiMessageError::DuplicateStatus)]
+    );
+    assert_eq!(
+        run(&[0xF2, 0x10, 0xF3, 0x01]),
+        [Out::Error(MidiMessageError::DuplicateStatus)]
+    );
+}
+
+#[test]
+fn data_byte_without_status() {
+    assert_eq!(
+        run(&[0x3C, 0x64]),
+        [Out::Error(MidiMessageError::UnexpectedDataByte)]
+    );
+}
+
+// Running status
+
+#[test]
+fn running_status_for_every_voice_length() {
+    for status in 0x80..=0xEFu8 {
+        let length = spec_length(status).unwrap();
+        let first: Vec<u8> = [status]
+            .into_iter()
+            .chain(0x10..0x10 + length as u8)
+            .collect();
+        let data: Vec<u8> = (0x20..0x20 + length as u8).collect();
+        let mut bytes = first.clone();
+        bytes.extend(&data);
+        bytes.extend(&data);
+        assert_eq!(
+            run(&bytes),
+            [
+                message(Kind::Voice, &first),
+                message(Kind::RunningStatus, &data),
+                message(Kind::RunningStatus, &data),
+            ],
+            "status {:#04x}",
+            status
+        );
+    }
+}
+
+#[test]
+fn new_status_replaces_running_status() {
+    assert_eq!(
+        run(&[0x90, 0x3C, 0x64, 0xC1, 0x05, 0x06]),
+        [
+            message(Kind::Voice, &[0x90, 0x3C, 0x64]),
+            message(Kind::Voice, &[0xC1, 0x05]),
+            message(Kind::RunningStatus, &[0x06]),
+        ]
+    );
+}
+
+#[test]
+fn system_common_cancels_running_status() {
+    for common in [
+        &[0xF1, 0x10][..],
+        &[0xF2, 0x10, 0x20],
+        &[0xF3, 0x01],
+        &[0xF6],
+    ] {
+        let mut bytes = vec![0x90, 0x3C, 0x64];
+        bytes.extend(common);
+        bytes.push(0x3E);
+        assert_eq!(
+            run(&bytes),
+            [
+                message(Kind::Voice, &[0x90, 0x3C, 0x64]),
+                message(Kind::SystemCommon, common),
+                Out::Error(MidiMessageError::UnexpectedDataByte),
+            ],
+            "{:02x?}",
+            common
+        );
+    }
+}
+
+#[test]
+fn system_common_has_no_running_status() {
+    assert_eq!(
+        run(&[0xF3, 0x01, 0x02]),
+        [
+            message(Kind::SystemCommon, &[0xF3, 0x01]),
+            Out::Error(MidiMessageError::UnexpectedDataByte),
+        ]
+    );
+}
+
+#[test]
+fn sysex_cancels_running_status() {
+    assert_eq!(
+        run(&[0x90, 0x3C, 0x64, 0xF0, 0x7D, 0xF7, 0x3E, 0x64]),
+        [
+            message(Kind::Voice, &[0x90, 0x3C, 0x64]),
+            message(Kind::SysEx, &[0xF0, 0x7D, 0xF7]),
+            Out::Error(MidiMessageError::UnexpectedDataByte),
+        ]
+    );
+}
+
+#[test]
+fn realtime_keeps_running_status() {
+    for realtime in [0xF8, 0xFA, 0xFB, 0xFC, 0xFE, 0xFF] {
+        assert_eq!(
+            run(&[0x90, 0x3C, 0x64, realtime, 0x3E, 0x64]),
+            [
+                message(Kind::Voice, &[0x90, 0x3C, 0x64]),
+                message(Kind::SystemRealtime, &[realtime]),
+                message(Kind::RunningStatus, &[0x3E, 0x64]),
+            ],
+            "realtime {:#04x}",
+            realtime
+        );
+    }
+}
+
+// Interleaved realtime
+
+#[test]
+fn realtime_between_any_two_bytes() {
+    let message_bytes = [0x90, 0x3C, 0x64];
+    for position in 0..=message_bytes.len() {
+        let mut bytes = message_bytes.to_vec();
+        bytes.insert(position, 0xF8);
+        let mut expected = vec![message(Kind::SystemRealtime, &[0xF8])];
+        let voice = message(Kind::Voice, &message_bytes);
+        if position == message_bytes.len() {
+            expected.insert(0, voice);
+        } else {
+            expected.push(voice);
+        }
+        assert_eq!(run(&bytes), expected, "position {}", position);
+    }
+}
+
+#[test]
+fn realtime_inside_running_status_message() {
+    assert_eq!(
+        run(&[0xB0, 0x07, 0x10, 0x07, 0xFE, 0x20]),
+        [
+            message(Kind::Voice, &[0xB0, 0x07, 0x10]),
+            message(Kind::SystemRealtime, &[0xFE]),
+            message(Kind::RunningStatus, &[0x07, 0x20]),
+        ]
+    );
+}
+
+#[test]
+fn realtime_inside_system_common() {
+    assert_eq!(
+        run(&[0xF2, 0x10, 0xFA, 0x20]),
+        [
+            message(Kind::SystemRealtime, &[0xFA]),
+            message(Kind::SystemCommon, &[0xF2, 0x10, 0x20]),
+        ]
+    );
+}
+
+#[test]
+fn realtime_inside_sysex() {
+    assert_eq!(
+        run(&[0xF0, 0x7D, 0xF8, 0x01, 0xF7]),
+        [
+            message(Kind::SystemRealtime, &[0xF8]),
+            message(Kind::SysEx, &[0xF0, 0x7D, 0x01, 0xF7]),
+        ]
+    );
+}
+
+// SysEx
+
+#[test]
+fn empty_sysex() {
+    assert_eq!(run(&[0xF0, 0xF7]), [message(Kind::SysEx, &[0xF0, 0xF7])]);
+}
+
+#[test]
+fn longest_captured_sysex() {
+    let mut bytes = vec![0xF0];
+    bytes.extend((0..SYSEX_CAPTURE_LEN as u8 - 2).map(|i| i & 0x7F));
+    bytes.push(0xF7);
+    assert_eq!(bytes.len(), SYSEX_CAPTURE_LEN);
+    assert_eq!(run(&bytes), [message(Kind::SysEx, &bytes)]);
+}
+
+#[test]
+fn sysex_too_long_to_capture_is_dropped() {
+    for extra in [1, 2, 100] {
+        let mut bytes = vec![0xF0];
+        bytes.extend(std::iter::repeat_n(0x01, SYSEX_CAPTURE_LEN - 2 + extra));
+        bytes.push(0xF7);
+        bytes.extend([0x90, 0x3C, 0x64]);
+        assert_eq!(
+            run(&bytes),
+            [message(Kind::Voice, &[0x90, 0x3C, 0x64])],
+            "{} bytes over",
+            extra
+        );
+    }
+}
+
+fn streaming() -> MidiParser {
+    let mut parser = MidiParser::default();
+    parser.set_sysex_streaming(true);
+    parser
+}
+
+#[test]
+fn streaming_leaves_short_sysex_whole() {
+    let mut bytes = vec![0xF0];
+    bytes.extend(std::iter::repeat_n(0x01, SYSEX_CAPTURE_LEN - 2));
+    bytes.push(0xF7);
+    assert_eq!(
+        run_with(streaming(), &bytes),
+        [message(Kind::SysEx, &bytes)]
+    );
+}
+
+#[test]
+fn long_sysex_is_streamed_in_chunks() {
+    for extra in [1, 2, SYSEX_CAPTURE_LEN, 100] {
+        let mut bytes = vec![0xF0];
+        bytes.extend((0..SYSEX_CAPTURE_LEN - 2 + extra).map(|i| i as u8 & 0x7F));
+        bytes.push(0xF7);
+        let chunks = run_with(streaming(), &bytes);
+        let mut streamed = Vec::new();
+        for (i, out) in chunks.iter().enumerate() {
+            let Out::Message(Kind::SysExChunk, chunk) = out else {
+                panic!("{:?} is not a chunk", out);
+            };
+            assert!(chunk.len() <= SYSEX_CAPTURE_LEN);
+            if i + 1 < chunks.len() {
+                assert_eq!(chunk.len(), SYSEX_CAPTURE_LEN, "only the last is short");
+            }
+            streamed.extend_from_slice(chunk);
+        }
+        assert_eq!(streamed, bytes, "{} bytes over", extra);
+    }
+}
+
+#[test]
+fn realtime_inside_streamed_sysex() {
+    let mut bytes = vec![0xF0];
+    bytes.extend(std::iter::repeat_n(0x01, SYSEX_CAPTURE_LEN - 1));
+    bytes.extend([0xF8, 0x02, 0xF7]);
+    let mut first = vec![0xF0];
+    first.extend(std::iter::repeat_n(0x01, SYSEX_CAPTURE_LEN - 1));
+    assert_eq!(
+        run_with(streaming(), &bytes),
+        [
+            message(Kind::SysExChunk, &first),
+            message(Kind::SystemRealtime, &[0xF8]),
+            message(Kind::SysExChunk, &[0x02, 0xF7]),
+        ]
+    );
+}
+
+#[test]
+fn streamed_sysex_terminated_by_status_byte() {
+    // The chunks so far are out, the rest is dropped
+    let mut bytes = vec![0xF0];
+    bytes.extend(std::iter::repeat_n(0x01, SYSEX_CAPTURE_LEN + 3));
+    bytes.extend([0x90, 0x3C, 0x64]);
+    assert_eq!(
+        run_with(streaming(), &bytes),
+        [
+            message(Kind::SysExChunk, &bytes[..SYSEX_CAPTURE_LEN]),
+            message(Kind::Voice, &[0x90, 0x3C, 0x64]),
+        ]
+    );
+}
+
+#[test]
+fn sysex_terminated_by_status_byte() {
+    // The unfinished SysEx is dropped, the status byte starts a message
+    assert_eq!(
+        run(&[0xF0, 0x7D, 0x01, 0x90, 0x3C, 0x64]),
+        [message(Kind::Voice, &[0x90, 0x3C, 0x64])]
+    );
+    assert_eq!(
+        run(&[0xF0, 0x7D, 0xF0, 0x01, 0xF7]),
+        [message(Kind::SysEx, &[0xF0, 0x01, 0xF7])]
+    );
+}
+
+#[test]
+fn sysex_terminated_by_undefined_status_byte() {
+    assert_eq!(
+        run(&[0xF0, 0x7D, 0xF4, 0xF7]),
+        [Out::Error(MidiMessageError::InvalidStatusByte)]
+    );
+}
+
+#[test]
+fn stray_end_of_exclusive_is_ignored() {
+    assert_eq!(
+        run(&[0xF7, 0x90, 0x3C, 0x64]),
+        [message(Kind::Voice, &[0x90, 0x3C, 0x64])]
+    );
+}
+
+#[test]
+fn stray_end_of_exclusive_cancels_running_status() {
+    assert_eq!(
+        run(&[0x90, 0x3C, 0x64, 0xF7, 0x3E, 0x64]),
+        [
+            message(Kind::Voice, &[0x90, 0x3C, 0x64]),
+            Out::Error(MidiMessageError::UnexpectedDataByte),
+        ]
+    );
+}
+
+// Recovery
+
+#[test]
+fn resync_skips_to_next_status_byte() {
+    // After the error the data bytes of the broken message are discarded,
+    // and so are undefined status bytes
+    assert_eq!(
+        run(&[0x90, 0x3C, 0x91, 0x40, 0x64, 0xF5, 0x40, 0x80, 0x3C, 0x00]),
+        [
+            Out::Error(MidiMessageError::DuplicateStatus),
+            message(Kind::Voice, &[0x80, 0x3C, 0x00]),
+        ]
+    );
+}
+
+#[test]
+fn realtime_passes_during_resync() {
+    assert_eq!(
+        run(&[0x3C, 0xF8, 0x64, 0x90, 0x3C, 0x64]),
+        [
+            Out::Error(MidiMessageError::UnexpectedDataByte),
+            message(Kind::SystemRealtime, &[0xF8]),
+            message(Kind::Voice, &[0x90, 0x3C, 0x64]),
+        ]
+    );
+}
@@ -0,0 +1,85 @@
+//! Unfinished messages the parser drops, reported with why
+
+mod common;
+
+use common::{feed, ManualClock};
+use embassy_time::Duration;
+use midi_parser::{DiscardCause, Discarded, MidiParser, ShortMessage};
+
+fn discarded(bytes: &[u8], len: usize, cause: DiscardCause) -> Option<Discarded> {
+    Some(Discarded {
+        bytes: ShortMessage::from_slice(bytes).unwrap(),
+        len,
+        cause,
+    })
+}
+
+#[test]
+fn reset_reports_the_partial_message() {
+    let mut parser = MidiParser::default();
+    feed(&mut parser, &[0x90, 0x3C]);
+    parser.reset();
+    assert_eq!(
+        parser.take_discarded(),
+        discarded(&[0x90, 0x3C], 2, DiscardCause::Reset)
+    );
+    // Taken once
+    assert_eq!(parser.take_discarded(), None);
+}
+
+#[test]
+fn reset_between_messages_discards_nothing() {
+    let mut parser = MidiParser::default();
+    feed(&mut parser, &[0x90, 0x3C, 0x64]);
+    parser.reset();
+    assert_eq!(parser.take_discarded(), None);
+}
+
+#[test]
+fn running_status_partial_has_only_data() {
+    let mut parser = MidiParser::default();
+    feed(&mut parser, &[0x90, 0x3C, 0x64, 0x3E]);
+    parser.reset();
+    assert_eq!(
+        parser.take_discarded(),
+        discarded(&[0x3E], 1, DiscardCause::Reset)
+    );
+}
+
+#[test]
+fn timeout_reports_the_partial_message() {
+    let clock = ManualClock::default();
+    let mut parser = MidiParser::with_clock(clock.clone());
+    feed(&mut parser, &[0xB0, 0x07]);
+    clock.advance(MidiParser::DEFAULT_BYTE_TIMEOUT + Duration::from_millis(1));
+    feed(&mut parser, &[0x64]);
+    assert_eq!(
+        parser.take_discarded(),
+        discarded(&[0xB0, 0x07], 2, DiscardCause::Timeout)
+    );
+}
+
+#[test]
+fn bad_byte_reports_the_partial_message() {
+    let mut parser = MidiParser::default();
+    // A second status byte before the data is complete
+    feed(&mut parser, &[0x90, 0x3C, 0xF4]);
+    assert_eq!(
+        parser.take_discarded(),
+        discarded(&[0x90, 0x3C], 2, DiscardCause::Error)
+    );
+    // The reset after the error has nothing more to drop
+    parser.reset();
+    assert_eq!(parser.take_discarded(), None);
+}
+
+#[test]
+fn sysex_reports_the_bytes_held() {
+    let mut parser = MidiParser::default();
+    feed(&mut parser, &[0xF0, 0x7D, 0x01, 0x02]);
+    parser.reset();
+    assert_eq!(
+        parser.take_discarded(),
+        discarded(&[0xF0], 4, DiscardCause::Reset)
+    );
+}
@@ -0,0 +1,169 @@
+//! Decoding parsed messages into events
+
+use midi_parser::event::{Event, Kind};
+use midi_parser::{MidiMessage, MidiParser, ShortMessage};
+
+/// Events of a stream, running status resolved as the merger does
+fn events(bytes: &[u8]) -> Vec<Option<Event>> {
+    let mut parser = MidiParser::default();
+    let mut status = None;
+    bytes
+        .iter()
+        .filter_map(|byte| parser.feed_byte(*byte).unwrap())
+        .map(|message| {
+            match &message {
+                MidiMessage::Voice(data) => status = Some(data[0]),
+                MidiMessage::SystemCommon(_) | MidiMessage::SysEx(_) => status = None,
+                _ => {}
+            }
+            message.event(status)
+        })
+        .collect()
+}
+
+#[test]
+fn channel_voice_messages() {
+    assert_eq!(
+        events(&[
+            0x80, 0x3C, 0x40, 0x91, 0x3C, 0x64, 0xA2, 0x3C, 0x10, 0xB3, 0x07, 0x7F, 0xC4, 0x05,
+            0xD5, 0x20, 0xEF, 0x00, 0x40,
+        ]),
+        [
+            Some(Event::NoteOff {
+                channel: 0,
+                note: 0x3C,
+                velocity: 0x40
+            }),
+            Some(Event::NoteOn {
+                channel: 1,
+                note: 0x3C,
+                velocity: 0x64
+            }),
+            Some(Event::PolyPressure {
+                channel: 2,
+                note: 0x3C,
+                pressure: 0x10
+            }),
+            Some(Event::ControlChange {
+                channel: 3,
+                controller: 7,
+                value: 0x7F
+            }),
+            Some(Event::ProgramChange {
+                channel: 4,
+                program: 5
+            }),
+            Some(Event::ChannelPressure {
+                channel: 5,
+                pressure: 0x20
+            }),
+            Some(Event::PitchBend {
+                channel: 15,
+                value: 8192
+            }),
+        ]
+    );
+}
+
+#[test]
+fn running_status_takes_the_cached_status() {
+    assert_eq!(
+        events(&[0x90, 0x3C, 0x64, 0x3E, 0x00]),
+        [
+            Some(Event::NoteOn {
+                channel: 0,
+                note: 0x3C,
+                velocity: 0x64
+            }),
+            Some(Event::NoteOn {
+                channel: 0,
+                note: 0x3E,
+                velocity: 0
+            }),
+        ]
+    );
+    let message = MidiMessage::RunningStatus(ShortMessage::from_slice(&[0x3E, 0x00]).unwrap());
+    assert_eq!(message.event(None), None);
+}
+
+#[test]
+fn system_messages() {
+    assert_eq!(
+        events(&[
+            0xF1, 0x25, 0xF2, 0x7F, 0x01, 0xF3, 0x03, 0xF6, 0xF8, 0xFA, 0xFB, 0xFC, 0xFE, 0xFF
+        ]),
+        [
+            Some(Event::QuarterFrame(0x25)),
+            Some(Event::SongPosition(0xFF)),
+            Some(Event::SongSelect(3)),
+            Some(Event::TuneRequest),
+            Some(Event::Clock),
+            Some(Event::Start),
+            Some(Event::Continue),
+            Some(Event::Stop),
+            Some(Event::ActiveSensing),
+            Some(Event::Reset),
+        ]
+    );
+}
+
+#[test]
+fn sysex_has_no_event() {
+    assert_eq!(events(&[0xF0, 0x7D, 0x01, 0xF7]), [None]);
+}
+
+#[test]
+fn wrong_data_length_has_no_event() {
+    assert_eq!(Event::decode(0x90, &[0x3C]), None);
+    assert_eq!(Event::decode(0xC0, &[0x01, 0x02]), None);
+    assert_eq!(Event::decode(0xF8, &[0x00]), None);
+    assert_eq!(Event::decode(0xF4, &[]), None);
+}
+
+fn message(bytes: &[u8]) -> MidiMessage {
+    let mut parser = MidiParser::default();
+    bytes
+        .iter()
+        .filter_map(|byte| parser.feed_byte(*byte).unwrap())
+        .last()
+        .unwrap()
+}
+
+#[test]
+fn accessors_of_a_voice_message() {
+    let message = message(&[0xB3, 0x07, 0x64]);
+    assert_eq!(message.status_byte(None), Some(0xB3));
+    assert_eq!(message.midi_channel(None), Some(3));
+    assert_eq!(message.kind(None), Some(Kind::ControlChange));
+    assert_eq!(message.data(), [0x07, 0x64]);
+}
+
+#[test]
+fn accessors_resolve_running_status() {
+    let message = message(&[0x95, 0x3C, 0x64, 0x3E, 0x64]);
+    assert!(matches!(message, MidiMessage::RunningStatus(_)));
+    assert_eq!(message.status_byte(Some(0x95)), Some(0x95));
+    assert_eq!(message.midi_channel(Some(0x95)), Some(5));
+    assert_eq!(message.kind(Some(0x95)), Some(Kind::NoteOn));
+    assert_eq!(message.data(), [0x3E, 0x64]);
+    assert_eq!(message.status_byte(None), None);
+    assert_eq!(message.kind(None), None);
+}
+
+#[test]
+fn accessors_of_system_messages() {
+    let clock = message(&[0xF8]);
+    assert_eq!(clock.status_byte(None), Some(0xF8));
+    assert_eq!(clock.midi_channel(None), None);
+    assert_eq!(clock.kind(None), Some(Kind::SystemRealtime));
+    assert_eq!(clock.data(), []);
+
+    let song_select = message(&[0xF3, 0x02]);
+    assert_eq!(song_select.kind(None), Some(Kind::SystemCommon));
+    assert_eq!(song_select.data(), [0x02]);
+
+    let sysex = message(&[0xF0, 0x7D, 0x01, 0xF7]);
+    assert_eq!(sysex.status_byte(None), None);
+    assert_eq!(sysex.kind(None), None);
+    assert_eq!(sysex.data(), [0x7D, 0x01]);
+}
@@ -0,0 +1,89 @@
+//! Running status expanded into full messages at parse time
+
+mod common;
+
+use common::{bytes, messages};
+use midi_parser::{MidiMessage, MidiMessageError, MidiParser};
+
+#[test]
+fn running_status_gets_its_status_byte() {
+    let stream = [0x93, 0x3C, 0x64, 0x3E, 0x64, 0x40, 0x00];
+    let mut parser = MidiParser::default();
+    // Off by default
+    assert!(matches!(
+        messages(&mut parser, &stream)[1],
+        MidiMessage::RunningStatus(_)
+    ));
+
+    parser.set_expand_running_status(true);
+    let messages = messages(&mut parser, &stream);
+    assert_eq!(
+        bytes(&messages),
+        [
+            vec![0x93, 0x3C, 0x64],
+            vec![0x93, 0x3E, 0x64],
+            vec![0x93, 0x40, 0x00]
+        ]
+    );
+    assert!(messages
+        .iter()
+        .all(|message| matches!(message, MidiMessage::Voice(_))));
+}
+
+#[test]
+fn one_data_byte_messages() {
+    let mut parser = MidiParser::default();
+    parser.set_expand_running_status(true);
+    let messages = messages(&mut parser, &[0xC2, 0x05, 0x06]);
+    assert!(matches!(messages[1], MidiMessage::Voice(_)));
+    assert_eq!(messages[1].bytes(), [0xC2, 0x06]);
+}
+
+#[test]
+fn realtime_between_keeps_the_status() {
+    let mut parser = MidiParser::default();
+    parser.set_expand_running_status(true);
+    let messages = messages(&mut parser, &[0xB0, 0x07, 0x64, 0xF8, 0x07, 0x50]);
+    assert_eq!(messages[1].bytes(), [0xF8]);
+    assert_eq!(messages[2].bytes(), [0xB0, 0x07, 0x50]);
+}
+
+#[test]
+fn with_normalized_note_off() {
+    let mut parser = MidiParser::default();
+    parser.set_expand_running_status(true);
+    parser.set_normalize_note_off(true);
+    let messages = messages(&mut parser, &[0x90, 0x3C, 0x64, 0x3C, 0x00, 0x3E, 0x64]);
+    assert_eq!(
+        bytes(&messages),
+        [
+            vec![0x90, 0x3C, 0x64],
+            vec![0x80, 0x3C, 0x00],
+            vec![0x90, 0x3E, 0x64]
+        ]
+    );
+}
+
+#[test]
+fn no_stale_status_after_reset() {
+    let mut parser = MidiParser::default();
+    parser.set_expand_running_status(true);
+    messages(&mut parser, &[0x90, 0x3C, 0x64]);
+    parser.reset();
+    // The parser resyncs and skips the data bytes until a status byte comes
+    assert!(matches!(parser.feed_byte(0x3E), Ok(None)));
+    assert!(matches!(parser.feed_byte(0x64), Ok(None)));
+    let messages = messages(&mut parser, &[0x91, 0x3E, 0x64]);
+    assert_eq!(messages[0].bytes(), [0x91, 0x3E, 0x64]);
+}
+
+#[test]
+fn no_running_status_after_system_common() {
+    let mut parser = MidiParser::default();
+    parser.set_expand_running_status(true);
+    messages(&mut parser, &[0x90, 0x3C, 0x64, 0xF3, 0x01]);
+    assert!(matches!(
+        parser.feed_byte(0x3E),
+        Err(MidiMessageError::UnexpectedDataByte)
+    ));
+}
@@ -0,0 +1,80 @@
+//! Feeding a slice of bytes at once
+
+use midi_parser::{MidiMessageError, MidiParser};
+
+/// Bytes of each message or the error, for a whole slice
+fn feed(parser: &mut MidiParser, bytes: &[u8]) -> Vec<Result<Vec<u8>, MidiMessageError>> {
+    parser
+        .feed_bytes(bytes)
+        .map(|result| result.map(|message| message.bytes().to_vec()))
+        .collect()
+}
+
+#[test]
+fn all_messages_of_a_slice() {
+    let mut parser = MidiParser::default();
+    assert_eq!(
+        feed(
+            &mut parser,
+            &[0x90, 0x3C, 0x64, 0x3E, 0x64, 0xF8, 0xC0, 0x05, 0xF0, 0x7D, 0xF7]
+        ),
+        [
+            Ok(vec![0x90, 0x3C, 0x64]),
+            Ok(vec![0x3E, 0x64]),
+            Ok(vec![0xF8]),
+            Ok(vec![0xC0, 0x05]),
+            Ok(vec![0xF0, 0x7D, 0xF7]),
+        ]
+    );
+}
+
+#[test]
+fn same_as_byte_by_byte() {
+    let stream = [
+        0x90, 0x3C, 0xF8, 0x64, 0x3E, 0x64, 0xF4, 0x40, 0xB0, 0x07, 0x7F, 0x07, 0x00,
+    ];
+    let mut one_by_one = MidiParser::default();
+    let expected: Vec<_> = stream
+        .iter()
+        .filter_map(|byte| one_by_one.feed_byte(*byte).transpose())
+        .map(|result| result.map(|message| message.bytes().to_vec()))
+        .collect();
+    assert_eq!(feed(&mut MidiParser::default(), &stream), expected);
+}
+
+#[test]
+fn message_continues_in_the_next_slice() {
+    let mut parser = MidiParser::default();
+    assert_eq!(feed(&mut parser, &[0x90, 0x3C]), []);
+    assert_eq!(feed(&mut parser, &[0x64]), [Ok(vec![0x90, 0x3C, 0x64])]);
+}
+
+#[test]
+fn errors_are_yielded_in_order() {
+    let mut parser = MidiParser::default();
+    assert_eq!(
+        feed(&mut parser, &[0xF8, 0x3C, 0x90, 0x3C, 0x64]),
+        [
+            Ok(vec![0xF8]),
+            Err(MidiMessageError::UnexpectedDataByte),
+            Ok(vec![0x90, 0x3C, 0x64]),
+        ]
+    );
+}
+
+#[test]
+fn consumed_stops_where_the_iterator_did() {
+    let mut parser = MidiParser::default();
+    let bytes = [0x90, 0x3C, 0x64, 0xF8, 0xC0, 0x05];
+    {
+        let mut messages = parser.feed_bytes(&bytes);
+        assert_eq!(messages.consumed(), 0);
+        messages.next();
+        assert_eq!(messages.consumed(), 3);
+        messages.next();
+        assert_eq!(messages.consumed(), 4);
+    }
+    // The rest wasn't fed
+    assert_eq!(parser.stats().voice, 1);
+    assert_eq!(parser.stats().realtime, 1);
+}
@@ -0,0 +1,57 @@
+//! Note On with velocity 0 rewritten as Note Off
+
+mod common;
+
+use common::{bytes, messages};
+use midi_parser::MidiParser;
+
+#[test]
+fn velocity_zero_note_on_becomes_note_off() {
+    let mut parser = MidiParser::default();
+    let stream = [0x93, 0x3C, 0x64, 0x93, 0x3C, 0x00, 0x83, 0x3E, 0x40];
+    // Off by default
+    assert_eq!(
+        bytes(&messages(&mut parser, &stream))[1],
+        [0x93, 0x3C, 0x00]
+    );
+
+    parser.set_normalize_note_off(true);
+    assert_eq!(
+        bytes(&messages(&mut parser, &stream)),
+        [
+            vec![0x93, 0x3C, 0x64],
+            vec![0x83, 0x3C, 0x00],
+            vec![0x83, 0x3E, 0x40]
+        ]
+    );
+}
+
+#[test]
+fn running_status_after_a_rewrite_gets_its_status_back() {
+    let mut parser = MidiParser::default();
+    parser.set_normalize_note_off(true);
+    assert_eq!(
+        bytes(&messages(
+            &mut parser,
+            &[0x90, 0x3C, 0x64, 0x3C, 0x00, 0x3E, 0x00, 0x40, 0x64, 0x42, 0x64]
+        )),
+        [
+            vec![0x90, 0x3C, 0x64],
+            vec![0x80, 0x3C, 0x00],
+            vec![0x80, 0x3E, 0x00],
+            vec![0x90, 0x40, 0x64],
+            vec![0x42, 0x64]
+        ]
+    );
+}
+
+#[test]
+fn other_messages_are_left_alone() {
+    let stream = [0xB0, 0x07, 0x00, 0xA0, 0x3C, 0x00, 0x08, 0x00, 0xF8];
+    let mut parser = MidiParser::default();
+    parser.set_normalize_note_off(true);
+    assert_eq!(
+        bytes(&messages(&mut parser, &stream)),
+        bytes(&messages(&mut MidiParser::default(), &stream))
+    );
+}
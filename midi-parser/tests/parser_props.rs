@@ -0,0 +1,175 @@
+//! Property tests of `MidiParser`
+//!
+//! Run with `PROPTEST_CASES=<n>` for a longer search.
+
+use midi_parser::{MidiMessage, MidiParser, SYSEX_CAPTURE_LEN};
+use proptest::prelude::*;
+
+/// Defined realtime bytes
+const REALTIME: [u8; 6] = [0xF8, 0xFA, 0xFB, 0xFC, 0xFE, 0xFF];
+
+/// Encoding of one complete message, always with its status byte
+fn message() -> impl Strategy<Value = Vec<u8>> {
+    let data = || 0u8..0x80;
+    prop_oneof![
+        // Program Change and Channel Pressure
+        (0xC0u8..0xE0, data()).prop_map(|(status, a)| vec![status, a]),
+        // Other Voice messages
+        (prop_oneof![0x80u8..0xC0, 0xE0u8..0xF0], data(), data())
+            .prop_map(|(status, a, b)| vec![status, a, b]),
+        // System Common
+        (prop_oneof![Just(0xF1u8), Just(0xF3)], data()).prop_map(|(status, a)| vec![status, a]),
+        (data(), data()).prop_map(|(a, b)| vec![0xF2, a, b]),
+        Just(vec![0xF6]),
+        // Realtime
+        proptest::sample::select(&REALTIME[..]).prop_map(|status| vec![status]),
+        // SysEx short enough to be captured
+        proptest::collection::vec(data(), 0..=SYSEX_CAPTURE_LEN - 2).prop_map(|body| {
+            let mut bytes = vec![0xF0];
+            bytes.extend(body);
+            bytes.push(0xF7);
+            bytes
+        }),
+    ]
+}
+
+/// Feed a stream, resetting the parser after errors as the firmware does
+fn parse(parser: &mut MidiParser, bytes: &[u8]) -> Vec<MidiMessage> {
+    let mut messages = Vec::new();
+    for byte in bytes {
+        match parser.feed_byte(*byte) {
+            Ok(Some(message)) => messages.push(message),
+            Ok(None) => {}
+            Err(_) => parser.reset(),
+        }
+    }
+    messages
+}
+
+/// Whether the message is encoded the way its variant says
+fn well_formed(message: &MidiMessage) -> bool {
+    let bytes = message.bytes();
+    let data = |bytes: &[u8]| bytes.iter().all(|byte| *byte < 0x80);
+    match message {
+        MidiMessage::SystemRealtime(_) => bytes.len() == 1 && REALTIME.contains(&bytes[0]),
+        MidiMessage::Voice(_) => {
+            let len = if (0xC0..0xE0).contains(&bytes[0]) {
+                2
+            } else {
+                3
+            };
+            (0x80..0xF0).contains(&bytes[0]) && bytes.len() == len && data(&bytes[1..])
+        }
+        MidiMessage::SystemCommon(_) => {
+            let len = match bytes[0] {
+                0xF1 | 0xF3 => 2,
+                0xF2 => 3,
+                0xF6 => 1,
+                _ => return false,
+            };
+            bytes.len() == len && data(&bytes[1..])
+        }
+        MidiMessage::RunningStatus(_) => (1..=2).contains(&bytes.len()) && data(bytes),
+        MidiMessage::SysEx(_) => {
+            bytes.len() >= 2
+                && bytes[0] == 0xF0
+                && bytes[bytes.len() - 1] == 0xF7
+                && data(&bytes[1..bytes.len() - 1])
+        }
+        MidiMessage::SysExChunk(_) => {
+            let start = usize::from(bytes.first() == Some(&0xF0));
+            let end = bytes.len() - usize::from(bytes.last() == Some(&0xF7));
+            !bytes.is_empty() && start <= end && data(&bytes[start..end])
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_bytes_never_panic(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+        let mut parser = MidiParser::default();
+        for byte in bytes {
+            // Without resetting after errors, too
+            let _ = parser.feed_byte(byte);
+        }
+    }
+
+    #[test]
+    fn emitted_messages_are_well_formed(
+        bytes in proptest::collection::vec(any::<u8>(), 0..512)
+    ) {
+        let mut parser = MidiParser::default();
+        for message in parse(&mut parser, &bytes) {
+            prop_assert!(well_formed(&message), "{:02x?}", message.bytes());
+        }
+    }
+
+    #[test]
+    fn valid_messages_round_trip(messages in proptest::collection::vec(message(), 0..64)) {
+        let mut parser = MidiParser::default();
+        let mut parsed = Vec::new();
+        for bytes in &messages {
+            for byte in bytes {
+                let message = parser.feed_byte(*byte);
+                prop_assert!(message.is_ok(), "{:02x?}: {:?}", bytes, message);
+                parsed.extend(message.unwrap());
+            }
+        }
+        let parsed: Vec<_> = parsed.iter().map(|message| message.bytes().to_vec()).collect();
+        prop_assert_eq!(parsed, messages);
+    }
+
+    #[test]
+    fn realtime_passes_through_anywhere(
+        messages in proptest::collection::vec(message(), 0..32),
+        realtime in proptest::collection::vec(
+            (any::<proptest::sample::Index>(), proptest::sample::select(&REALTIME[..])),
+            0..16
+        )
+    ) {
+        let mut stream: Vec<u8> = messages.concat();
+        for (index, byte) in &realtime {
+            stream.insert(index.index(stream.len() + 1), *byte);
+        }
+
+        let mut parser = MidiParser::default();
+        let parsed = parse(&mut parser, &stream);
+        prop_assert_eq!(parser.resync_count(), 0);
+
+        // Interrupted messages are unaffected, and every realtime byte comes
+        // out on its own
+        let (parsed_realtime, parsed_other): (Vec<_>, Vec<_>) = parsed
+            .iter()
+            .map(|message| message.bytes().to_vec())
+            .partition(|bytes| REALTIME.contains(&bytes[0]));
+        let (own_realtime, other): (Vec<_>, Vec<_>) = messages
+            .into_iter()
+            .partition(|bytes| REALTIME.contains(&bytes[0]));
+        prop_assert_eq!(parsed_other, other);
+        prop_assert_eq!(parsed_realtime.len(), own_realtime.len() + realtime.len());
+    }
+
+    #[test]
+    fn resync_ends_at_next_status_byte(
+        prefix in proptest::collection::vec(any::<u8>(), 0..64),
+        garbage in proptest::collection::vec(
+            prop_oneof![0u8..0x80, Just(0xF4), Just(0xF5)],
+            0..32
+        ),
+        message in message(),
+    ) {
+        let mut parser = MidiParser::default();
+        parse(&mut parser, &prefix);
+        parser.reset();
+
+        // Data bytes and undefined status bytes are skipped, the first valid
+        // status byte starts the next message
+        let parsed = parse(&mut parser, &garbage);
+        prop_assert!(parsed.is_empty(), "{:?}", parsed);
+        let parsed: Vec<_> = parse(&mut parser, &message)
+            .iter()
+            .map(|message| message.bytes().to_vec())
+            .collect();
+        prop_assert_eq!(parsed, vec![message]);
+    }
+}
@@ -0,0 +1,91 @@
+//! Parser statistics counters
+
+use midi_parser::{MidiParser, ParserStats, ResyncPolicy};
+
+/// Feed a stream, resetting after errors as the read tasks do
+fn feed(parser: &mut MidiParser, bytes: &[u8]) {
+    for byte in bytes {
+        if parser.feed_byte(*byte).is_err() {
+            parser.reset();
+        }
+    }
+}
+
+#[test]
+fn messages_are_counted_by_category() {
+    let mut parser = MidiParser::default();
+    feed(
+        &mut parser,
+        &[
+            0x90, 0x3C, 0x64, 0x3E, 0x64, 0x40, 0x64, 0xF8, 0xF2, 0x00, 0x00, 0xF8, 0xF0, 0x7D,
+            0xF7,
+        ],
+    );
+    assert_eq!(
+        parser.stats(),
+        ParserStats {
+            voice: 1,
+            running_status: 2,
+            system_common: 1,
+            realtime: 2,
+            sysex: 1,
+            ..ParserStats::default()
+        }
+    );
+}
+
+#[test]
+fn errors_are_counted_by_kind() {
+    let mut parser = MidiParser::default();
+    feed(
+        &mut parser,
+        &[
+            // Data byte without running status
+            0x3C, //
+            // Status byte inside a message
+            0x90, 0x3C, 0x90, //
+            0xB0, 0x07, 0x64, //
+            // Undefined status bytes
+            0xF4, 0xB0, 0x07, 0x64, 0xF9,
+        ],
+    );
+    let stats = parser.stats();
+    assert_eq!(stats.unexpected_data_byte, 1);
+    assert_eq!(stats.duplicate_status, 1);
+    assert_eq!(stats.invalid_status_byte, 2);
+    assert_eq!(stats.voice, 2);
+    // Once per error; the reset after each doesn't count again
+    assert_eq!(stats.resyncs, 4);
+    assert_eq!(stats.resyncs, parser.resync_count());
+}
+
+#[test]
+fn skipped_bytes_are_counted() {
+    let mut parser = MidiParser::with_policy(ResyncPolicy::Permissive);
+    feed(&mut parser, &[0x90, 0x3C, 0xF4, 0x64]);
+    assert_eq!(parser.stats().skipped, 1);
+    assert_eq!(parser.stats().invalid_status_byte, 0);
+    assert_eq!(parser.stats().voice, 1);
+}
+
+#[test]
+fn streamed_sysex_counts_once() {
+    let mut parser = MidiParser::default();
+    parser.set_sysex_streaming(true);
+    let mut bytes = vec![0xF0];
+    bytes.extend(std::iter::repeat_n(0x01, 100));
+    bytes.push(0xF7);
+    feed(&mut parser, &bytes);
+    assert_eq!(parser.stats().sysex, 1);
+}
+
+#[test]
+fn reset_stats_keeps_the_resync_count() {
+    let mut parser = MidiParser::default();
+    feed(&mut parser, &[0x3C, 0x90, 0x3C, 0x64]);
+    parser.reset_stats();
+    assert_eq!(parser.stats(), ParserStats::default());
+    assert_eq!(parser.resync_count(), 1);
+    feed(&mut parser, &[0x80, 0x3C, 0x00]);
+    assert_eq!(parser.stats().voice, 1);
+}
@@ -0,0 +1,168 @@
+//! Error recovery under each `ResyncPolicy`
+
+use midi_parser::{MidiMessageError, MidiParser, ResyncPolicy, UndefinedRealtime};
+
+/// What one byte of a stream produced
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Out {
+    Message(Vec<u8>),
+    Error(MidiMessageError),
+}
+
+/// Everything a parser produces for a stream, resetting it after errors as
+/// the read tasks do
+fn run(parser: &mut MidiParser, bytes: &[u8]) -> Vec<Out> {
+    bytes
+        .iter()
+        .filter_map(|byte| match parser.feed_byte(*byte) {
+            Ok(Some(message)) => Some(Out::Message(message.bytes().to_vec())),
+            Ok(None) => None,
+            Err(err) => {
+                parser.reset();
+                Some(Out::Error(err))
+            }
+        })
+        .collect()
+}
+
+fn message(bytes: &[u8]) -> Out {
+    Out::Message(bytes.to_vec())
+}
+
+#[test]
+fn default_policy_is_strict() {
+    assert_eq!(MidiParser::default().policy(), ResyncPolicy::Strict);
+}
+
+#[test]
+fn strict_drops_the_message_around_an_undefined_byte() {
+    let mut parser = MidiParser::with_policy(ResyncPolicy::Strict);
+    let out = run(&mut parser, &[0x90, 0x3C, 0xF4, 0x64, 0x90, 0x3E, 0x64]);
+    assert_eq!(
+        out,
+        [
+            Out::Error(MidiMessageError::InvalidStatusByte),
+            message(&[0x90, 0x3E, 0x64]),
+        ]
+    );
+    assert_eq!(parser.skipped_count(), 0);
+    assert_eq!(parser.resync_count(), 1);
+}
+
+#[test]
+fn permissive_keeps_the_message_around_undefined_bytes() {
+    let mut parser = MidiParser::with_policy(ResyncPolicy::Permissive);
+    let out = run(
+        &mut parser,
+        &[0x90, 0x3C, 0xF4, 0xF9, 0x64, 0xFD, 0x3E, 0x64],
+    );
+    assert_eq!(out, [message(&[0x90, 0x3C, 0x64]), message(&[0x3E, 0x64])]);
+    assert_eq!(parser.skipped_count(), 3);
+    assert_eq!(parser.resync_count(), 0);
+}
+
+#[test]
+fn permissive_skips_data_without_running_status() {
+    let mut parser = MidiParser::with_policy(ResyncPolicy::Permissive);
+    let out = run(&mut parser, &[0x12, 0x34, 0xC0, 0x05]);
+    assert_eq!(out, [message(&[0xC0, 0x05])]);
+    assert_eq!(parser.skipped_count(), 2);
+}
+
+#[test]
+fn permissive_restarts_on_an_interrupting_status() {
+    let mut parser = MidiParser::with_policy(ResyncPolicy::Permissive);
+    let out = run(&mut parser, &[0x90, 0x3C, 0xB0, 0x07, 0x64]);
+    assert_eq!(out, [message(&[0xB0, 0x07, 0x64])]);
+    assert_eq!(parser.skipped_count(), 1);
+    assert_eq!(parser.resync_count(), 0);
+}
+
+#[test]
+fn budget_resyncs_once_spent() {
+    let mut parser = MidiParser::with_policy(ResyncPolicy::Budget(2));
+    let out = run(
+        &mut parser,
+        &[0x90, 0xF4, 0x3C, 0xF5, 0xF4, 0x64, 0x90, 0x3E, 0x64],
+    );
+    assert_eq!(
+        out,
+        [
+            Out::Error(MidiMessageError::InvalidStatusByte),
+            message(&[0x90, 0x3E, 0x64]),
+        ]
+    );
+    assert_eq!(parser.skipped_count(), 2);
+    assert_eq!(parser.resync_count(), 1);
+}
+
+#[test]
+fn budget_refills_on_each_complete_message() {
+    let mut parser = MidiParser::with_policy(ResyncPolicy::Budget(1));
+    let out = run(
+        &mut parser,
+        &[0x90, 0xF4, 0x3C, 0x64, 0xF5, 0x3E, 0x64, 0xF4, 0x40, 0x64],
+    );
+    assert_eq!(
+        out,
+        [
+            message(&[0x90, 0x3C, 0x64]),
+            message(&[0x3E, 0x64]),
+            message(&[0x40, 0x64]),
+        ]
+    );
+    assert_eq!(parser.skipped_count(), 3);
+    assert_eq!(parser.resync_count(), 0);
+}
+
+#[test]
+fn zero_budget_behaves_as_strict() {
+    let bytes = [0x90, 0x3C, 0xF4, 0x64, 0x55, 0x90, 0x3C, 0xB0, 0x07, 0x64];
+    let mut strict = MidiParser::with_policy(ResyncPolicy::Strict);
+    let mut budget = MidiParser::with_policy(ResyncPolicy::Budget(0));
+    assert_eq!(run(&mut budget, &bytes), run(&mut strict, &bytes));
+    assert_eq!(budget.skipped_count(), 0);
+}
+
+// Undefined realtime bytes
+
+#[test]
+fn undefined_realtime_is_rejected_by_default() {
+    let mut parser = MidiParser::default();
+    assert_eq!(parser.undefined_realtime(), UndefinedRealtime::Reject);
+    assert_eq!(
+        run(&mut parser, &[0x90, 0x3C, 0xF9, 0x64, 0x90, 0x3E, 0x64]),
+        [
+            Out::Error(MidiMessageError::InvalidStatusByte),
+            message(&[0x90, 0x3E, 0x64])
+        ]
+    );
+}
+
+#[test]
+fn dropped_undefined_realtime_leaves_the_stream_alone() {
+    let mut parser = MidiParser::default();
+    parser.set_undefined_realtime(UndefinedRealtime::Drop);
+    assert_eq!(
+        run(&mut parser, &[0x90, 0x3C, 0xF9, 0x64, 0xFD, 0x3E, 0x64]),
+        [message(&[0x90, 0x3C, 0x64]), message(&[0x3E, 0x64])]
+    );
+    assert_eq!(parser.resync_count(), 0);
+    assert_eq!(parser.skipped_count(), 0);
+}
+
+#[test]
+fn forwarded_undefined_realtime_interrupts_like_a_clock() {
+    let mut parser = MidiParser::default();
+    parser.set_undefined_realtime(UndefinedRealtime::Forward);
+    assert_eq!(
+        run(&mut parser, &[0x90, 0x3C, 0xF9, 0x64, 0xFD, 0x3E, 0x64]),
+        [
+            message(&[0xF9]),
+            message(&[0x90, 0x3C, 0x64]),
+            message(&[0xFD]),
+            message(&[0x3E, 0x64])
+        ]
+    );
+    assert_eq!(parser.resync_count(), 0);
+}
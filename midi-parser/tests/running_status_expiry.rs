@@ -0,0 +1,110 @@
+//! Running status forgotten after a silence
+
+mod common;
+
+use common::{paced, ManualClock};
+use embassy_time::Duration;
+use midi_parser::{MidiMessageError, MidiParser};
+
+const EXPIRY: Duration = Duration::from_secs(10);
+
+#[test]
+fn silence_past_the_expiry_forgets_the_status() {
+    let stream = [
+        (0, 0x90),
+        (0, 0x3C),
+        (0, 0x64),
+        (10_001, 0x3E),
+        (0, 0x64),
+        (0, 0x91),
+        (0, 0x3E),
+        (0, 0x64),
+    ];
+    let clock = ManualClock::default();
+    let mut parser = MidiParser::with_clock(clock.clone());
+    // Kept forever by default
+    assert_eq!(paced(&mut parser, &clock, &stream).len(), 3);
+
+    parser.reset();
+    parser.set_running_status_expiry(Some(EXPIRY));
+    // The data bytes after the pause wait for the next status byte
+    assert_eq!(
+        paced(&mut parser, &clock, &stream),
+        [vec![0x90, 0x3C, 0x64], vec![0x91, 0x3E, 0x64]]
+    );
+}
+
+#[test]
+fn data_after_the_expiry_is_an_error() {
+    let clock = ManualClock::default();
+    let mut parser = MidiParser::with_clock(clock.clone());
+    parser.set_running_status_expiry(Some(EXPIRY));
+    paced(&mut parser, &clock, &[(0, 0xB0), (0, 0x07), (0, 0x64)]);
+    clock.advance(EXPIRY + Duration::from_millis(1));
+    assert!(matches!(
+        parser.feed_byte(0x07),
+        Err(MidiMessageError::UnexpectedDataByte)
+    ));
+}
+
+#[test]
+fn silence_within_the_expiry_keeps_the_status() {
+    let clock = ManualClock::default();
+    let mut parser = MidiParser::with_clock(clock.clone());
+    parser.set_running_status_expiry(Some(EXPIRY));
+    assert_eq!(
+        paced(
+            &mut parser,
+            &clock,
+            &[(0, 0x90), (0, 0x3C), (0, 0x64), (10_000, 0x3E), (0, 0x64)]
+        ),
+        [vec![0x90, 0x3C, 0x64], vec![0x3E, 0x64]]
+    );
+}
+
+#[test]
+fn realtime_is_not_activity() {
+    let clock = ManualClock::default();
+    let mut parser = MidiParser::with_clock(clock.clone());
+    parser.set_running_status_expiry(Some(EXPIRY));
+    assert_eq!(
+        paced(
+            &mut parser,
+            &clock,
+            &[
+                (0, 0x90),
+                (0, 0x3C),
+                (0, 0x64),
+                (6_000, 0xFE),
+                (6_000, 0xFE),
+                (0, 0x3E),
+                (0, 0x64)
+            ]
+        ),
+        [vec![0x90, 0x3C, 0x64], vec![0xFE], vec![0xFE]]
+    );
+}
+
+#[test]
+fn expanded_status_expires_too() {
+    let clock = ManualClock::default();
+    let mut parser = MidiParser::with_clock(clock.clone());
+    parser.set_expand_running_status(true);
+    parser.set_running_status_expiry(Some(EXPIRY));
+    assert_eq!(
+        paced(
+            &mut parser,
+            &clock,
+            &[
+                (0, 0x90),
+                (0, 0x3C),
+                (0, 0x64),
+                (5_000, 0x3E),
+                (0, 0x64),
+                (10_001, 0x40),
+                (0, 0x64)
+            ]
+        ),
+        [vec![0x90, 0x3C, 0x64], vec![0x90, 0x3E, 0x64]]
+    );
+}
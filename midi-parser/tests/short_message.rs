@@ -0,0 +1,70 @@
+//! Inline storage of messages other than SysEx
+
+use core::mem::size_of;
+use midi_parser::SYSEX_CAPTURE_LEN;
+use midi_parser::{MidiMessage, MidiParser, ShortMessage};
+
+#[test]
+fn four_bytes_inline() {
+    assert_eq!(size_of::<ShortMessage>(), 4);
+}
+
+#[test]
+fn sysex_capture_sets_the_message_size() {
+    // SysEx and SysExChunk hold the capture buffer inline, so a message (and
+    // every queue slot) is as large as the buffer plus the tag however short
+    // it is: 32 bytes on a 64-bit host, 24 on the RP2040 with the default
+    // 16-byte capture
+    let sysex = size_of::<heapless::Vec<u8, SYSEX_CAPTURE_LEN>>();
+    assert_eq!(size_of::<MidiMessage>(), sysex + size_of::<usize>());
+    #[cfg(target_pointer_width = "64")]
+    if SYSEX_CAPTURE_LEN == 16 {
+        assert_eq!(size_of::<MidiMessage>(), 32);
+    }
+}
+
+#[test]
+fn from_slice_takes_up_to_three_bytes() {
+    assert_eq!(ShortMessage::from_slice(&[]).unwrap(), []);
+    assert_eq!(
+        ShortMessage::from_slice(&[0x90, 0x3C, 0x64]).unwrap(),
+        [0x90, 0x3C, 0x64]
+    );
+    assert_eq!(ShortMessage::from_slice(&[0xF0, 0x7D, 0x01, 0xF7]), None);
+}
+
+#[test]
+fn new_puts_the_status_first() {
+    assert_eq!(
+        ShortMessage::new(Some(0xB0), &[0x07, 0x64]),
+        [0xB0, 0x07, 0x64]
+    );
+    assert_eq!(ShortMessage::new(None, &[0x07, 0x64]), [0x07, 0x64]);
+    assert_eq!(ShortMessage::new(Some(0xF8), &[]), [0xF8]);
+}
+
+#[test]
+fn bytes_can_be_changed_in_place() {
+    let mut message = ShortMessage::new(Some(0x90), &[0x3C, 0x64]);
+    message[2] = 0x40;
+    assert_eq!(message, [0x90, 0x3C, 0x40]);
+}
+
+#[test]
+fn parsed_messages_keep_their_bytes() {
+    let mut parser = MidiParser::default();
+    let messages: Vec<MidiMessage> = [0x90, 0x3C, 0x64, 0x3E, 0x64, 0xC1, 0x05, 0xF8]
+        .iter()
+        .filter_map(|byte| parser.feed_byte(*byte).unwrap())
+        .collect();
+    let bytes: Vec<&[u8]> = messages.iter().map(MidiMessage::bytes).collect();
+    assert_eq!(
+        bytes,
+        [
+            &[0x90, 0x3C, 0x64][..],
+            &[0x3E, 0x64],
+            &[0xC1, 0x05],
+            &[0xF8]
+        ]
+    );
+}
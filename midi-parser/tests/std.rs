@@ -0,0 +1,16 @@
+//! Host build with the `std` feature
+
+#![cfg(feature = "std")]
+
+use midi_parser::{MidiMessageError, MidiParser};
+
+#[test]
+fn parser_errors_are_std_errors() {
+    let mut parser = MidiParser::default();
+    let err: Box<dyn std::error::Error> = parser.feed_byte(0x3C).unwrap_err().into();
+    assert_eq!(err.to_string(), "unexpected data byte");
+    assert_eq!(
+        MidiMessageError::InvalidStatusByte.to_string(),
+        "undefined status byte"
+    );
+}
@@ -0,0 +1,99 @@
+//! MTC quarter frames assembled into timecodes by the parser
+
+mod common;
+
+use common::{bytes, each_message, messages};
+use midi_parser::timecode::{quarter_frame_data, FrameRate, MtcTimecode, Timecode};
+use midi_parser::MidiParser;
+
+/// Quarter frames running from a timecode, two frames per cycle
+fn quarter_frames(start: Timecode, rate: FrameRate, count: u64) -> Vec<u8> {
+    let first = start.to_frames(rate) * 4;
+    (first..first + count)
+        .flat_map(|quarter| {
+            let piece = (quarter % 8) as u8;
+            let timecode = Timecode::from_frames((quarter - piece as u64) / 4, rate);
+            [0xF1, quarter_frame_data(timecode, rate, piece)]
+        })
+        .collect()
+}
+
+/// Timecodes taken after each message of the bytes
+fn timecodes(parser: &mut MidiParser, bytes: &[u8]) -> Vec<MtcTimecode> {
+    let mut timecodes = Vec::new();
+    each_message(parser, bytes, |parser, _| {
+        timecodes.extend(parser.take_timecode())
+    });
+    timecodes
+}
+
+#[test]
+fn one_timecode_per_cycle() {
+    let rate = FrameRate::Fps25;
+    // An even frame count, so the first cycle starts with piece 0
+    let bytes = quarter_frames(Timecode::new(1, 2, 3, 5), rate, 24);
+    let mut parser = MidiParser::default();
+    // Off by default
+    assert_eq!(timecodes(&mut parser, &bytes), []);
+
+    parser.set_assemble_timecode(true);
+    assert_eq!(
+        timecodes(&mut parser, &bytes),
+        [
+            MtcTimecode {
+                timecode: Timecode::new(1, 2, 3, 6),
+                rate
+            },
+            MtcTimecode {
+                timecode: Timecode::new(1, 2, 3, 8),
+                rate
+            },
+            MtcTimecode {
+                timecode: Timecode::new(1, 2, 3, 10),
+                rate
+            },
+        ]
+    );
+}
+
+#[test]
+fn drop_frame_rate() {
+    let mut parser = MidiParser::default();
+    parser.set_assemble_timecode(true);
+    let rate = FrameRate::Fps30Drop;
+    let bytes = quarter_frames(Timecode::new(0, 0, 59, 28), rate, 8);
+    assert_eq!(
+        timecodes(&mut parser, &bytes),
+        [MtcTimecode {
+            timecode: Timecode::new(0, 0, 59, 29),
+            rate
+        }]
+    );
+}
+
+#[test]
+fn cycle_joined_midway_waits_for_the_next() {
+    let mut parser = MidiParser::default();
+    parser.set_assemble_timecode(true);
+    let bytes = quarter_frames(Timecode::new(0, 0, 10, 0), FrameRate::Fps24, 16);
+    // Start at piece 4
+    assert_eq!(timecodes(&mut parser, &bytes[8..]).len(), 1);
+}
+
+#[test]
+fn piece_out_of_order_drops_the_lock() {
+    let mut parser = MidiParser::default();
+    parser.set_assemble_timecode(true);
+    let mut bytes = quarter_frames(Timecode::new(0, 0, 10, 0), FrameRate::Fps24, 16);
+    // Piece 3 of the second cycle lost
+    bytes.drain(22..24);
+    assert_eq!(timecodes(&mut parser, &bytes).len(), 1);
+}
+
+#[test]
+fn quarter_frames_pass_unchanged() {
+    let stream = quarter_frames(Timecode::new(0, 0, 0, 0), FrameRate::Fps25, 8);
+    let mut parser = MidiParser::default();
+    parser.set_assemble_timecode(true);
+    assert_eq!(bytes(&messages(&mut parser, &stream)).concat(), stream);
+}
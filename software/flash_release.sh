@@ -1 +1 @@
-DEFMT_LOG=error cargo run --release
\ No newline at end of file
+cargo run --release
\ No newline at end of file
@@ -0,0 +1,52 @@
+//! Per-input pitch bend range rescaling
+//!
+//! A controller set to bend ±2 semitones sounds ±12 on a module fixed at
+//! ±12. With a source range set for its input in `PITCH_BEND_SOURCES`,
+//! `write_uart` scales the 14-bit bend values by source/destination, so the
+//! module bends as far as the controller means to. Bends beyond the
+//! destination's range, from a source wider than it, are clamped.
+//!
+//! `PITCH_BEND_SETUP` also sets the destination's range with RPN 0 on all
+//! 16 channels at boot, for modules that accept it.
+
+/// Center of the 14-bit bend range, no bend
+const CENTER: i32 = 8192;
+
+/// Rescale a 14-bit bend value from one range to another
+///
+/// # Arguments
+/// * `source` - Bend range of the sender in semitones
+/// * `destination` - Bend range of the receiver in semitones, at least 1
+pub fn rescale(value: u16, source: u8, destination: u8) -> u16 {
+    let deviation = value as i32 - CENTER;
+    let scaled = deviation * source as i32 / destination.max(1) as i32;
+    (CENTER + scaled).clamp(0, 0x3FFF) as u16
+}
+
+/// RPN 0 (pitch bend sensitivity) set to `semitones` on every channel, then
+/// the null RPN so later data entry doesn't change it
+pub const fn range_setup(semitones: u8) -> [u8; 16 * 18] {
+    let mut bytes = [0; 16 * 18];
+    let mut channel = 0;
+    while channel < 16 {
+        let status = 0xB0 | channel as u8;
+        let controllers = [
+            (101, 0),
+            (100, 0),
+            (6, semitones),
+            (38, 0),
+            (101, 127),
+            (100, 127),
+        ];
+        let mut i = 0;
+        while i < controllers.len() {
+            let at = channel * 18 + i * 3;
+            bytes[at] = status;
+            bytes[at + 1] = controllers[i].0;
+            bytes[at + 2] = controllers[i].1;
+            i += 1;
+        }
+        channel += 1;
+    }
+    bytes
+}
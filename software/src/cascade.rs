@@ -0,0 +1,101 @@
+//! Cascade mode for chained mergers
+//!
+//! Chaining mergers, one's output into another's input, gives a rig more
+//! than four inputs, but the downstream unit can't tell the messages a player
+//! sent from the ones the upstream unit made up itself: its random CC, panic,
+//! note cleanup, snapshot recall and boot setup. It would transpose, rescale
+//! and filter those a second time.
+//!
+//! With `CASCADE` set, every block of voice messages the merger makes up goes
+//! out behind a tag, `F0 7D 4D 4D <dev> 10 <count> F7` with the merger's
+//! `DEVICE_ID` and the number of voice messages in the block (see
+//! `sysex::cascade_tag`). A downstream merger in cascade mode passes the
+//! voice messages a tag covers through without its per-input processing
+//! (gestures, input selection by CC, note ranges, transpose, velocity, CC
+//! ranges, pitch bend), and tags each of them again so mergers further down
+//! do the same. Messages tagged with its own ID have come back around a loop
+//! and are dropped.
+//!
+//! Realtime and system common messages are never tagged: per-input
+//! processing leaves them alone, and a tag in front of every clock would
+//! only delay it. SysEx replies already carry the device ID of the merger
+//! that sent them. Every merger on the chain needs its own `DEVICE_ID`.
+
+use crate::midi_uart::UartChannel;
+use midi_core::parser::MidiMessage;
+
+/// Voice messages in a block of bytes, including running status repeats
+pub fn message_count(bytes: &[u8]) -> u16 {
+    let mut count: u16 = 0;
+    // Data bytes of the current voice status, 0 outside voice messages
+    let mut data_len = 0;
+    let mut remaining = 0;
+    for &byte in bytes {
+        match byte {
+            0x80..=0xEF => {
+                data_len = if matches!(byte & 0xF0, 0xC0 | 0xD0) {
+                    1
+                } else {
+                    2
+                };
+                remaining = data_len;
+            }
+            // Realtime doesn't interrupt a message
+            0xF8..=0xFF => {}
+            // System common and SysEx cancel running status
+            0xF0..=0xF7 => {
+                data_len = 0;
+                remaining = 0;
+            }
+            _ if data_len == 0 => {}
+            _ => {
+                if remaining == 0 {
+                    // Running status
+                    remaining = data_len;
+                }
+                remaining -= 1;
+                if remaining == 0 {
+                    count = count.saturating_add(1);
+                }
+            }
+        }
+    }
+    count
+}
+
+/// Tags received from upstream mergers, per input
+pub struct Cascade {
+    /// Device ID of the merger and the voice messages it still covers
+    covered: [Option<(u8, u16)>; UartChannel::COUNT],
+}
+
+impl Cascade {
+    pub fn new() -> Self {
+        Self {
+            covered: [None; UartChannel::COUNT],
+        }
+    }
+
+    /// Take a tag that arrived on an input, replacing what is left of the
+    /// previous one
+    pub fn tag(&mut self, input: usize, device: u8, count: u16) {
+        self.covered[input] = (count > 0).then_some((device, count));
+    }
+
+    /// The upstream merger that made up a message, if a tag covers it
+    pub fn origin(&mut self, input: usize, message: &MidiMessage) -> Option<u8> {
+        if !matches!(
+            message,
+            MidiMessage::Voice(_) | MidiMessage::RunningStatus(_)
+        ) {
+            return None;
+        }
+        let (device, remaining) = self.covered[input].as_mut()?;
+        let device = *device;
+        *remaining -= 1;
+        if *remaining == 0 {
+            self.covered[input] = None;
+        }
+        Some(device)
+    }
+}
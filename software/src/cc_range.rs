@@ -0,0 +1,78 @@
+//! Per-input Control Change value range rescaling
+//!
+//! A pedal that only reaches 20-110 never sweeps the full range of the
+//! parameter it drives. A `CcRange` in the input's `CC_RANGES` entry
+//! stretches the values of its controller from the range the pedal produces
+//! to the range the destination should see; an output range from high to
+//! low inverts the controller. Values outside the input range are clamped
+//! to it first.
+
+/// Value range rule for one controller
+#[derive(Debug, Clone, Copy)]
+pub struct CcRange {
+    /// MIDI channel, 1-16, or `None` for every channel
+    pub channel: Option<u8>,
+    pub controller: u8,
+    /// Lowest and highest value the controller sends
+    pub input: (u8, u8),
+    /// Values those map to, (127, 0) inverts
+    pub output: (u8, u8),
+}
+
+impl CcRange {
+    /// Whether the rule is usable: a non-empty input range, data byte values
+    pub const fn is_valid(&self) -> bool {
+        self.input.0 < self.input.1
+            && self.input.1 <= 127
+            && self.output.0 <= 127
+            && self.output.1 <= 127
+            && self.controller <= 127
+            && matches!(self.channel, None | Some(1..=16))
+    }
+
+    fn applies(&self, status: u8, controller: u8) -> bool {
+        controller == self.controller
+            && self
+                .channel
+                .is_none_or(|channel| channel - 1 == status & 0x0F)
+    }
+
+    /// Rescaled value, rounded to the nearest step
+    fn rescale(&self, value: u8) -> u8 {
+        let (in_low, in_high) = (self.input.0 as i32, self.input.1 as i32);
+        let (out_low, out_high) = (self.output.0 as i32, self.output.1 as i32);
+        let offset = (value as i32).clamp(in_low, in_high) - in_low;
+        let span = in_high - in_low;
+        let scaled = offset * (out_high - out_low);
+        // Round half away from zero, the output range may run downwards
+        let rounded = (scaled + scaled.signum() * span / 2) / span;
+        (out_low + rounded) as u8
+    }
+}
+
+/// Apply the first matching rule to a Control Change value
+///
+/// # Arguments
+/// * `status` - Control Change status byte, for the channel
+pub fn apply(rules: &[CcRange], status: u8, controller: u8, value: u8) -> u8 {
+    rules
+        .iter()
+        .find(|rule| rule.applies(status, controller))
+        .map_or(value, |rule| rule.rescale(value))
+}
+
+/// Whether every rule of every input is usable, for a compile-time check
+pub const fn all_valid(rules: &[&[CcRange]]) -> bool {
+    let mut i = 0;
+    while i < rules.len() {
+        let mut j = 0;
+        while j < rules[i].len() {
+            if !rules[i][j].is_valid() {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
@@ -0,0 +1,74 @@
+//! Build-time queue and buffer sizes
+//!
+//! The defaults suit a couple of keyboards and controllers. Resource-constrained
+//! or SysEx-heavy builds can override each size with an environment variable
+//! at build time, without patching the source:
+//!
+//! ```text
+//! MIDI_CHANNEL_DEPTH=128 MIDI_UART_RX_BUF_LEN=2048 cargo build --release
+//! ```
+//!
+//! | Variable                 | Default | Size of                        |
+//! |--------------------------|---------|--------------------------------|
+//! | `MIDI_INPUT_QUEUE_DEPTH` | 32      | Each input queue, in messages  |
+//! | `MIDI_CHANNEL_DEPTH`     | 16      | Control channel, in messages   |
+//! | `MIDI_UART_RX_BUF_LEN`   | 256     | Each UART RX buffer, in bytes  |
+//! | `MIDI_UART_TX_BUF_LEN`   | 256     | UART TX buffer, in bytes       |
+//! | `MIDI_SYSEX_CAPTURE_LEN` | 16      | Longest SysEx request captured |
+//!
+//! The `large-rx-buffers` feature raises the RX buffer default to 1024. The
+//! SysEx capture length belongs to the parser
+//! (`midi_core::parser::SYSEX_CAPTURE_LEN`).
+//!
+//! Every queue slot is as large as the largest message, so a longer SysEx
+//! capture costs the extra bytes once for each slot of the input queues and
+//! the control channel. Invalid values fail the build.
+
+/// Depth of each input's queue to `write_uart` (see queues.rs)
+pub const INPUT_QUEUE_DEPTH: usize = env_or(option_env!("MIDI_INPUT_QUEUE_DEPTH"), 32);
+
+/// Depth of the channel of control messages from the other tasks to
+/// `write_uart`
+pub const CHANNEL_DEPTH: usize = env_or(option_env!("MIDI_CHANNEL_DEPTH"), 16);
+
+#[cfg(not(feature = "large-rx-buffers"))]
+const DEFAULT_UART_RX_BUF_LEN: usize = 256;
+#[cfg(feature = "large-rx-buffers")]
+const DEFAULT_UART_RX_BUF_LEN: usize = 1024;
+
+/// Size of each UART RX buffer in bytes
+pub const UART_RX_BUF_LEN: usize =
+    env_or(option_env!("MIDI_UART_RX_BUF_LEN"), DEFAULT_UART_RX_BUF_LEN);
+
+/// Size of the UART TX buffer in bytes
+pub const UART_TX_BUF_LEN: usize = env_or(option_env!("MIDI_UART_TX_BUF_LEN"), 256);
+
+const _: () = {
+    assert!(
+        INPUT_QUEUE_DEPTH > 0,
+        "MIDI_INPUT_QUEUE_DEPTH must not be 0"
+    );
+    assert!(CHANNEL_DEPTH > 0, "MIDI_CHANNEL_DEPTH must not be 0");
+    assert!(UART_RX_BUF_LEN > 0, "MIDI_UART_RX_BUF_LEN must not be 0");
+    assert!(UART_TX_BUF_LEN > 0, "MIDI_UART_TX_BUF_LEN must not be 0");
+};
+
+/// Parse a decimal size at compile time, or fall back to the default
+const fn env_or(value: Option<&str>, default: usize) -> usize {
+    let Some(value) = value else {
+        return default;
+    };
+    let bytes = value.as_bytes();
+    assert!(!bytes.is_empty(), "empty size in build environment");
+    let mut result: usize = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(
+            bytes[i].is_ascii_digit(),
+            "size in build environment is not a number"
+        );
+        result = result * 10 + (bytes[i] - b'0') as usize;
+        i += 1;
+    }
+    result
+}
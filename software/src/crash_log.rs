@@ -0,0 +1,192 @@
+//! Persistent crash log
+//!
+//! The last flash sector holds a record of the most recent crash, so a panic
+//! or hang during a gig can be diagnosed afterwards. The panic handler writes
+//! the panic message, uptime, counters and the last messages received, then
+//! leaves the hardware watchdog to reboot the board. A watchdog reset without
+//! a panic (a task hogging the executor) is recorded at the next boot; the
+//! state of the previous run is lost in that case.
+//!
+//! The record survives until it is cleared with SysEx `08`, and is read back
+//! with SysEx `07` (see sysex.rs).
+
+use crate::midi_uart::UartMidiMessage;
+use crate::reset::{self, ResetReason};
+use crate::stats::{self, Stats};
+use crate::trace;
+use core::cell::RefCell;
+use core::fmt::Write;
+use core::panic::PanicInfo;
+use defmt::Format;
+use embassy_rp::flash::{Blocking, Flash, ERASE_SIZE};
+use embassy_rp::pac;
+use embassy_rp::peripherals::FLASH;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::Instant;
+use heapless::{HistoryBuffer, String, Vec};
+use serde::{Deserialize, Serialize};
+
+/// Flash size of the Pico (W25Q16), must match memory.x
+pub const FLASH_SIZE: usize = 2 * 1024 * 1024;
+
+/// The last sector is reserved for the crash log in memory.x
+const LOG_OFFSET: u32 = (FLASH_SIZE - ERASE_SIZE) as u32;
+
+/// Marks a valid record; cleared by overwriting it with zeroes, which needs
+/// no sector erase
+const MAGIC: u32 = 0x4D4D_4352;
+
+/// Set in watchdog scratch register 0 by the panic handler, so the next boot
+/// knows the watchdog reset was the tail end of a panic
+const PANIC_MARKER: u32 = 0x5041_4E43;
+
+/// Panic message bytes kept (location and message, truncated)
+pub const PANIC_MESSAGE_LEN: usize = 96;
+
+/// Number of most recent input messages kept
+pub const RECENT_LEN: usize = 8;
+
+// Magic and postcard length prefix, then the postcard encoded record
+const HEADER_LEN: usize = 6;
+// Room for the worst case postcard size of a record (~250 bytes)
+const RECORD_BUF_LEN: usize = 320;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format, Serialize, Deserialize)]
+pub enum CrashReason {
+    Panic = 1,
+    Watchdog = 2,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CrashRecord {
+    pub reason: CrashReason,
+    pub uptime_secs: u32,
+    pub stats: Stats,
+    /// Oldest first, each packed by `trace::pack()`
+    pub recent: Vec<u32, RECENT_LEN>,
+    pub message: String<PANIC_MESSAGE_LEN>,
+}
+
+static RECENT: Mutex<CriticalSectionRawMutex, RefCell<HistoryBuffer<u32, RECENT_LEN>>> =
+    Mutex::new(RefCell::new(HistoryBuffer::new()));
+
+/// Remember a received message for the crash record
+pub fn record_message(message: &UartMidiMessage) {
+    let packed = trace::pack(message.uart_channel, message.message.bytes());
+    RECENT.lock(|recent| recent.borrow_mut().write(packed));
+}
+
+/// Check for a crash of the previous run and log the stored record
+///
+/// Must run once at boot, after `reset::init()`.
+pub fn check_boot() {
+    let watchdog = pac::WATCHDOG;
+    let panicked = watchdog.scratch0().read() == PANIC_MARKER;
+    watchdog.scratch0().write(|w| *w = 0);
+    if !panicked && reset::reason() == ResetReason::Watchdog {
+        store(&CrashRecord {
+            reason: CrashReason::Watchdog,
+            uptime_secs: 0,
+            stats: Stats::default(),
+            recent: Vec::new(),
+            message: String::new(),
+        });
+    }
+
+    if let Some(record) = load() {
+        defmt::println!(
+            "Crash log: {:?} after {}s: {=str}",
+            record.reason,
+            record.uptime_secs,
+            record.message.as_str()
+        );
+    }
+}
+
+/// Read the stored crash record, if any
+pub fn load() -> Option<CrashRecord> {
+    let mut buf = [0u8; HEADER_LEN + RECORD_BUF_LEN];
+    flash().blocking_read(LOG_OFFSET, &mut buf).ok()?;
+    let (header, body) = buf.split_at(HEADER_LEN);
+    if u32::from_le_bytes([header[0], header[1], header[2], header[3]]) != MAGIC {
+        return None;
+    }
+    let len = u16::from_le_bytes([header[4], header[5]]) as usize;
+    postcard::from_bytes(body.get(..len)?).ok()
+}
+
+/// Invalidate the stored crash record
+pub fn clear() {
+    if flash().blocking_write(LOG_OFFSET, &[0; 4]).is_err() {
+        defmt::error!("Failed to clear crash log");
+    }
+}
+
+fn store(record: &CrashRecord) {
+    let mut buf = [0u8; HEADER_LEN + RECORD_BUF_LEN];
+    let Ok(len) = postcard::to_slice(record, &mut buf[HEADER_LEN..]).map(|body| body.len()) else {
+        return;
+    };
+    buf[..4].copy_from_slice(&MAGIC.to_le_bytes());
+    buf[4..HEADER_LEN].copy_from_slice(&(len as u16).to_le_bytes());
+
+    let mut flash = flash();
+    let written = flash
+        .blocking_erase(LOG_OFFSET, LOG_OFFSET + ERASE_SIZE as u32)
+        .and_then(|_| flash.blocking_write(LOG_OFFSET, &buf[..HEADER_LEN + len]));
+    if written.is_err() {
+        defmt::error!("Failed to write crash log");
+    }
+}
+
+fn flash() -> Flash<'static, FLASH, Blocking, FLASH_SIZE> {
+    // Only this module touches the flash, and never from two places at once:
+    // the boot check finishes before the write task starts, and the panic
+    // handler disables interrupts before writing
+    Flash::new_blocking(unsafe { FLASH::steal() })
+}
+
+/// Truncates instead of failing when the panic message doesn't fit
+struct Truncating<'a>(&'a mut String<PANIC_MESSAGE_LEN>);
+
+impl Write for Truncating<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for c in s.chars() {
+            if self.0.push(if c.is_ascii() { c } else { '?' }).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    static mut PANICKED: bool = false;
+
+    cortex_m::interrupt::disable();
+    // A panic while writing the record must not recurse
+    // SAFETY: interrupts are disabled and the RP2040 only runs core 0
+    if unsafe { core::mem::replace(&mut *core::ptr::addr_of_mut!(PANICKED), true) } {
+        cortex_m::asm::udf();
+    }
+
+    defmt::error!("{}", defmt::Display2Format(info));
+
+    let mut message = String::new();
+    let _ = write!(Truncating(&mut message), "{}", info);
+    let recent = RECENT.lock(|recent| recent.borrow().oldest_ordered().copied().collect());
+    store(&CrashRecord {
+        reason: CrashReason::Panic,
+        uptime_secs: Instant::now().as_secs() as u32,
+        stats: stats::snapshot(),
+        recent,
+        message,
+    });
+    pac::WATCHDOG.scratch0().write(|w| *w = PANIC_MARKER);
+
+    // Same exit as panic-probe: a debugger shows the fault, otherwise the
+    // watchdog reboots the board
+    cortex_m::asm::udf()
+}
@@ -0,0 +1,136 @@
+//! Generative random CC source
+//!
+//! With `RANDOM_CC` set, an internal task walks one controller randomly
+//! within a range, a few steps up or down at a time, and `write_uart` mixes
+//! the values into the merged output like a third player turning a knob. The
+//! walk steps on a timer, or every few MIDI clocks received on any input, so
+//! it follows the tempo of the rig.
+//!
+//! Values are only sent when they change. Generated messages always carry
+//! their status byte.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use midi_core::parser::{MidiMessage, ShortMessage};
+
+/// When the walk takes a step
+// Only constructed by a `RANDOM_CC` setting, which is off by default
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rate {
+    /// Every this many milliseconds
+    Millis(u64),
+    /// Every this many MIDI clocks, 24 per quarter note
+    Clocks(u32),
+}
+
+/// Settings of the random CC source
+#[derive(Debug, Clone, Copy)]
+pub struct RandomCc {
+    /// MIDI channel, 1-16
+    pub channel: u8,
+    pub controller: u8,
+    /// Lowest and highest value sent
+    pub range: (u8, u8),
+    /// Largest change per step
+    pub max_step: u8,
+    pub rate: Rate,
+}
+
+impl RandomCc {
+    /// The Control Change message for a value
+    pub fn message(&self, value: u8) -> MidiMessage {
+        let bytes = [0xB0 | (self.channel - 1), self.controller, value];
+        MidiMessage::Voice(ShortMessage::from_slice(&bytes).unwrap())
+    }
+}
+
+/// Bounded random walk, driven by a xorshift generator
+pub struct RandomWalk {
+    state: u32,
+    value: u8,
+    low: u8,
+    high: u8,
+    max_step: u8,
+}
+
+impl RandomWalk {
+    /// Start in the middle of the range
+    ///
+    /// # Arguments
+    /// * `seed` - Generator seed, 0 is replaced as xorshift would stay at 0
+    pub fn new(seed: u32, range: (u8, u8), max_step: u8) -> Self {
+        let (low, high) = range;
+        Self {
+            state: if seed == 0 { 0x9E37_79B9 } else { seed },
+            value: low + (high - low) / 2,
+            low,
+            high,
+            max_step,
+        }
+    }
+
+    fn next_random(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+
+    pub fn value(&self) -> u8 {
+        self.value
+    }
+
+    /// Take a step, bouncing off the ends of the range, and return the value
+    pub fn step(&mut self) -> u8 {
+        let span = 2 * self.max_step as u32 + 1;
+        let delta = (self.next_random() % span) as i16 - self.max_step as i16;
+        let mut value = self.value as i16 + delta;
+        if value > self.high as i16 {
+            value = 2 * self.high as i16 - value;
+        }
+        if value < self.low as i16 {
+            value = 2 * self.low as i16 - value;
+        }
+        // A step larger than the range can bounce out again
+        self.value = value.clamp(self.low as i16, self.high as i16) as u8;
+        self.value
+    }
+}
+
+// MIDI clocks received since boot, written by the read tasks only
+static CLOCKS: AtomicU32 = AtomicU32::new(0);
+static CLOCK: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Count a MIDI clock from one of the inputs
+pub fn clock_tick() {
+    // Single writer: the read tasks share the thread-mode executor
+    CLOCKS.store(
+        CLOCKS.load(Ordering::Relaxed).wrapping_add(1),
+        Ordering::Relaxed,
+    );
+    CLOCK.signal(());
+}
+
+/// Wait until at least `count` clocks have arrived since `since`
+///
+/// Returns the clock count to wait from next time.
+pub async fn wait_clocks(since: u32, count: u32) -> u32 {
+    loop {
+        let now = CLOCKS.load(Ordering::Relaxed);
+        let elapsed = now.wrapping_sub(since);
+        if elapsed >= count {
+            // Steps missed while busy are skipped, not caught up on
+            return since.wrapping_add(elapsed - elapsed % count);
+        }
+        CLOCK.wait().await;
+    }
+}
+
+/// Current clock count, to start waiting from
+pub fn clocks() -> u32 {
+    CLOCKS.load(Ordering::Relaxed)
+}
@@ -0,0 +1,95 @@
+//! Panic gesture
+//!
+//! With `PANIC_GESTURE` set, striking its key the given number of times
+//! within the window, on any input and MIDI channel, makes `write_uart` send
+//! All Sound Off and All Notes Off on all 16 channels. Stuck notes can then
+//! be cleared from the keyboard when the merger is racked out of reach. The
+//! key presses themselves are forwarded as usual.
+
+use crate::midi_uart::UartChannel;
+use embassy_time::{Duration, Instant};
+use midi_core::event::Event;
+use midi_core::parser::MidiMessage;
+
+/// All Sound Off (CC 120) and All Notes Off (CC 123) on every channel
+pub const PANIC: [u8; 96] = {
+    let mut bytes = [0; 96];
+    let mut channel = 0;
+    while channel < 16 {
+        bytes[channel * 6] = 0xB0 | channel as u8;
+        bytes[channel * 6 + 1] = 120;
+        bytes[channel * 6 + 3] = 0xB0 | channel as u8;
+        bytes[channel * 6 + 4] = 123;
+        channel += 1;
+    }
+    bytes
+};
+
+/// The key presses that trigger a panic
+#[derive(Debug, Clone, Copy)]
+pub struct PanicGesture {
+    /// Note number of the key, e.g. 108 for the top C of an 88-key keyboard
+    pub note: u8,
+    pub presses: u8,
+    /// Time from the first press to the last
+    pub window: Duration,
+}
+
+/// Presses of the gesture key so far on an input
+#[derive(Debug, Clone, Copy)]
+struct Presses {
+    count: u8,
+    first: Instant,
+}
+
+pub struct GestureDetector {
+    gesture: Option<PanicGesture>,
+    presses: [Option<Presses>; UartChannel::COUNT],
+}
+
+impl GestureDetector {
+    pub fn new(gesture: Option<PanicGesture>) -> Self {
+        Self {
+            gesture,
+            presses: [None; UartChannel::COUNT],
+        }
+    }
+
+    /// Take a message from an input, returns whether it completes the gesture
+    ///
+    /// # Arguments
+    /// * `cached_status` - The input's last status, resolves running status
+    pub fn completes(
+        &mut self,
+        channel: UartChannel,
+        message: &MidiMessage,
+        cached_status: Option<u8>,
+        now: Instant,
+    ) -> bool {
+        let Some(gesture) = self.gesture else {
+            return false;
+        };
+        let Some(Event::NoteOn { note, velocity, .. }) = message.event(cached_status) else {
+            return false;
+        };
+        if note != gesture.note || velocity == 0 {
+            return false;
+        }
+
+        let presses = &mut self.presses[channel.index()];
+        match presses {
+            Some(Presses { count, first }) if now - *first <= gesture.window => *count += 1,
+            _ => {
+                *presses = Some(Presses {
+                    count: 1,
+                    first: now,
+                })
+            }
+        }
+        if presses.is_some_and(|presses| presses.count >= gesture.presses) {
+            *presses = None;
+            return true;
+        }
+        false
+    }
+}
@@ -0,0 +1,128 @@
+//! Cable unplug and replug on the UART inputs
+//!
+//! Pulling a MIDI cable mid-phrase leaves the notes of that player hanging
+//! on every receiver, and the plug often shorts the line into a Break on the
+//! way out. With `HOTPLUG_DETECTION`, a Break on a UART input counts as an
+//! unplug, and so does silence for `HOTPLUG_IDLE`, if set. The read task has
+//! already flushed the parser by then (on the Break, or by the byte timeout
+//! after silence), and `write_uart` sends a Note Off for every note of that
+//! input still sounding on the output. The first message afterwards logs the
+//! input as reconnected.
+//!
+//! An input counts as plugged in from its first message on. Devices sending
+//! Active Sensing keep the idle timer from running out between notes; for
+//! others `HOTPLUG_IDLE` has to outlast the longest note held.
+
+use crate::midi_uart::UartChannel;
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::{Duration, Instant};
+use heapless::Vec;
+
+/// Inputs a cable can be pulled from
+const CABLE_INPUTS: [UartChannel; 2] = [UartChannel::Zero, UartChannel::One];
+
+/// Notes each input has sounding on the output
+pub struct HeldNotes {
+    /// One bit per note number, per MIDI channel
+    notes: [[u128; 16]; UartChannel::COUNT],
+}
+
+impl HeldNotes {
+    pub const fn new() -> Self {
+        Self {
+            notes: [[0; 16]; UartChannel::COUNT],
+        }
+    }
+
+    /// Track a voice message written to the output
+    ///
+    /// # Arguments
+    /// * `status` - Status of the message, also when sent with running status
+    /// * `bytes` - The message as written, data bytes last
+    pub fn track(&mut self, input: usize, status: u8, bytes: &[u8]) {
+        let [.., note, velocity] = bytes else {
+            return;
+        };
+        let notes = &mut self.notes[input][(status & 0x0F) as usize];
+        let bit = 1u128 << (note & 0x7F);
+        match status & 0xF0 {
+            0x90 if *velocity > 0 => *notes |= bit,
+            0x80 | 0x90 => *notes &= !bit,
+            _ => {}
+        }
+    }
+
+    /// Take the next note of an input still sounding, as a Note Off
+    pub fn release(&mut self, input: usize) -> Option<[u8; 3]> {
+        self.notes[input]
+            .iter_mut()
+            .enumerate()
+            .find(|(_, notes)| **notes != 0)
+            .map(|(channel, notes)| {
+                let note = notes.trailing_zeros();
+                *notes &= !(1 << note);
+                [0x80 | channel as u8, note as u8, 0]
+            })
+    }
+}
+
+/// Whether a cable is in an input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Link {
+    /// No message yet
+    Unknown,
+    /// Last message received at
+    Active(Instant),
+    Unplugged,
+}
+
+static LINKS: Mutex<CriticalSectionRawMutex, RefCell<[Link; UartChannel::COUNT]>> =
+    Mutex::new(RefCell::new([Link::Unknown; UartChannel::COUNT]));
+
+/// Note a message from an input
+///
+/// Returns true if the input was unplugged, so the message is the first
+/// after a replug.
+pub fn seen(channel: UartChannel, now: Instant) -> bool {
+    LINKS.lock(|links| {
+        let link = &mut links.borrow_mut()[channel.index()];
+        let replugged = *link == Link::Unplugged;
+        *link = Link::Active(now);
+        replugged
+    })
+}
+
+/// Mark an input as unplugged
+///
+/// Returns false if it already was.
+pub fn unplug(channel: UartChannel) -> bool {
+    LINKS.lock(|links| {
+        let link = &mut links.borrow_mut()[channel.index()];
+        let unplugged = *link != Link::Unplugged;
+        *link = Link::Unplugged;
+        unplugged
+    })
+}
+
+/// Mark the cable inputs silent for `idle` or longer as unplugged
+///
+/// Returns the inputs newly unplugged.
+pub fn unplug_silent(idle: Duration, now: Instant) -> Vec<UartChannel, 2> {
+    LINKS.lock(|links| {
+        let mut links = links.borrow_mut();
+        let mut silent = Vec::new();
+        for channel in CABLE_INPUTS {
+            let link = &mut links[channel.index()];
+            if let Link::Active(last) = *link {
+                if now.saturating_duration_since(last) >= idle {
+                    *link = Link::Unplugged;
+                    // Sized for all cable inputs
+                    let _ = silent.push(channel);
+                }
+            }
+        }
+        silent
+    })
+}
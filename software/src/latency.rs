@@ -0,0 +1,118 @@
+use crate::log;
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::Instant;
+
+/// Loopback latency statistics, all values in microseconds
+///
+/// Jitter is the smoothed mean deviation between consecutive samples, as
+/// used for RTP interarrival jitter (RFC 3550, section 6.4.1).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LatencyStats {
+    pub samples: u32,
+    pub min_us: u32,
+    pub max_us: u32,
+    pub mean_us: u32,
+    pub jitter_us: u32,
+}
+
+#[derive(Debug)]
+struct Accumulator {
+    samples: u32,
+    min_us: u32,
+    max_us: u32,
+    total_us: u64,
+    last_us: Option<u32>,
+    jitter_us: u32,
+}
+
+impl Accumulator {
+    const fn new() -> Self {
+        Self {
+            samples: 0,
+            min_us: u32::MAX,
+            max_us: 0,
+            total_us: 0,
+            last_us: None,
+            jitter_us: 0,
+        }
+    }
+
+    fn add(&mut self, latency_us: u32) {
+        self.samples = self.samples.wrapping_add(1);
+        self.min_us = self.min_us.min(latency_us);
+        self.max_us = self.max_us.max(latency_us);
+        self.total_us += latency_us as u64;
+        if let Some(last_us) = self.last_us {
+            let deviation = latency_us.abs_diff(last_us);
+            // J += (|D| - J) / 16, in integer arithmetic
+            self.jitter_us = ((self.jitter_us as i64 * 15 + deviation as i64) / 16) as u32;
+        }
+        self.last_us = Some(latency_us);
+    }
+
+    fn stats(&self) -> LatencyStats {
+        if self.samples == 0 {
+            return LatencyStats::default();
+        }
+        LatencyStats {
+            samples: self.samples,
+            min_us: self.min_us,
+            max_us: self.max_us,
+            mean_us: (self.total_us / self.samples as u64) as u32,
+            jitter_us: self.jitter_us,
+        }
+    }
+}
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+static ACCUMULATOR: Mutex<CriticalSectionRawMutex, RefCell<Accumulator>> =
+    Mutex::new(RefCell::new(Accumulator::new()));
+
+/// Start a latency test, discarding the results of any previous run
+///
+/// While the test runs, `write_uart` emits a probe SysEx at a fixed interval
+/// and the user patches the output back into one of the inputs.
+pub fn start() {
+    ACCUMULATOR.lock(|acc| *acc.borrow_mut() = Accumulator::new());
+    RUNNING.store(true, Ordering::Relaxed);
+}
+
+/// Stop the latency test, keeping the results for `snapshot()`
+pub fn stop() {
+    RUNNING.store(false, Ordering::Relaxed);
+}
+
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::Relaxed)
+}
+
+/// Timestamp to embed in an outgoing probe
+///
+/// Microseconds since boot, truncated to 32 bits. The wrap every ~71 minutes
+/// is harmless because latencies are computed with wrapping arithmetic.
+pub fn probe_timestamp() -> u32 {
+    Instant::now().as_micros() as u32
+}
+
+/// Record a probe that came back on an input
+///
+/// # Arguments
+/// * `sent_us` - Timestamp embedded in the probe when it was written
+pub fn record(sent_us: u32) {
+    if !is_running() {
+        // Late probe from a stopped test
+        return;
+    }
+    let latency_us = probe_timestamp().wrapping_sub(sent_us);
+    ACCUMULATOR.lock(|acc| acc.borrow_mut().add(latency_us));
+    log::debug!("Loopback latency {}us", latency_us);
+}
+
+/// Results of the current (or last) latency test
+pub fn snapshot() -> LatencyStats {
+    ACCUMULATOR.lock(|acc| acc.borrow().stats())
+}
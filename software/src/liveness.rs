@@ -0,0 +1,70 @@
+//! Task liveness tracking
+//!
+//! The pipeline tasks spend most of their time waiting for input, which can
+//! legitimately take forever, so they can't be expected to check in on a
+//! fixed schedule. Instead each task marks itself idle before waiting for
+//! input and busy once it has some. A task that stays busy for longer than
+//! `STALL_TIMEOUT` is stuck: blocked on a full channel behind a dead writer,
+//! or waiting for an output that never drains.
+//!
+//! A task spinning without awaiting stalls the whole executor, including the
+//! supervisor; that case is left to the hardware watchdog.
+
+use crate::midi_uart::UartChannel;
+use core::cell::RefCell;
+use defmt::Format;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::{Duration, Instant};
+
+/// How long a task may stay busy before it counts as stalled
+pub const STALL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Tasks watched by the supervisor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum Task {
+    /// The read (or bridge) task of an input
+    Input(UartChannel),
+    /// The merge task writing the output
+    Write,
+}
+
+impl Task {
+    pub const COUNT: usize = UartChannel::COUNT + 1;
+
+    pub const ALL: [Task; Task::COUNT] = [
+        Task::Input(UartChannel::Zero),
+        Task::Input(UartChannel::One),
+        Task::Input(UartChannel::I2c),
+        Task::Input(UartChannel::Spi),
+        Task::Write,
+    ];
+
+    fn index(self) -> usize {
+        match self {
+            Task::Input(channel) => channel.index(),
+            Task::Write => UartChannel::COUNT,
+        }
+    }
+}
+
+/// `None` while idle, otherwise when the task became busy
+static BUSY_SINCE: Mutex<CriticalSectionRawMutex, RefCell<[Option<Instant>; Task::COUNT]>> =
+    Mutex::new(RefCell::new([None; Task::COUNT]));
+
+/// Mark a task as waiting for input
+pub fn idle(task: Task) {
+    BUSY_SINCE.lock(|busy| busy.borrow_mut()[task.index()] = None);
+}
+
+/// Mark a task as processing
+pub fn busy(task: Task) {
+    BUSY_SINCE.lock(|busy| busy.borrow_mut()[task.index()] = Some(Instant::now()));
+}
+
+/// Whether the task has been busy for longer than `STALL_TIMEOUT`
+pub fn is_stalled(task: Task) -> bool {
+    BUSY_SINCE.lock(|busy| {
+        busy.borrow()[task.index()].is_some_and(|since| since.elapsed() > STALL_TIMEOUT)
+    })
+}
@@ -0,0 +1,4 @@
+//! Runtime log level on top of defmt, shared with midi-core (see
+//! `midi_core::log`)
+
+pub use midi_core::log::*;
@@ -1,22 +1,447 @@
 #![no_std]
 #![no_main]
 
+use cascade::Cascade;
+use cc_range::CcRange;
+use config::{CHANNEL_DEPTH, UART_RX_BUF_LEN, UART_TX_BUF_LEN};
 use defmt_rtt as _;
-use embassy_executor::Spawner;
+use embassy_executor::{InterruptExecutor, Spawner};
+use embassy_futures::select::{select, Either};
 use embassy_rp::bind_interrupts;
-use embassy_rp::peripherals::{UART0, UART1};
+#[cfg(feature = "random-cc")]
+use embassy_rp::clocks::RoscRng;
+use embassy_rp::gpio::{Input, Level, Output, Pull};
+use embassy_rp::i2c::AbortReason;
+use embassy_rp::i2c_slave::I2cSlave;
+use embassy_rp::interrupt;
+use embassy_rp::interrupt::{InterruptExt, Priority};
+#[cfg(feature = "led-meter")]
+use embassy_rp::peripherals::PIO0;
+use embassy_rp::peripherals::{I2C0, SPI0, UART0, UART1};
+#[cfg(feature = "led-meter")]
+use embassy_rp::pio::Pio;
+use embassy_rp::spi::Spi;
 use embassy_rp::uart::{
     BufferedInterruptHandler, BufferedUart, BufferedUartRx, BufferedUartTx, Config, Instance,
 };
-use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_rp::watchdog::Watchdog;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::channel::Channel;
-use embedded_io_async::Write;
-use midi_parser::{MidiMessage, MidiMessageError};
+use embassy_sync::mutex::Mutex;
+use embassy_time::{with_timeout, Duration, Instant, Timer};
+use embedded_io_async::{BufRead, Write};
+#[cfg(feature = "random-cc")]
+use generative::{RandomCc, RandomWalk, Rate};
+use gesture::{GestureDetector, PanicGesture};
+use hotplug::HeldNotes;
+use liveness::Task;
+use log::LogLevel;
+use midi_core::event::Event;
+use midi_core::merge::Merger;
+use midi_core::parser::{
+    MidiMessage, MidiMessageError, MidiParser, ResyncPolicy, UndefinedRealtime,
+};
+#[cfg(feature = "timecode")]
+use midi_core::timecode::{self, ClockOutput, ClockToMtc, FrameRate, MtcToClock, Timecode};
+use midi_i2c::{I2cMidiError, MidiI2c};
 use midi_uart::{MidiUart, UartChannel, UartMidiError, UartMidiMessage};
-use panic_probe as _;
+use monitor::Source;
+use pacing::Pacer;
+use pairing::PairHold;
+use quantize::{Relay, TransportQuantizer};
+use queues::{InputSender, Inputs};
+#[cfg(feature = "random-cc")]
+use rand_core::RngCore;
+use reinit::Escalation;
+use selector::Selector;
+use spi_bridge::{SpiBridge, SpiBridgeError};
+use static_cell::ConstStaticCell;
+use sysex::{ModuleReset, Request};
+use thru::ThruFilter;
+use trace::ErrorKind;
+use transpose::Transposer;
+use velocity::Dynamics;
+#[cfg(feature = "led-meter")]
+use ws2812::{Rgb, Ws2812};
 
-mod midi_parser;
+mod bend;
+mod cascade;
+mod cc_range;
+mod config;
+mod crash_log;
+#[cfg(feature = "random-cc")]
+mod generative;
+mod gesture;
+mod hotplug;
+mod latency;
+mod liveness;
+mod log;
+#[cfg(feature = "led-meter")]
+mod meter;
+mod midi_i2c;
 mod midi_uart;
+mod monitor;
+mod pacing;
+mod pairing;
+mod profiling;
+mod pulse;
+mod quantize;
+mod queues;
+mod reinit;
+mod reset;
+mod selector;
+mod selftest;
+mod snapshot;
+mod spi_bridge;
+mod stats;
+mod sysex;
+mod tempo;
+mod thru;
+mod trace;
+mod transpose;
+mod velocity;
+#[cfg(feature = "led-meter")]
+mod ws2812;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+// RX polarity inversion per input port
+//
+// A standard MIDI input (6N138 optocoupler with pull-up) idles high, which is
+// what the UART expects. Some DIY front-ends use an inverting stage instead, so
+// the line idles low and every byte arrives as framing errors. Set the flag for
+// the affected port to let the UART invert the signal in hardware.
+const UART0_INVERT_RX: bool = false;
+const UART1_INVERT_RX: bool = false;
+
+// SysEx device ID of this merger (0-126), so several mergers on one chain can
+// be addressed individually. Every merger also answers to 0x7F.
+pub const DEVICE_ID: u8 = 0x00;
+
+// Cascade mode for chained mergers (see cascade.rs): voice messages the merger
+// makes up itself are tagged with DEVICE_ID, and tagged messages from an
+// upstream merger skip the per-input processing. Every merger on the chain
+// needs its own DEVICE_ID.
+const CASCADE: bool = false;
+
+// I2C target address of the I2C MIDI input port
+const I2C_MIDI_ADDRESS: u16 = 0x55;
+
+// SPI clock of the co-processor bridge, and how often the co-processor is
+// polled for messages when we have nothing to send
+const SPI_BRIDGE_FREQUENCY: u32 = 1_000_000;
+const SPI_BRIDGE_POLL_INTERVAL_MS: u64 = 1;
+
+// Output write retries after a TX error, with a backoff doubling from one
+// byte time at 31250 baud
+const TX_RETRY_LIMIT: u32 = 3;
+const TX_RETRY_BACKOFF_US: u64 = 320;
+
+// Minimum gap between output messages for slow receivers, 0 disables pacing
+// (see pacing.rs). E.g. 2000 limits the output to 500 messages per second.
+const OUTPUT_MIN_GAP_US: u64 = 0;
+
+// Output bursts: while more messages are queued, `write_uart` collects the
+// prepared messages and writes up to this many bytes at once, instead of
+// taking the output for every message. Bursts are flushed before any other
+// output, and with the `realtime-bypass` feature realtime bytes still get in
+// between them; 32 bytes take 10 ms on the wire. 0 writes every message on
+// its own. Pacing (OUTPUT_MIN_GAP_US) turns bursts off.
+const OUTPUT_BURST_LEN: usize = 0;
+
+// Release velocity written for every Note Off, for receivers that misread
+// it (e.g. Some(64), the spec's default). None passes it through untouched.
+const NOTE_OFF_VELOCITY: Option<u8> = None;
+
+// Parse Note On with velocity 0 as Note Off (0x8n) on all inputs, so the
+// output and the note tracking see one kind of note end whatever the device
+// sends. Costs a status byte on the wire per note end that would otherwise
+// ride on running status. E.g. true for receivers that ignore velocity 0.
+const NORMALIZE_NOTE_OFF: bool = false;
+
+// Parse running status into full messages on all parsed inputs, so
+// write_uart doesn't resolve it from its status cache. The status comes from
+// the parser instead, which RUNNING_STATUS_EXPIRY also expires. Costs a
+// status byte on the wire per message that would otherwise ride on running
+// status. E.g. true for receivers that mishandle running status.
+const EXPAND_RUNNING_STATUS: bool = false;
+
+// Control Change value ranges of each input (see cc_range.rs), applied to the
+// first matching rule. E.g. &[CcRange { channel: None, controller: 11,
+// input: (20, 110), output: (0, 127) }] lets an expression pedal that only
+// reaches 20-110 sweep the full range.
+const CC_RANGES: [&[CcRange]; UartChannel::COUNT] = [&[], &[], &[], &[]];
+
+const _: () = assert!(cc_range::all_valid(&CC_RANGES));
+
+// Velocity compressor/expander of each input, applied to Note On velocities
+// after calibration (see velocity.rs). None passes them unchanged. E.g.
+// Some(Dynamics { threshold: 64, ratio: (3, 1), makeup: 10 }) tames a
+// player who hits hard.
+const VELOCITY_DYNAMICS: [Option<Dynamics>; UartChannel::COUNT] = [None; UartChannel::COUNT];
+
+// Pitch bend range of each input's sender in semitones (see bend.rs), None
+// passes bends untouched. Bends of an input with a range are rescaled to
+// PITCH_BEND_DESTINATION, e.g. Some(2) for a controller at ±2 driving a
+// module fixed at ±12. PITCH_BEND_SETUP also sends RPN 0 with the destination
+// range on all channels at boot.
+const PITCH_BEND_SOURCES: [Option<u8>; UartChannel::COUNT] = [None; UartChannel::COUNT];
+const PITCH_BEND_DESTINATION: u8 = 12;
+const PITCH_BEND_SETUP: bool = false;
+
+const _: () = assert!(PITCH_BEND_DESTINATION > 0);
+
+// Notes each input passes, as (lowest, highest) note number, e.g. (36, 127)
+// ignores the bottom octave of a controller whose lowest keys play another
+// rig. Note On, Note Off and poly pressure outside the range are dropped.
+const NOTE_RANGES: [(u8, u8); UartChannel::COUNT] = [(0, 127); UartChannel::COUNT];
+
+// How the input parsers recover from bad bytes: Strict resyncs on every one,
+// Permissive skips them, Budget(n) skips up to n between complete messages.
+// Noisy cables on a stage may do better with e.g. Budget(2).
+const RESYNC_POLICY: ResyncPolicy = ResyncPolicy::Strict;
+
+// What the input parsers do with the undefined realtime bytes 0xF9 and 0xFD:
+// Reject treats them as bad bytes under RESYNC_POLICY, Drop discards them
+// quietly, Forward passes them to the output like a clock. Gear that sends
+// them regardless otherwise costs a resync each time, e.g. Drop for it.
+const UNDEFINED_REALTIME: UndefinedRealtime = UndefinedRealtime::Reject;
+
+// Longest gap between the bytes of a message per input before its parser
+// drops the message and resyncs. Slow legacy gear that pauses mid-message
+// needs more than the default 300 ms, e.g. Duration::from_secs(1); a fast
+// controller recovers sooner from a glitch with less. SPI has no parser.
+const BYTE_TIMEOUTS: [Duration; UartChannel::COUNT] =
+    [MidiParser::DEFAULT_BYTE_TIMEOUT; UartChannel::COUNT];
+
+// Restart the receiver of a UART input whose parser keeps resyncing (see
+// reinit.rs), e.g. `Escalation { resyncs: 50, window: Duration::from_secs(1) }`.
+// A baud mismatch or a stuck receiver otherwise resyncs forever.
+const UART_REINIT: Option<Escalation> = None;
+
+// Cable unplug detection on the UART inputs (see hotplug.rs): a Break, or
+// silence for HOTPLUG_IDLE if set, releases the notes the input still holds
+// on the output. Without Active Sensing from the device, the idle time has to
+// outlast the longest held note, e.g. Some(Duration::from_secs(30)).
+const HOTPLUG_DETECTION: bool = false;
+const HOTPLUG_IDLE: Option<Duration> = None;
+
+// Silence after which an input's running status is forgotten, None keeps it
+// forever. A device restarting after a long pause with running status
+// against a status seen minutes ago has its data-only messages dropped until
+// it sends a status byte, instead of them going out on the old status. The
+// parsers of the UART and I2C inputs forget theirs (with or without
+// EXPAND_RUNNING_STATUS), write_uart its status cache, which the SPI input's
+// messages resolve against. Realtime messages don't count as activity. E.g.
+// Some(Duration::from_secs(10)).
+const RUNNING_STATUS_EXPIRY: Option<Duration> = None;
+
+// Forward SysEx from the inputs to the output, e.g. patch dumps from a synth
+// to a librarian on the output. SysEx on the UART inputs longer than the
+// capture buffer is streamed in chunks as it arrives (see
+// `MidiParser::set_sysex_streaming`), and the output belongs to that input
+// until its F7: the other inputs and the control messages wait, only realtime
+// messages go between the chunks. Messages of the other inputs that don't fit
+// in their queues meanwhile are dropped (counted as queue drops), so the
+// streaming input is never left unread. A stream that stops short is closed
+// with an F7 once the input sends something else or stays silent for
+// SYSEX_STREAM_TIMEOUT. SysEx addressed to the merger is answered, not
+// forwarded.
+const SYSEX_FORWARDING: bool = false;
+const SYSEX_STREAM_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Longest time messages from other inputs are held back so the LSB of a
+// 14-bit controller follows its MSB directly, and the next step of an RPN or
+// NRPN edit follows the one before (see pairing.rs), 0 disables it. Two
+// message times at 31250 baud. Only controllers an input has sent an LSB for
+// are held, plain 7-bit CCs never delay the other inputs.
+const CC_PAIR_HOLD_US: u64 = 2000;
+
+// Output filters of thru-box mode (see thru.rs), for UART0 TX and UART1 TX
+// (GPIO4). E.g. `ThruFilter { channels: 1 << 9, types: thru::types::NOTES }`
+// passes only the notes on channel 10.
+const THRU_FILTERS: [ThruFilter; 2] = [ThruFilter::ALL, ThruFilter::ALL];
+
+// Splitter mode: with the thru jumper closed, input 1 is split by channel
+// instead of mirrored: channels SPLIT_A (first, last) go to UART0 TX (OUT A),
+// SPLIT_B to UART1 TX (OUT B), system messages to both
+const SPLITTER_MODE: bool = false;
+const SPLIT_A: (u8, u8) = (1, 8);
+const SPLIT_B: (u8, u8) = (9, 16);
+
+const OUTPUT_FILTERS: [ThruFilter; 2] = if SPLITTER_MODE {
+    [
+        ThruFilter::channels(SPLIT_A.0, SPLIT_A.1),
+        ThruFilter::channels(SPLIT_B.0, SPLIT_B.1),
+    ]
+} else {
+    THRU_FILTERS
+};
+
+// Clock and transport (Clock, Start, Continue, Stop, Song Position Pointer)
+// per output of thru-box and splitter mode, on top of the filters above.
+// E.g. [true, false] keeps the clock from an effects unit on OUT B that
+// misuses it.
+const OUTPUT_CLOCK: [bool; 2] = [true, true];
+
+const _: () = {
+    assert!(SPLIT_A.0 >= 1 && SPLIT_A.0 <= SPLIT_A.1 && SPLIT_A.1 <= 16);
+    assert!(SPLIT_B.0 >= 1 && SPLIT_B.0 <= SPLIT_B.1 && SPLIT_B.1 <= 16);
+};
+
+// A/B selector mode (see selector.rs): instead of merging, only the selected
+// input reaches the output. The button on GPIO10 (active low) steps through
+// the inputs, and this Control Change from any input selects one by value
+// (0-31 input 1, 32-63 input 2, 64-95 I2C, 96-127 SPI); None disables the CC.
+// 119 is undefined in the spec.
+const SELECTOR_MODE: bool = false;
+const SELECTOR_CC: Option<u8> = Some(119);
+const SELECTOR_DEBOUNCE_MS: u64 = 20;
+
+// Panic gesture (see gesture.rs): striking this key the given number of times
+// within the window on any input sends All Sound Off and All Notes Off on
+// every channel. None disables it. E.g. Some(PanicGesture { note: 108,
+// presses: 3, window: Duration::from_secs(1) }) for the top key of an 88-key
+// keyboard.
+const PANIC_GESTURE: Option<PanicGesture> = None;
+
+// MIDI channel (1-16) whose notes set the transpose of all other channels
+// relative to middle C (see transpose.rs), None disables transposing. Notes
+// on this channel are not forwarded.
+const TRANSPOSE_CONTROL_CHANNEL: Option<u8> = None;
+
+const _: () = assert!(matches!(TRANSPOSE_CONTROL_CHANNEL, None | Some(1..=16)));
+
+// Generative random CC source (see generative.rs): a random walk on one
+// controller mixed into the merged output, None disables it. E.g. filter
+// cutoff (CC 74) on channel 1 between 32 and 96, a step of up to 3 every
+// sixteenth note:
+// `Some(RandomCc { channel: 1, controller: 74, range: (32, 96), max_step: 3,
+// rate: Rate::Clocks(6) })`
+#[cfg(feature = "random-cc")]
+const RANDOM_CC: Option<RandomCc> = None;
+
+// MTC-to-clock conversion (see midi-core's timecode.rs): MIDI Clock, Start,
+// Stop and Continue derived from the MTC quarter frames on any input, for
+// clock-only gear following a DAW that only sends timecode. Set as (tempo in
+// BPM, timecode of the first beat), None disables it. The DAW's tempo map is
+// not known, the clock runs at this fixed tempo. E.g.
+// `Some((120, Timecode::new(1, 0, 0, 0)))` for a session starting at
+// 01:00:00:00.
+#[cfg(feature = "timecode")]
+const MTC_TO_CLOCK: Option<(u16, Timecode)> = None;
+
+// MTC generation (see midi-core's timecode.rs): quarter frames generated
+// while the MIDI Clock of any input runs, for lighting consoles and
+// recorders that chase timecode. Set as (frame rate, timecode at Start), None
+// disables it. Song Position Pointers locate it at the measured tempo. E.g.
+// `Some((FrameRate::Fps25, Timecode::new(1, 0, 0, 0)))`.
+#[cfg(feature = "timecode")]
+const CLOCK_TO_MTC: Option<(FrameRate, Timecode)> = None;
+
+#[cfg(feature = "timecode")]
+const _: () = {
+    if let Some((bpm, _)) = MTC_TO_CLOCK {
+        assert!(bpm > 0);
+    }
+};
+
+#[cfg(feature = "random-cc")]
+const _: () = {
+    if let Some(random_cc) = RANDOM_CC {
+        assert!(random_cc.channel >= 1 && random_cc.channel <= 16);
+        assert!(random_cc.controller < 128);
+        assert!(random_cc.range.0 <= random_cc.range.1 && random_cc.range.1 < 128);
+        assert!(!matches!(random_cc.rate, Rate::Millis(0) | Rate::Clocks(0)));
+    }
+};
+
+// Quantized transport relay (see quantize.rs): a Start or Continue arriving
+// while the clock runs is held until the next bar line, given here in clocks
+// (96 for 4/4, 72 for 3/4). None relays transport at once.
+const QUANTIZE_TRANSPORT: Option<u32> = None;
+
+const _: () = {
+    if let Some(clocks_per_bar) = QUANTIZE_TRANSPORT {
+        assert!(clocks_per_bar > 0);
+        // Realtime messages would bypass write_uart
+        assert!(
+            !cfg!(feature = "realtime-bypass"),
+            "QUANTIZE_TRANSPORT needs realtime messages to go through write_uart"
+        );
+    }
+};
+
+// Interval between periodic queue high-water mark reports (info level)
+const QUEUE_REPORT_INTERVAL_SECS: u64 = 60;
+
+// Interval of the telemetry SysEx frame on the output (see
+// sysex::telemetry), for hosts health-checking installed mergers. None
+// disables it. E.g. Some(Duration::from_secs(10)).
+const TELEMETRY_INTERVAL: Option<Duration> = None;
+
+// Interval between probes during a loopback latency test
+const LATENCY_PROBE_INTERVAL_MS: u64 = 100;
+
+// Stream every message over RTT from boot (see monitor.rs), otherwise the
+// monitor is switched on with the SysEx command `F0 7D 4D 4D <dev> 06 01 F7`
+const MONITOR_AT_BOOT: bool = false;
+
+// Monitor-only mode: the output stays silent and every input message is
+// printed decoded over RTT (see monitor.rs), to use the box purely as a
+// diagnostic probe in a rig. SysEx requests are still handled, their replies
+// are not sent.
+const MONITOR_ONLY: bool = false;
+
+// Identity Reply (`F0 7E <dev> 06 02 ...`, the answer to an Identity Request,
+// see sysex.rs) sent unsolicited at power-up, so a monitor on the output
+// shows which firmware version just booted.
+const ANNOUNCE_AT_BOOT: bool = false;
+
+// Reset sent on the output at power-up, before any merged traffic, so the
+// downstream module always starts in a known state. None sends nothing.
+const BOOT_RESET: Option<ModuleReset> = None;
+
+// Messages sent on the output at power-up, after BOOT_RESET, to initialize
+// the downstream rig: bank select and program changes, volumes, other
+// controllers. Raw bytes, e.g. &[0xB0, 0, 0, 0xC0, 5, 0xB0, 7, 100] selects
+// program 6 of bank 0 and sets the volume on channel 1. Empty sends nothing.
+const BOOT_SNAPSHOT: &[u8] = &[];
+
+const _: () = assert!(BOOT_SNAPSHOT.is_empty() || BOOT_SNAPSHOT[0] & 0x80 != 0);
+
+// Controllers whose latest value per channel is tracked on the output, for
+// SysEx `0C` to capture and `0D` to send again (see snapshot.rs). Volume,
+// pan, expression, reverb and chorus send by default.
+const SNAPSHOT_CONTROLLERS: &[u8] = &[7, 10, 11, 91, 93];
+
+const _: () = assert!(SNAPSHOT_CONTROLLERS.len() <= snapshot::MAX_CONTROLLERS);
+
+// Heartbeat LED on GPIO25: a short blink every second while all tasks are
+// healthy, a fast flicker while any of them is stalled
+const HEARTBEAT_ON_MS: u64 = 100;
+const HEARTBEAT_PERIOD_MS: u64 = 1000;
+const STALL_BLINK_MS: u64 = 50;
+
+// WS2812 activity meter on GPIO2 (see meter.rs): LED_METER_SEGMENT LEDs per
+// input, in input order along the strip, each input lit in its color; 0
+// disables the meter.
+#[cfg(feature = "led-meter")]
+const LED_METER_SEGMENT: usize = 0;
+#[cfg(feature = "led-meter")]
+const LED_METER_COLORS: [Rgb; UartChannel::COUNT] = [
+    Rgb::new(0, 255, 0),
+    Rgb::new(0, 0, 255),
+    Rgb::new(255, 160, 0),
+    Rgb::new(255, 0, 255),
+];
+
+// Hardware watchdog timeout and feed interval. A stalled executor (or a
+// panic, after the crash log is written) reboots the board within the timeout.
+const WATCHDOG_TIMEOUT_MS: u64 = 1000;
+const WATCHDOG_FEED_INTERVAL_MS: u64 = 250;
 
 // ============================================================================
 // CONTROL MESSAGES
@@ -29,16 +454,34 @@ mod midi_uart;
 /// be processed with stale running status after a parser error.
 ///
 /// Flow example (parser error on UART0):
-/// 1. read_uart0 detects error (UartError or MessageError)
-/// 2. read_uart0 resets its parser state
-/// 3. read_uart0 sends InvalidateRunningStatus(Zero) control message
+/// 1. The UART0 reader detects error (UartError or MessageError)
+/// 2. The reader resets the UART0 parser state
+/// 3. The reader sends InvalidateRunningStatus(Zero) control message
 /// 4. write_uart receives control message (ordered after any pending MIDI)
 /// 5. write_uart clears cached status for UART0
 /// 6. Next running status message from UART0 will be rejected (no cached status)
 /// 7. UART0 must send a full status byte to re-establish running status
-#[derive(Debug, Clone, Copy)]
+///
+/// SysEx requests addressed to the merger are also delivered as control
+/// messages, so replies are written by `write_uart` between regular messages.
+#[derive(Debug, Clone)]
 pub enum ControlMessage {
     InvalidateRunningStatus(UartChannel),
+    /// Cable pulled from an input (see `hotplug`)
+    InputLost(UartChannel),
+    /// Cascade tag on an input, with the device ID of the merger that sent
+    /// it and the number of messages it covers (see `cascade`)
+    CascadeTag(UartChannel, u8, u16),
+    SysExRequest(Request),
+    /// Emit a latency probe on the output (see `latency`)
+    LatencyProbe,
+    /// Emit a telemetry frame on the output (see `TELEMETRY_INTERVAL`)
+    Telemetry,
+    /// Selector button pressed (see `selector`)
+    SelectNextInput,
+    /// Message from an internal source (see `generative`, `timecode`)
+    #[cfg(any(feature = "timecode", feature = "random-cc"))]
+    Internal(MidiMessage),
 }
 
 /// Channel messages can be either MIDI data or control commands
@@ -51,44 +494,124 @@ pub enum ChannelMessage {
     Control(ControlMessage),
 }
 
+// ============================================================================
+// WRITE EXECUTOR
+// ============================================================================
+
+// Executor for the write path
+//
+// `write_uart` runs here, preempting the thread-mode executor that runs
+// everything else. Bulk work on the inputs or in future features (display,
+// filters) can then never delay bytes already due on the wire. Its software
+// interrupt sits below the hardware interrupts, so UART and timer handling
+// stays prompt.
+static EXECUTOR_HIGH: InterruptExecutor = InterruptExecutor::new();
+
+#[interrupt]
+unsafe fn SWI_IRQ_1() {
+    EXECUTOR_HIGH.on_interrupt()
+}
+
 // ============================================================================
 // STATIC BUFFERS
 // ============================================================================
 
-// Channel for passing MIDI messages from input tasks to output task
-static CHANNEL: Channel<ThreadModeRawMutex, ChannelMessage, 64> = Channel::new();
+// Channel for passing control messages from the other tasks to the output
+// task; the inputs each have their own queue (see queues.rs)
+//
+// The output task runs on the interrupt executor, so the channel is guarded
+// by a critical section rather than thread mode exclusivity
+pub static CHANNEL: Channel<CriticalSectionRawMutex, ChannelMessage, CHANNEL_DEPTH> =
+    Channel::new();
+
+// Merged output, set once in main()
+//
+// Writers hold the lock for a whole message, so messages never interleave on
+// the wire. `write_uart` is the only writer, unless the `realtime-bypass`
+// feature lets the read tasks write realtime bytes directly (see
+// `dispatch_message`).
+static OUTPUT: Mutex<CriticalSectionRawMutex, Option<BufferedUartTx<'static, UART0>>> =
+    Mutex::new(None);
 
 // BufferedUart requires static buffers for background interrupt-driven I/O.
 // These buffers allow the hardware to accumulate incoming bytes and queue
 // outgoing bytes without CPU intervention, reducing interrupt overhead.
 //
-// Memory usage: 256 bytes × 3 buffers = 768 bytes total (0.3% of 264KB RAM)
+// Memory usage: 256 bytes × 4 buffers = 1KB total (0.4% of 264KB RAM),
+// or 2.5KB with the `large-rx-buffers` feature, which gives bursts of SysEx
+// more room while the parser catches up. Sizes can be overridden at build
+// time (see config.rs).
 
 // UART0 RX buffer: Receives MIDI from input 1
-static mut UART0_RX_BUF: [u8; 256] = [0u8; 256];
+static UART0_RX_BUF: ConstStaticCell<[u8; UART_RX_BUF_LEN]> =
+    ConstStaticCell::new([0u8; UART_RX_BUF_LEN]);
 
 // UART0 TX buffer: Sends merged MIDI output
-static mut UART0_TX_BUF: [u8; 256] = [0u8; 256];
+static UART0_TX_BUF: ConstStaticCell<[u8; UART_TX_BUF_LEN]> =
+    ConstStaticCell::new([0u8; UART_TX_BUF_LEN]);
 
 // UART1 RX buffer: Receives MIDI from input 2
-static mut UART1_RX_BUF: [u8; 256] = [0u8; 256];
+static UART1_RX_BUF: ConstStaticCell<[u8; UART_RX_BUF_LEN]> =
+    ConstStaticCell::new([0u8; UART_RX_BUF_LEN]);
 
-#[derive(Debug, Default)]
-struct UartStatus {
-    uart0: Option<u8>,
-    uart1: Option<u8>,
-    last_tx_from: Option<UartChannel>,
-}
+// UART1 TX buffer: Second output in thru-box mode, where UART1 has no input
+static UART1_TX_BUF: ConstStaticCell<[u8; UART_TX_BUF_LEN]> =
+    ConstStaticCell::new([0u8; UART_TX_BUF_LEN]);
 
 // ============================================================================
 // WRITE TASK - Merges MIDI from both inputs to single output
 // ============================================================================
 
 #[embassy_executor::task]
-async fn write_uart(mut usart: BufferedUartTx<'static, UART0>) {
-    let mut uart_status = UartStatus::default();
+async fn write_uart(mut inputs: Inputs) {
+    let mut merger = Merger::<{ UartChannel::COUNT }>::default();
+    let mut pacer = Pacer::new(OUTPUT_MIN_GAP_US);
+    let mut pairs = PairHold::new(CC_PAIR_HOLD_US);
+    let mut selector = Selector::new(SELECTOR_MODE, SELECTOR_CC);
+    let mut quantizer = TransportQuantizer::new(QUANTIZE_TRANSPORT);
+    let mut transposer = Transposer::new(TRANSPOSE_CONTROL_CHANNEL);
+    let mut gestures = GestureDetector::new(PANIC_GESTURE);
+    let mut held = HeldNotes::new();
+    let mut cascade = Cascade::new();
+    // Last non-realtime message of each input, for RUNNING_STATUS_EXPIRY of
+    // the status cache
+    let mut last_status_activity = [Instant::from_ticks(0); UartChannel::COUNT];
+    let mut burst = heapless::Vec::<u8, OUTPUT_BURST_LEN>::new();
+    // Input whose SysEx is being streamed, until its F7 (SYSEX_FORWARDING)
+    let mut streaming: Option<UartChannel> = None;
     loop {
-        let channel_message = CHANNEL.receive().await;
+        if inputs.is_empty() {
+            // Nothing more to add, don't hold the burst while waiting
+            flush_burst(&mut burst, &mut merger).await;
+        }
+        liveness::idle(Task::Write);
+        let channel_message = match streaming {
+            Some(channel) => {
+                match with_timeout(SYSEX_STREAM_TIMEOUT, inputs.receive_from(channel)).await {
+                    Ok(message) => message,
+                    Err(_) => {
+                        liveness::busy(Task::Write);
+                        log::warn!("SysEx from {:?} timed out", channel);
+                        close_sysex(&mut burst, &mut merger).await;
+                        streaming = None;
+                        continue;
+                    }
+                }
+            }
+            None => pairs.next(&mut inputs).await,
+        };
+        liveness::busy(Task::Write);
+        stats::record_channel_level(inputs.len() + 1);
+        if let Some(channel) =
+            streaming.filter(|&channel| !continues_sysex(&channel_message, channel))
+        {
+            log::warn!("SysEx from {:?} cut short", channel);
+            close_sysex(&mut burst, &mut merger).await;
+            streaming = None;
+        }
+        if !matches!(channel_message, ChannelMessage::Midi(_)) {
+            flush_burst(&mut burst, &mut merger).await;
+        }
         match channel_message {
             ChannelMessage::Control(ControlMessage::InvalidateRunningStatus(channel)) => {
                 // Parser reset on error - invalidate cached running status
@@ -108,186 +631,1487 @@ async fn write_uart(mut usart: BufferedUartTx<'static, UART0>) {
                 //   2. UART0 has error, parser reset, InvalidateRunningStatus(Zero) sent
                 //   3. We clear uart0=None
                 //   4. UART1 running status uses correct UART1 status → CORRECT
-                match channel {
-                    UartChannel::Zero => uart_status.uart0 = None,
-                    UartChannel::One => uart_status.uart1 = None,
-                }
-                // If this was the last TX channel, clear that too
-                if uart_status.last_tx_from == Some(channel) {
-                    uart_status.last_tx_from = None;
-                }
-                defmt::debug!("Invalidated running status for {:?}", channel);
-            }
-            ChannelMessage::Midi(message) => {
-                match message.message {
-                    MidiMessage::Voice(data) => {
-                        match message.uart_channel {
-                            // Set the current status for the corresponding channel
-                            UartChannel::Zero => uart_status.uart0 = Some(data[0]),
-                            UartChannel::One => uart_status.uart1 = Some(data[0]),
-                        }
-                        if usart.write(&data).await.is_err() {
-                            defmt::error!("Failed to write Voice message");
-                            continue;
-                        }
+                merger.invalidate(channel.index());
+                log::debug!("Invalidated running status for {:?}", channel);
+            }
+            ChannelMessage::Control(ControlMessage::InputLost(channel)) => {
+                let input = channel.index();
+                merger.invalidate(input);
+                let mut released = 0;
+                while let Some(note_off) = held.release(input) {
+                    if write_injected(&note_off).await.is_err() {
+                        defmt::error!("Failed to write note off {=[u8]:x}", note_off);
+                    }
+                    released += 1;
+                }
+                if released > 0 {
+                    log::info!("Released {} notes of {:?}", released, channel);
+                    merger.interrupt();
+                }
+            }
+            ChannelMessage::Control(ControlMessage::CascadeTag(channel, device, count)) => {
+                cascade.tag(channel.index(), device, count);
+            }
+            ChannelMessage::Control(ControlMessage::SysExRequest(request)) => {
+                if request == Request::LatencyStop {
+                    // Inputs were muted during the test, so the statuses
+                    // cached before it may no longer match their streams
+                    merger.reset();
+                }
+                if answer_request(request).await {
+                    // SysEx cancels running status on the receiving end, the
+                    // next running status message must carry its status byte
+                    merger.interrupt();
+                }
+            }
+            ChannelMessage::Control(ControlMessage::Telemetry) => {
+                let uptime_secs = Instant::now().as_secs() as u32;
+                let frame = sysex::telemetry(&stats::snapshot(), uptime_secs);
+                if write_output(&frame).await.is_err() {
+                    defmt::error!("Failed to write telemetry");
+                }
+                merger.interrupt();
+            }
+            ChannelMessage::Control(ControlMessage::LatencyProbe) => {
+                let probe = sysex::latency_probe(latency::probe_timestamp());
+                if write_output(&probe).await.is_err() {
+                    defmt::error!("Failed to write latency probe");
+                }
+                merger.interrupt();
+            }
+            ChannelMessage::Control(ControlMessage::SelectNextInput) => {
+                let next = selector.next();
+                switch_input(&mut selector, &mut merger, next).await;
+            }
+            #[cfg(any(feature = "timecode", feature = "random-cc"))]
+            ChannelMessage::Control(ControlMessage::Internal(message)) => {
+                if write_injected(message.bytes()).await.is_err() {
+                    defmt::error!("Failed to write internal message{:?}", message);
+                }
+                if !matches!(message, MidiMessage::SystemRealtime(_)) {
+                    // Sent with its own status byte, which replaces the
+                    // receiver's running status
+                    merger.interrupt();
+                }
+            }
+            ChannelMessage::Midi(mut message) => {
+                let input = message.uart_channel.index();
+                let received = profiling::taken(message.uart_channel);
+                if let (Some(expiry), false) = (
+                    RUNNING_STATUS_EXPIRY,
+                    matches!(message.message, MidiMessage::SystemRealtime(_)),
+                ) {
+                    let now = Instant::now();
+                    if now - last_status_activity[input] > expiry && merger.status(input).is_some()
+                    {
+                        merger.invalidate(input);
+                        log::debug!(
+                            "Running status of {:?} expired after {} ms",
+                            message.uart_channel,
+                            (now - last_status_activity[input]).as_millis()
+                        );
+                    }
+                    last_status_activity[input] = now;
+                }
+                let origin = cascade.origin(input, &message.message);
+                if origin == Some(DEVICE_ID) {
+                    // Made up here, come back around a loop of mergers
+                    merger.skip(input, &message.message);
+                    continue;
+                }
+                if origin.is_none()
+                    && gestures.completes(
+                        message.uart_channel,
+                        &message.message,
+                        merger.status(input),
+                        Instant::now(),
+                    )
+                {
+                    log::warn!("Panic gesture on {:?}", message.uart_channel);
+                    flush_burst(&mut burst, &mut merger).await;
+                    if write_injected(&gesture::PANIC).await.is_err() {
+                        defmt::error!("Failed to write panic");
+                    }
+                    merger.interrupt();
+                }
+                let selected = match origin {
+                    None => selector.selected_by(&message.message, merger.status(input)),
+                    Some(_) => None,
+                };
+                if let Some(channel) = selected {
+                    // The selecting CC itself is not forwarded
+                    merger.skip(input, &message.message);
+                    flush_burst(&mut burst, &mut merger).await;
+                    switch_input(&mut selector, &mut merger, channel).await;
+                    continue;
+                }
+                if !selector.passes(message.uart_channel) {
+                    merger.skip(input, &message.message);
+                    continue;
+                }
+                if let MidiMessage::SysEx(data) | MidiMessage::SysExChunk(data) = &message.message {
+                    let chunk = matches!(message.message, MidiMessage::SysExChunk(_));
+                    if chunk && streaming.is_none() && data.first() != Some(&0xF0) {
+                        // The rest of a SysEx already closed on a timeout
+                        stats::record_output_drop();
+                        continue;
                     }
-                    MidiMessage::SystemCommon(data) | MidiMessage::SystemRealtime(data) => {
-                        // Nothing to do, immediately send
-                        if usart.write(&data).await.is_err() {
-                            defmt::error!("Failed to write System message");
+                    flush_burst(&mut burst, &mut merger).await;
+                    if write_output(data).await.is_err() {
+                        defmt::error!("Failed to write SysEx from {:?}", message.uart_channel);
+                    }
+                    // SysEx cancels running status on the receiving end
+                    merger.interrupt();
+                    profiling::written(message.uart_channel, received);
+                    streaming =
+                        (chunk && data.last() != Some(&0xF7)).then_some(message.uart_channel);
+                    continue;
+                }
+                if let MidiMessage::SystemRealtime(data) = &message.message {
+                    let clock_running = tempo::bpm_hundredths().is_some();
+                    match quantizer.relay(data[0], clock_running) {
+                        Relay::Pass => {}
+                        Relay::Hold => {
+                            log::info!("Holding{:?} until the next bar", message.message);
                             continue;
                         }
+                        Relay::ReleaseFirst(held) => {
+                            flush_burst(&mut burst, &mut merger).await;
+                            if write_output(&[held]).await.is_err() {
+                                defmt::error!("Failed to write held transport {=u8:x}", held);
+                            }
+                        }
                     }
-                    MidiMessage::RunningStatus(data) => {
-                        defmt::debug!("Running status: {:?}", data);
-
-                        // Determine if we need to prepend status byte
-                        let need_status = uart_status
-                            .last_tx_from
-                            .map(|prev| prev != message.uart_channel)
-                            .unwrap_or(true); // First message ever, need status
-
-                        if need_status {
-                            // Get the appropriate status byte for this channel
-                            let status_byte = match message.uart_channel {
-                                UartChannel::Zero => uart_status.uart0,
-                                UartChannel::One => uart_status.uart1,
-                            };
-
-                            match status_byte {
-                                Some(status) => {
-                                    defmt::debug!("Need to add previous status");
-                                    if usart.write(&[status]).await.is_err() {
-                                        defmt::error!("Failed to write status byte");
-                                        continue;
-                                    }
+                }
+                if pacer.should_thin(
+                    &message.message,
+                    message.uart_channel,
+                    merger.status(input),
+                    inputs.len(),
+                ) {
+                    merger.skip(input, &message.message);
+                    log::debug!(
+                        "Thinning{:?} from {:?}",
+                        message.message,
+                        message.uart_channel
+                    );
+                    stats::record_output_drop();
+                    continue;
+                }
+
+                if origin.is_none()
+                    && !note_in_range(message.uart_channel, &message.message, merger.status(input))
+                {
+                    merger.skip(input, &message.message);
+                    continue;
+                }
+
+                let status = merger.status(input);
+                if origin.is_none()
+                    && (transposer.takes(&mut message.message, status)
+                        || !transposer.transpose(&mut message.message, status))
+                {
+                    merger.skip(input, &message.message);
+                    continue;
+                }
+
+                if origin.is_some() {
+                    // Tagged again below, and a tag cancels running status
+                    merger.interrupt();
+                }
+                let Some(mut bytes) = merger.prepare(input, &message.message) else {
+                    // Running status without prior voice message - protocol violation
+                    defmt::error!(
+                        "Running status without previous voice message on {:?}",
+                        message.uart_channel
+                    );
+                    stats::record_output_drop();
+                    continue;
+                };
+
+                if let (None, MidiMessage::Voice(_) | MidiMessage::RunningStatus(_)) =
+                    (origin, &message.message)
+                {
+                    let status = merger.status(input);
+                    // Notes always have both data bytes, velocity last
+                    if let (Some(status), Some(velocity)) = (status, bytes.last_mut()) {
+                        match status & 0xF0 {
+                            0x80 => {
+                                if let Some(release) = NOTE_OFF_VELOCITY {
+                                    *velocity = release;
                                 }
-                                None => {
-                                    // Running status without prior voice message - protocol violation
-                                    defmt::error!(
-                                        "Running status without previous voice message on {:?}",
-                                        message.uart_channel
-                                    );
-                                    continue;
+                            }
+                            0x90 if *velocity > 0 => {
+                                *velocity = velocity::calibrate(message.uart_channel, *velocity);
+                                if let Some(dynamics) = VELOCITY_DYNAMICS[input] {
+                                    *velocity = dynamics.apply(*velocity);
                                 }
                             }
+                            _ => {}
                         }
+                    }
+                    if let (Some(status @ 0xB0..=0xBF), [.., controller, value]) =
+                        (status, &mut bytes[..])
+                    {
+                        *value = cc_range::apply(CC_RANGES[input], status, *controller, *value);
+                        snapshot::track(SNAPSHOT_CONTROLLERS, status, *controller, *value);
+                    }
+                    if let (Some(0xE0..=0xEF), Some(source)) = (status, PITCH_BEND_SOURCES[input]) {
+                        // The 14-bit value is in the last two bytes, LSB first
+                        let len = bytes.len();
+                        let value = (bytes[len - 1] as u16) << 7 | bytes[len - 2] as u16;
+                        let value = bend::rescale(value, source, PITCH_BEND_DESTINATION);
+                        bytes[len - 2] = (value & 0x7F) as u8;
+                        bytes[len - 1] = (value >> 7) as u8;
+                    }
+                }
 
-                        if usart.write(&data).await.is_err() {
-                            defmt::error!("Failed to write running status data");
-                            continue;
-                        }
+                if let Some(device) = origin {
+                    flush_burst(&mut burst, &mut merger).await;
+                    if write_output(&sysex::cascade_tag(device, 1)).await.is_err() {
+                        defmt::error!("Failed to write cascade tag");
+                    }
+                }
+
+                pacer.wait(&message.message).await;
+                if OUTPUT_MIN_GAP_US == 0 && burst.extend_from_slice(&bytes).is_ok() {
+                    // Goes out with the burst, flushed once the queue is empty
+                    monitor::record(Source::Output, &bytes);
+                    profiling::bursting(message.uart_channel, received);
+                } else {
+                    // The burst is full or bursts are off
+                    flush_burst(&mut burst, &mut merger).await;
+                    if let Err(written) = write_output(&bytes).await {
+                        defmt::error!(
+                            "Failed to write message from {:?}, {} of {} bytes sent",
+                            message.uart_channel,
+                            written,
+                            bytes.len()
+                        );
+                        // The receiver may hold a partial message or a running
+                        // status we no longer know, so the next message must
+                        // start with a status byte, which also discards the rest
+                        merger.interrupt();
+                        continue;
                     }
+                    profiling::written(message.uart_channel, received);
+                }
+                spi_bridge::forward(&message);
+                pairs.sent(&message.message, message.uart_channel, merger.status(input));
+                merger.sent(input, &message.message);
+                if let (true, Some(status), MidiMessage::Voice(_) | MidiMessage::RunningStatus(_)) =
+                    (HOTPLUG_DETECTION, merger.status(input), &message.message)
+                {
+                    held.track(input, status, &bytes);
+                }
+            }
+        }
+    }
+}
+
+/// Whether a message may go out while a SysEx of an input is streamed
+///
+/// Realtime messages and the next chunk of that SysEx may; anything else
+/// from the input means the SysEx stopped short.
+fn continues_sysex(message: &ChannelMessage, channel: UartChannel) -> bool {
+    match message {
+        ChannelMessage::Midi(midi) => match &midi.message {
+            MidiMessage::SystemRealtime(_) => true,
+            MidiMessage::SysExChunk(data) => {
+                midi.uart_channel == channel && data.first() != Some(&0xF0)
+            }
+            _ => false,
+        },
+        ChannelMessage::Control(_) => false,
+    }
+}
+
+/// End a streamed SysEx that stopped short with an F7
+///
+/// Receivers wait for the F7 and may take the next bytes as part of the
+/// SysEx until they see one.
+async fn close_sysex(
+    burst: &mut heapless::Vec<u8, OUTPUT_BURST_LEN>,
+    merger: &mut Merger<{ UartChannel::COUNT }>,
+) {
+    flush_burst(burst, merger).await;
+    if write_output(&[0xF7]).await.is_err() {
+        defmt::error!("Failed to close SysEx");
+    }
+    merger.interrupt();
+}
+
+/// Whether a message passes its input's `NOTE_RANGES` entry
+///
+/// The ranges are fixed, so the Note Off of a dropped Note On is dropped as
+/// well and no note is left hanging.
+///
+/// # Arguments
+/// * `cached_status` - The input's last status, resolves running status
+fn note_in_range(channel: UartChannel, message: &MidiMessage, cached_status: Option<u8>) -> bool {
+    let note = match message.event(cached_status) {
+        Some(
+            Event::NoteOff { note, .. }
+            | Event::NoteOn { note, .. }
+            | Event::PolyPressure { note, .. },
+        ) => note,
+        _ => return true,
+    };
+    let (lowest, highest) = NOTE_RANGES[channel.index()];
+    (lowest..=highest).contains(&note)
+}
+
+/// Route another input to the output in selector mode
+///
+/// Silences the notes of the previous input and resets the output's running
+/// status.
+async fn switch_input(
+    selector: &mut Selector,
+    merger: &mut Merger<{ UartChannel::COUNT }>,
+    channel: UartChannel,
+) {
+    if !selector.select(channel) {
+        return;
+    }
+    log::info!("Selected {:?}", channel);
+    if write_injected(&selector::ALL_NOTES_OFF).await.is_err() {
+        defmt::error!("Failed to write All Notes Off");
+    }
+    merger.interrupt();
+}
+
+/// Write a complete message to the output, retrying failed writes
+///
+/// Callers serialize the whole message, including any injected status byte,
+/// into `bytes` first, so a message is never split across separate writes
+/// that an error could fall between.
+///
+/// `write()` may take only part of the message when the TX ring buffer wraps
+/// or is nearly full, so the remainder is written until everything is sent.
+/// The bytes still reach the wire back to back, since the output lock is held
+/// for the whole message. The buffered UART does not report errors today; a
+/// failed write would be retried after a backoff that doubles from one byte
+/// time, up to `TX_RETRY_LIMIT` times. Every successful message is passed to
+/// the monitor. In monitor-only mode nothing is written.
+///
+/// # Returns
+/// * `Ok(())` - The whole message was sent
+/// * `Err(written)` - Gave up after `written` bytes
+async fn write_output(bytes: &[u8]) -> Result<(), usize> {
+    write_bytes(bytes).await?;
+    monitor::record(Source::Output, bytes);
+    pulse::toggle(pulse::Event::Output);
+    Ok(())
+}
+
+/// Write messages the merger made up itself, behind a cascade tag in
+/// cascade mode (see cascade.rs)
+///
+/// The tag cancels the receiver's running status, so the caller interrupts
+/// the merger as for any SysEx.
+async fn write_injected(bytes: &[u8]) -> Result<(), usize> {
+    let count = cascade::message_count(bytes);
+    if CASCADE && count > 0 {
+        write_output(&sysex::cascade_tag(DEVICE_ID, count)).await?;
+    }
+    write_output(bytes).await
+}
+
+/// Write a burst of messages collected by `write_uart` and empty it
+///
+/// The messages were passed to the monitor as they were collected. A failed
+/// write resets the output's running status, as for a single message.
+async fn flush_burst(
+    burst: &mut heapless::Vec<u8, OUTPUT_BURST_LEN>,
+    merger: &mut Merger<{ UartChannel::COUNT }>,
+) {
+    if burst.is_empty() {
+        return;
+    }
+    if let Err(written) = write_bytes(burst).await {
+        defmt::error!(
+            "Failed to write burst, {} of {} bytes sent",
+            written,
+            burst.len()
+        );
+        merger.interrupt();
+    }
+    pulse::toggle(pulse::Event::Output);
+    profiling::burst_written();
+    burst.clear();
+}
+
+/// Write bytes to the output, the part of `write_output` that takes the UART
+async fn write_bytes(bytes: &[u8]) -> Result<(), usize> {
+    if MONITOR_ONLY {
+        // The output is silenced, not failing
+        return Ok(());
+    }
+    let mut output = OUTPUT.lock().await;
+    let Some(usart) = output.as_mut() else {
+        // Only before main() hands over the UART
+        return Err(0);
+    };
+    let mut written = 0;
+    let mut retries = 0;
+    while written < bytes.len() {
+        match usart.write(&bytes[written..]).await {
+            Ok(len) => written += len,
+            Err(_) => {
+                stats::record_tx_error();
+                if retries == TX_RETRY_LIMIT {
+                    return Err(written);
                 }
-                uart_status.last_tx_from = Some(message.uart_channel)
+                Timer::after_micros(TX_RETRY_BACKOFF_US << retries).await;
+                retries += 1;
             }
         }
     }
+    Ok(())
+}
+
+/// Act on a SysEx request addressed to the merger
+///
+/// Returns `true` if a reply was written to the output.
+async fn answer_request(request: Request) -> bool {
+    match request {
+        Request::Status => {
+            let stats = stats::snapshot();
+            let uptime_secs = Instant::now().as_secs() as u32;
+            log::info!(
+                "Status: uptime {}s (reset: {:?}), {} queue drops, {} output drops, {} TX errors, {} module resets",
+                uptime_secs,
+                reset::reason(),
+                stats.queue_drops,
+                stats.output_drops,
+                stats.tx_errors,
+                stats.module_resets
+            );
+            log_queue_levels(&stats);
+            let bpm = tempo::bpm_hundredths();
+            match bpm {
+                Some(bpm) => log::info!("  Tempo {}.{=u32:02} BPM", bpm / 100, bpm % 100),
+                None => log::info!("  No clock"),
+            }
+            for channel in UartChannel::ALL {
+                let input = stats.inputs[channel.index()];
+                log::info!(
+                    "  {:?}: {} messages, {} transport errors, {} parse errors, {} resyncs",
+                    channel,
+                    input.messages,
+                    input.transport_errors,
+                    input.parse_errors,
+                    input.resyncs
+                );
+            }
+
+            let report =
+                sysex::status_report(&stats, uptime_secs, reset::reason(), bpm.unwrap_or(0));
+            if write_output(&report).await.is_err() {
+                defmt::error!("Failed to write status report");
+            }
+            true
+        }
+        Request::LatencyStart => {
+            log::info!("Latency test started, patch the output into an input");
+            latency::start();
+            false
+        }
+        Request::LatencyStop => {
+            latency::stop();
+            let results = latency::snapshot();
+            log::info!(
+                "Latency test: {} samples, min {}us, max {}us, mean {}us, jitter {}us",
+                results.samples,
+                results.min_us,
+                results.max_us,
+                results.mean_us,
+                results.jitter_us
+            );
+
+            let report = sysex::latency_report(&results);
+            if write_output(&report).await.is_err() {
+                defmt::error!("Failed to write latency report");
+            }
+            true
+        }
+        Request::LatencyProbe(_) => {
+            // Probes are consumed by the read tasks
+            false
+        }
+        Request::CalibrateVelocity => {
+            log::info!("Velocity calibration armed, play softly and hard");
+            velocity::start();
+            false
+        }
+        Request::ClearVelocityCalibration => {
+            log::info!("Velocity calibration cleared");
+            velocity::clear();
+            false
+        }
+        Request::CaptureSnapshot => {
+            snapshot::capture();
+            false
+        }
+        Request::RecallSnapshot => {
+            let recall = snapshot::recall(SNAPSHOT_CONTROLLERS);
+            log::info!("Recalling the snapshot, {} messages", recall.len() / 3);
+            if write_injected(&recall).await.is_err() {
+                defmt::error!("Failed to write snapshot");
+            }
+            true
+        }
+        Request::Profile => {
+            let profile = profiling::snapshot();
+            log_profile(&profile);
+            let report = sysex::profile_report(&profile);
+            if write_output(&report).await.is_err() {
+                defmt::error!("Failed to write profile");
+            }
+            true
+        }
+        Request::SetLogLevel(level) => {
+            log::set_level(level);
+            defmt::println!("Log level set to {:?}", level);
+            false
+        }
+        Request::SetMonitor(enabled) => {
+            monitor::set_enabled(enabled);
+            defmt::println!("Monitor {}", if enabled { "enabled" } else { "disabled" });
+            false
+        }
+        Request::CrashLog => {
+            let record = crash_log::load();
+            match &record {
+                Some(record) => log::info!(
+                    "Crash log: {:?} after {}s: {=str}",
+                    record.reason,
+                    record.uptime_secs,
+                    record.message.as_str()
+                ),
+                None => log::info!("Crash log empty"),
+            }
+
+            let report = sysex::crash_report(record.as_ref());
+            if write_output(&report).await.is_err() {
+                defmt::error!("Failed to write crash log");
+            }
+            true
+        }
+        Request::ClearCrashLog => {
+            crash_log::clear();
+            log::info!("Crash log cleared");
+            false
+        }
+        Request::Identity => {
+            log::info!("Identity request");
+            if write_output(&sysex::identity_reply()).await.is_err() {
+                defmt::error!("Failed to write identity reply");
+            }
+            true
+        }
+        Request::Trace => {
+            let trace = trace::dump();
+            log::info!(
+                "Trace: {} events{=str}",
+                trace.events.len(),
+                if trace.triggered {
+                    ", stopped by an error"
+                } else {
+                    ""
+                }
+            );
+
+            let report = sysex::trace_report(&trace, Instant::now().as_micros());
+            if write_output(&report).await.is_err() {
+                defmt::error!("Failed to write trace");
+            }
+            true
+        }
+    }
 }
 
 // ============================================================================
 // READ TASK - Receives MIDI from one input and sends to channel
 // ============================================================================
 
-async fn read_from_uart(usart: BufferedUartRx<'static, impl Instance>, uart_channel: UartChannel) {
+fn log_message_error(err: &MidiMessageError) {
+    match err {
+        MidiMessageError::DuplicateStatus => {
+            defmt::error!("Duplicate status byte");
+        }
+        MidiMessageError::UnexpectedDataByte => {
+            defmt::error!("Unexpected data byte");
+        }
+        MidiMessageError::UnknownStatus => {
+            defmt::error!("Unknown status byte");
+        }
+        MidiMessageError::InvalidStatusByte => {
+            defmt::error!("Invalid/undefined status byte");
+        }
+    }
+}
+
+/// Pass a received message on to the write task
+///
+/// Shared by all inputs: counts and logs the message, and turns SysEx
+/// requests addressed to the merger into control messages. Any other SysEx
+/// is dropped here, as the merged output does not carry SysEx.
+///
+/// While a latency test runs, the output is patched back into an input, so
+/// regular messages are dropped to keep them from circling through the
+/// merger forever. Only SysEx (probes and requests) gets through.
+///
+/// With the `realtime-bypass` feature, realtime messages skip the channel and
+/// are written to the output right here, so clock keeps sub-millisecond
+/// latency however deep the queue is. Bytes already in the TX buffer still go
+/// out first; a smaller `MIDI_UART_TX_BUF_LEN` bounds that delay.
+///
+/// # Arguments
+/// * `message` - Complete message from one of the inputs
+/// * `resyncs` - Current resync count of that input's parser
+async fn dispatch_message(message: UartMidiMessage, resyncs: u32, queue: &mut InputSender) {
+    let uart_channel = message.uart_channel;
+    stats::record_message(uart_channel, resyncs);
+    if HOTPLUG_DETECTION && hotplug::seen(uart_channel, Instant::now()) {
+        log::info!("{:?} reconnected", uart_channel);
+    }
+    monitor::record(Source::Input(uart_channel), message.message.bytes());
+    match uart_channel {
+        UartChannel::Zero => pulse::toggle(pulse::Event::Input0),
+        UartChannel::One => pulse::toggle(pulse::Event::Input1),
+        _ => {}
+    }
+    crash_log::record_message(&message);
+    trace::record_message(&message);
+    #[cfg(feature = "led-meter")]
+    if LED_METER_SEGMENT != 0 {
+        meter::record(uart_channel, &message.message);
+    }
+    if message.message.bytes() == [0xF8] {
+        #[cfg(feature = "random-cc")]
+        generative::clock_tick();
+        tempo::record_clock();
+    }
+    #[cfg(feature = "timecode")]
+    if let (Some(_), [0xF1, data]) = (MTC_TO_CLOCK, message.message.bytes()) {
+        if QUARTER_FRAMES.try_send((*data, Instant::now())).is_err() {
+            log::warn!("Quarter frame dropped, MTC clock busy");
+        }
+    }
+    #[cfg(feature = "timecode")]
+    if let (Some(_), [0xF2 | 0xF8 | 0xFA..=0xFC, ..]) = (CLOCK_TO_MTC, message.message.bytes()) {
+        if CLOCK_MESSAGES
+            .try_send((message.message.clone(), Instant::now()))
+            .is_err()
+        {
+            log::warn!("Clock message dropped, MTC generator busy");
+        }
+    }
+
+    match &message.message {
+        MidiMessage::SysEx(data) => {
+            match sysex::parse_request(data) {
+                Some(Request::LatencyProbe(sent_us)) => {
+                    latency::record(sent_us);
+                }
+                Some(request) => {
+                    log::info!("SysEx request {:?} on channel {:?}", request, uart_channel);
+                    queue
+                        .send(ChannelMessage::Control(ControlMessage::SysExRequest(
+                            request,
+                        )))
+                        .await;
+                }
+                None => match sysex::parse_cascade_tag(data).filter(|_| CASCADE) {
+                    Some((device, count)) => {
+                        queue
+                            .send(ChannelMessage::Control(ControlMessage::CascadeTag(
+                                uart_channel,
+                                device,
+                                count,
+                            )))
+                            .await;
+                    }
+                    None => {
+                        if let Some(reset) = sysex::parse_module_reset(data) {
+                            log::info!("{:?} on channel {:?}", reset, uart_channel);
+                            stats::record_module_reset();
+                        }
+                        if SYSEX_FORWARDING && !latency::is_running() {
+                            log::info!(
+                                "Forwarding SysEx of {} bytes on channel {:?}",
+                                data.len(),
+                                uart_channel
+                            );
+                            profiling::received(uart_channel);
+                            queue.send(ChannelMessage::Midi(message)).await;
+                        } else {
+                            log::debug!("Dropping SysEx on channel {:?}", uart_channel);
+                        }
+                    }
+                },
+            }
+            return;
+        }
+        _ if latency::is_running() => {
+            return;
+        }
+        // Not logged one by one, a dump would flood the log
+        MidiMessage::SysExChunk(_) => {}
+        MidiMessage::SystemRealtime(_) if cfg!(feature = "realtime-bypass") => {
+            // Straight to the output between two messages, instead of
+            // waiting behind everything queued on its input
+            if write_output(message.message.bytes()).await.is_err() {
+                defmt::error!("Failed to write realtime message from {:?}", uart_channel);
+                return;
+            }
+            spi_bridge::forward(&message);
+            return;
+        }
+        MidiMessage::SystemRealtime(_) => {}
+        _ => {
+            log::info!(
+                "Received message: {:?} on channel {:?}",
+                message.message,
+                uart_channel
+            );
+        }
+    }
+
+    profiling::received(uart_channel);
+    queue.send(ChannelMessage::Midi(message)).await;
+}
+
+/// Handle the outcome of one read on a UART input
+///
+/// Dispatches messages, and on errors logs them, resets the parser and
+/// invalidates the input's running status.
+async fn handle_uart_read(
+    midi_uart: &mut MidiUart<BufferedUartRx<'static, impl Instance>>,
+    result: Result<UartMidiMessage, UartMidiError<embassy_rp::uart::Error>>,
+    queue: &mut InputSender,
+) {
+    let uart_channel = midi_uart.uart_channel;
+    match result {
+        Ok(message) => {
+            dispatch_message(message, midi_uart.resync_count(), queue).await;
+        }
+        Err(error) => {
+            // Handle error
+            match error {
+                UartMidiError::UartError(uart_error) => {
+                    // UART hardware errors can leave the parser in an inconsistent state
+                    // (e.g., expecting data bytes that will never arrive due to lost bytes).
+                    // Reset the parser to ensure clean recovery.
+                    match uart_error {
+                        embassy_rp::uart::Error::Overrun => {
+                            defmt::error!("Uart Overrun error");
+                        }
+                        embassy_rp::uart::Error::Framing => {
+                            defmt::error!("Uart Framing error");
+                        }
+                        embassy_rp::uart::Error::Break => {
+                            defmt::error!("Uart Break error");
+                        }
+                        embassy_rp::uart::Error::Parity => {
+                            defmt::error!("Uart Parity error");
+                        }
+                        _ => {
+                            defmt::error!("Unknown Uart error");
+                        }
+                    }
+                    stats::record_transport_error(uart_channel);
+                    trace::record_error(uart_channel, ErrorKind::Transport);
+                    monitor::record_error(uart_channel, ErrorKind::Transport);
+                    // Reset parser after any UART error to prevent state corruption
+                    midi_uart.reset_parser();
+
+                    // Invalidate running status tracking for this channel
+                    // Send control message to write_uart task to clear cached status.
+                    // This ensures the output task won't inject stale status bytes
+                    // after the parser has been reset.
+                    queue
+                        .send(ChannelMessage::Control(
+                            ControlMessage::InvalidateRunningStatus(uart_channel),
+                        ))
+                        .await;
+
+                    if HOTPLUG_DETECTION
+                        && matches!(uart_error, embassy_rp::uart::Error::Break)
+                        && hotplug::unplug(uart_channel)
+                    {
+                        log::warn!("{:?} unplugged", uart_channel);
+                        queue
+                            .send(ChannelMessage::Control(ControlMessage::InputLost(
+                                uart_channel,
+                            )))
+                            .await;
+                    }
+                }
+                UartMidiError::MessageError(err) => {
+                    // MIDI protocol errors require parser reset and running status invalidation
+                    log_message_error(&err);
+                    stats::record_parse_error(uart_channel);
+                    trace::record_error(uart_channel, ErrorKind::Parse);
+                    monitor::record_error(uart_channel, ErrorKind::Parse);
+                    pulse::toggle(pulse::Event::ParseError);
+                    // Reset parser after any message error to prevent state corruption
+                    midi_uart.reset_parser();
+
+                    // Invalidate running status tracking for this channel
+                    // Send control message to write_uart task to clear cached status.
+                    // This ensures the output task won't inject stale status bytes
+                    // after the parser has been reset.
+                    queue
+                        .send(ChannelMessage::Control(
+                            ControlMessage::InvalidateRunningStatus(uart_channel),
+                        ))
+                        .await;
+                }
+            }
+        }
+    }
+
+    if let Some(escalation) = UART_REINIT {
+        if reinit::escalates(
+            escalation,
+            uart_channel,
+            midi_uart.resync_count(),
+            Instant::now(),
+        ) {
+            defmt::error!(
+                "{:?}: {} resyncs within {} ms, restarting the receiver",
+                uart_channel,
+                escalation.resyncs,
+                escalation.window.as_millis()
+            );
+            reinit::restart_receiver(uart_channel, &mut midi_uart.usart).await;
+            midi_uart.reset_parser();
+            queue
+                .send(ChannelMessage::Control(
+                    ControlMessage::InvalidateRunningStatus(uart_channel),
+                ))
+                .await;
+        }
+    }
+
+    // Bytes a timeout, a bad byte or a reset cut off, for chasing flaky cables
+    if let Some(discarded) = midi_uart.take_discarded() {
+        log::warn!("{:?}: dropped {}", uart_channel, discarded);
+    }
+}
+
+/// Read one UART input in its own task
+#[cfg(feature = "split-readers")]
+async fn read_from_uart(
+    usart: BufferedUartRx<'static, impl Instance>,
+    uart_channel: UartChannel,
+    mut queue: InputSender,
+) {
     let mut midi_uart = MidiUart::new(usart, uart_channel);
+    midi_uart.set_resync_policy(RESYNC_POLICY);
+    midi_uart.set_undefined_realtime(UNDEFINED_REALTIME);
+    midi_uart.set_normalize_note_off(NORMALIZE_NOTE_OFF);
+    midi_uart.set_expand_running_status(EXPAND_RUNNING_STATUS);
+    midi_uart.set_running_status_expiry(RUNNING_STATUS_EXPIRY);
+    midi_uart.set_sysex_streaming(SYSEX_FORWARDING);
+    midi_uart.set_byte_timeout(BYTE_TIMEOUTS[uart_channel.index()]);
+    let task = Task::Input(uart_channel);
     loop {
+        liveness::idle(task);
         let result = midi_uart.read().await;
+        liveness::busy(task);
+        handle_uart_read(&mut midi_uart, result, &mut queue).await;
+    }
+}
+
+#[cfg(feature = "split-readers")]
+#[embassy_executor::task]
+async fn read_uart0(usart: BufferedUartRx<'static, UART0>, queue: InputSender) {
+    read_from_uart(usart, UartChannel::Zero, queue).await
+}
+
+#[cfg(feature = "split-readers")]
+#[embassy_executor::task]
+async fn read_uart1(usart: BufferedUartRx<'static, UART1>, queue: InputSender) {
+    read_from_uart(usart, UartChannel::One, queue).await
+}
+
+/// Read both UART inputs from a single task
+///
+/// Saves the stack and task storage of a second reader. `select` polls input
+/// 1 first, so when both inputs have a complete message ready, input 1 goes
+/// first. Dropping the losing read is safe: `MidiUart::read()` only awaits
+/// `fill_buf()`, and bytes are consumed together with the parser update.
+#[cfg(not(feature = "split-readers"))]
+#[embassy_executor::task]
+async fn read_uarts(
+    usart0: BufferedUartRx<'static, UART0>,
+    usart1: BufferedUartRx<'static, UART1>,
+    mut queue0: InputSender,
+    mut queue1: InputSender,
+) {
+    let mut midi_uart0 = MidiUart::new(usart0, UartChannel::Zero);
+    let mut midi_uart1 = MidiUart::new(usart1, UartChannel::One);
+    midi_uart0.set_resync_policy(RESYNC_POLICY);
+    midi_uart0.set_undefined_realtime(UNDEFINED_REALTIME);
+    midi_uart0.set_normalize_note_off(NORMALIZE_NOTE_OFF);
+    midi_uart0.set_expand_running_status(EXPAND_RUNNING_STATUS);
+    midi_uart0.set_running_status_expiry(RUNNING_STATUS_EXPIRY);
+    midi_uart1.set_resync_policy(RESYNC_POLICY);
+    midi_uart1.set_undefined_realtime(UNDEFINED_REALTIME);
+    midi_uart1.set_normalize_note_off(NORMALIZE_NOTE_OFF);
+    midi_uart1.set_expand_running_status(EXPAND_RUNNING_STATUS);
+    midi_uart1.set_running_status_expiry(RUNNING_STATUS_EXPIRY);
+    midi_uart0.set_byte_timeout(BYTE_TIMEOUTS[UartChannel::Zero.index()]);
+    midi_uart1.set_byte_timeout(BYTE_TIMEOUTS[UartChannel::One.index()]);
+    midi_uart0.set_sysex_streaming(SYSEX_FORWARDING);
+    midi_uart1.set_sysex_streaming(SYSEX_FORWARDING);
+    let tasks = [
+        Task::Input(UartChannel::Zero),
+        Task::Input(UartChannel::One),
+    ];
+    loop {
+        tasks.into_iter().for_each(liveness::idle);
+        let result = select(midi_uart0.read(), midi_uart1.read()).await;
+        tasks.into_iter().for_each(liveness::busy);
         match result {
-            Ok(message) => {
-                match message.message {
-                    MidiMessage::SystemRealtime(_) => {}
-                    _ => {
-                        defmt::info!(
-                            "Received message: {:?} on channel {:?}",
-                            message.message,
-                            uart_channel
-                        );
-                    }
-                }
+            Either::First(result) => handle_uart_read(&mut midi_uart0, result, &mut queue0).await,
+            Either::Second(result) => handle_uart_read(&mut midi_uart1, result, &mut queue1).await,
+        }
+    }
+}
 
-                CHANNEL.send(ChannelMessage::Midi(message)).await;
+#[embassy_executor::task]
+async fn read_i2c(i2c: I2cSlave<'static, I2C0>, mut queue: InputSender) {
+    let mut midi_i2c = MidiI2c::new(i2c);
+    midi_i2c.set_resync_policy(RESYNC_POLICY);
+    midi_i2c.set_undefined_realtime(UNDEFINED_REALTIME);
+    midi_i2c.set_normalize_note_off(NORMALIZE_NOTE_OFF);
+    midi_i2c.set_expand_running_status(EXPAND_RUNNING_STATUS);
+    midi_i2c.set_running_status_expiry(RUNNING_STATUS_EXPIRY);
+    midi_i2c.set_byte_timeout(BYTE_TIMEOUTS[UartChannel::I2c.index()]);
+    let task = Task::Input(UartChannel::I2c);
+    loop {
+        liveness::idle(task);
+        let result = midi_i2c.read().await;
+        liveness::busy(task);
+        match result {
+            Ok(message) => {
+                dispatch_message(message, midi_i2c.resync_count(), &mut queue).await;
             }
             Err(error) => {
-                // Handle error
                 match error {
-                    UartMidiError::UartError(uart_error) => {
-                        // UART hardware errors can leave the parser in an inconsistent state
-                        // (e.g., expecting data bytes that will never arrive due to lost bytes).
-                        // Reset the parser to ensure clean recovery.
-                        match uart_error {
-                            embassy_rp::uart::Error::Overrun => {
-                                defmt::error!("Uart Overrun error");
-                            }
-                            embassy_rp::uart::Error::Framing => {
-                                defmt::error!("Uart Framing error");
-                            }
-                            embassy_rp::uart::Error::Break => {
-                                defmt::error!("Uart Break error");
+                    I2cMidiError::I2cError(i2c_error) => {
+                        stats::record_transport_error(UartChannel::I2c);
+                        trace::record_error(UartChannel::I2c, ErrorKind::Transport);
+                        monitor::record_error(UartChannel::I2c, ErrorKind::Transport);
+                        match i2c_error {
+                            embassy_rp::i2c_slave::Error::Abort(AbortReason::ArbitrationLoss) => {
+                                defmt::error!("I2C arbitration lost");
                             }
-                            embassy_rp::uart::Error::Parity => {
-                                defmt::error!("Uart Parity error");
+                            embassy_rp::i2c_slave::Error::Abort(_) => {
+                                defmt::error!("I2C transfer aborted");
                             }
                             _ => {
-                                defmt::error!("Unknown Uart error");
+                                defmt::error!("Unknown I2C error");
                             }
                         }
-                        // Reset parser after any UART error to prevent state corruption
-                        midi_uart.reset_parser();
-
-                        // Invalidate running status tracking for this channel
-                        // Send control message to write_uart task to clear cached status.
-                        // This ensures the output task won't inject stale status bytes
-                        // after the parser has been reset.
-                        CHANNEL
-                            .send(ChannelMessage::Control(
-                                ControlMessage::InvalidateRunningStatus(uart_channel),
-                            ))
-                            .await;
                     }
-                    UartMidiError::MessageError(err) => {
-                        // MIDI protocol errors require parser reset and running status invalidation
-                        match err {
-                            MidiMessageError::DuplicateStatus => {
-                                defmt::error!("Duplicate status byte");
-                            }
-                            MidiMessageError::UnexpectedDataByte => {
-                                defmt::error!("Unexpected data byte");
-                            }
-                            MidiMessageError::UnknownStatus => {
-                                defmt::error!("Unknown status byte");
-                            }
-                            MidiMessageError::InvalidStatusByte => {
-                                defmt::error!("Invalid/undefined status byte");
-                            }
-                        }
-                        // Reset parser after any message error to prevent state corruption
-                        midi_uart.reset_parser();
-
-                        // Invalidate running status tracking for this channel
-                        // Send control message to write_uart task to clear cached status.
-                        // This ensures the output task won't inject stale status bytes
-                        // after the parser has been reset.
-                        CHANNEL
-                            .send(ChannelMessage::Control(
-                                ControlMessage::InvalidateRunningStatus(uart_channel),
-                            ))
-                            .await;
+                    I2cMidiError::MessageError(err) => {
+                        log_message_error(&err);
+                        stats::record_parse_error(UartChannel::I2c);
+                        trace::record_error(UartChannel::I2c, ErrorKind::Parse);
+                        monitor::record_error(UartChannel::I2c, ErrorKind::Parse);
+                        pulse::toggle(pulse::Event::ParseError);
+                    }
+                }
+                // Same recovery as the UART inputs: clean parser state and
+                // no stale running status for this port
+                midi_i2c.reset_parser();
+                queue
+                    .send(ChannelMessage::Control(
+                        ControlMessage::InvalidateRunningStatus(UartChannel::I2c),
+                    ))
+                    .await;
+            }
+        }
+    }
+}
+
+// ============================================================================
+// SPI BRIDGE TASK - Exchanges messages with a co-processor
+// ============================================================================
+
+#[embassy_executor::task]
+async fn spi_bridge_task(mut bridge: SpiBridge<'static, SPI0>, mut queue: InputSender) {
+    let task = Task::Input(UartChannel::Spi);
+    loop {
+        liveness::idle(task);
+        // Send merged output as soon as it is queued, otherwise poll the
+        // co-processor with an idle frame so it gets a chance to talk
+        let outgoing = match select(
+            spi_bridge::next_outgoing(),
+            Timer::after_millis(SPI_BRIDGE_POLL_INTERVAL_MS),
+        )
+        .await
+        {
+            Either::First(message) => Some(message),
+            Either::Second(()) => None,
+        };
+        liveness::busy(task);
+
+        match bridge.exchange(outgoing.as_ref()).await {
+            Ok(Some(message)) => {
+                // The co-processor sends parsed messages, there is no parser
+                // on this input that could resync
+                dispatch_message(message, 0, &mut queue).await;
+            }
+            Ok(None) => {}
+            Err(error) => {
+                match error {
+                    SpiBridgeError::Transfer(_) => {
+                        defmt::error!("SPI bridge transfer failed");
                     }
+                    SpiBridgeError::Encode => {
+                        defmt::error!("SPI bridge failed to encode frame");
+                    }
+                    SpiBridgeError::Decode => {
+                        defmt::error!("SPI bridge received malformed frame");
+                    }
+                }
+                stats::record_transport_error(UartChannel::Spi);
+                trace::record_error(UartChannel::Spi, ErrorKind::Transport);
+                monitor::record_error(UartChannel::Spi, ErrorKind::Transport);
+                // A lost frame may have carried a status byte
+                queue
+                    .send(ChannelMessage::Control(
+                        ControlMessage::InvalidateRunningStatus(UartChannel::Spi),
+                    ))
+                    .await;
+            }
+        }
+    }
+}
+
+// ============================================================================
+// LATENCY TASK - Paces probes while a latency test runs
+// ============================================================================
+
+#[embassy_executor::task]
+async fn latency_probe_task() {
+    loop {
+        Timer::after_millis(LATENCY_PROBE_INTERVAL_MS).await;
+        if latency::is_running() {
+            CHANNEL
+                .send(ChannelMessage::Control(ControlMessage::LatencyProbe))
+                .await;
+        }
+    }
+}
+
+// ============================================================================
+// TELEMETRY TASK - Paces the periodic status frames
+// ============================================================================
+
+#[embassy_executor::task]
+async fn telemetry_task(interval: Duration) {
+    loop {
+        Timer::after(interval).await;
+        CHANNEL
+            .send(ChannelMessage::Control(ControlMessage::Telemetry))
+            .await;
+    }
+}
+
+// ============================================================================
+// MTC GENERATOR TASK - Timecode derived from MIDI Clock
+// ============================================================================
+
+/// Clock, transport and song position messages from the inputs with their
+/// arrival time
+#[cfg(feature = "timecode")]
+static CLOCK_MESSAGES: Channel<CriticalSectionRawMutex, (MidiMessage, Instant), 8> = Channel::new();
+
+#[cfg(feature = "timecode")]
+#[embassy_executor::task]
+async fn mtc_generator_task(rate: FrameRate, offset: Timecode) {
+    let mut generator = ClockToMtc::new(rate, offset);
+    loop {
+        let received = match generator.deadline_us() {
+            Some(deadline) => {
+                let deadline = Instant::from_micros(deadline);
+                match select(CLOCK_MESSAGES.receive(), Timer::at(deadline)).await {
+                    Either::First(message) => Some(message),
+                    Either::Second(()) => None,
+                }
+            }
+            None => Some(CLOCK_MESSAGES.receive().await),
+        };
+        match received {
+            Some((message, at)) => generator.message(message.bytes(), at.as_micros()),
+            None => {
+                if let Some(data) = generator.poll(Instant::now().as_micros()) {
+                    send_internal(MidiMessage::SystemCommon(
+                        midi_core::parser::ShortMessage::from_slice(&[0xF1, data]).unwrap(),
+                    ))
+                    .await;
                 }
             }
         }
     }
 }
 
+// ============================================================================
+// RANDOM CC TASK - Generative controller source
+// ============================================================================
+
+#[cfg(feature = "random-cc")]
 #[embassy_executor::task]
-async fn read_uart0(usart: BufferedUartRx<'static, UART0>) {
-    read_from_uart(usart, UartChannel::Zero).await
+async fn random_cc_task(config: RandomCc) {
+    let mut walk = RandomWalk::new(RoscRng.next_u32(), config.range, config.max_step);
+    let mut last = None;
+    let mut clocks = generative::clocks();
+    loop {
+        match config.rate {
+            Rate::Millis(ms) => Timer::after_millis(ms).await,
+            Rate::Clocks(count) => clocks = generative::wait_clocks(clocks, count).await,
+        }
+        if latency::is_running() {
+            // Muted like the inputs during a latency test
+            continue;
+        }
+        let value = if last.is_some() {
+            walk.step()
+        } else {
+            walk.value()
+        };
+        if last == Some(value) {
+            continue;
+        }
+        last = Some(value);
+        send_internal(config.message(value)).await;
+    }
+}
+
+/// Queue a message from an internal source for the output
+#[cfg(any(feature = "timecode", feature = "random-cc"))]
+async fn send_internal(message: MidiMessage) {
+    CHANNEL
+        .send(ChannelMessage::Control(ControlMessage::Internal(message)))
+        .await;
 }
 
+// ============================================================================
+// MTC CLOCK TASK - MIDI Clock derived from timecode
+// ============================================================================
+
+/// Quarter frames from the inputs with their arrival time
+#[cfg(feature = "timecode")]
+static QUARTER_FRAMES: Channel<CriticalSectionRawMutex, (u8, Instant), 8> = Channel::new();
+
+#[cfg(feature = "timecode")]
 #[embassy_executor::task]
-async fn read_uart1(usart: BufferedUartRx<'static, UART1>) {
-    read_from_uart(usart, UartChannel::One).await
+async fn mtc_clock_task(bpm: u16, offset: Timecode) {
+    let mut converter = MtcToClock::new(bpm, offset);
+    loop {
+        let received = match converter.deadline_us() {
+            Some(deadline) => {
+                let deadline = Instant::from_micros(deadline);
+                match select(QUARTER_FRAMES.receive(), Timer::at(deadline)).await {
+                    Either::First(frame) => Some(frame),
+                    Either::Second(()) => None,
+                }
+            }
+            None => Some(QUARTER_FRAMES.receive().await),
+        };
+        let output = match received {
+            Some((data, at)) => converter.quarter_frame(data, at.as_micros()),
+            None => converter.poll(Instant::now().as_micros()),
+        };
+        let realtime = |byte| {
+            MidiMessage::SystemRealtime(
+                midi_core::parser::ShortMessage::from_slice(&[byte]).unwrap(),
+            )
+        };
+        match output {
+            Some(ClockOutput::Clock) => send_internal(realtime(0xF8)).await,
+            Some(ClockOutput::Start) => {
+                log::info!("MTC clock: Start");
+                send_internal(realtime(0xFA)).await;
+            }
+            Some(ClockOutput::Continue(position)) => {
+                log::info!("MTC clock: Continue from sixteenth {}", position);
+                let pointer = timecode::song_position_pointer(position);
+                send_internal(MidiMessage::SystemCommon(
+                    midi_core::parser::ShortMessage::from_slice(&pointer).unwrap(),
+                ))
+                .await;
+                send_internal(realtime(0xFB)).await;
+            }
+            Some(ClockOutput::Stop) => {
+                log::info!("MTC clock: Stop");
+                send_internal(realtime(0xFC)).await;
+            }
+            None => {}
+        }
+    }
+}
+
+// ============================================================================
+// QUEUE REPORT TASK - Logs queue high-water marks
+// ============================================================================
+
+fn log_queue_levels(stats: &stats::Stats) {
+    log::info!(
+        "Queue high-water marks: merge queues {}/{}, SPI bridge {}/{}",
+        stats.channel_high_water,
+        queues::capacity(),
+        stats.bridge_high_water,
+        spi_bridge::QUEUE_LEN
+    );
+}
+
+fn log_profile(profile: &profiling::Profile) {
+    for channel in UartChannel::ALL {
+        let input = profile.inputs[channel.index()];
+        log::info!(
+            "Processing time {:?}: {} messages, min {}us, max {}us, mean {}us",
+            channel,
+            input.samples,
+            input.min_us,
+            input.max_us,
+            input.mean_us
+        );
+    }
+    log::info!("Processing time histogram: {}", profile.histogram);
+}
+
+/// Periodically log how full the queues have been, to validate their sizes
+/// against real-world traffic
+#[embassy_executor::task]
+async fn queue_report_task() {
+    loop {
+        Timer::after_secs(QUEUE_REPORT_INTERVAL_SECS).await;
+        log_queue_levels(&stats::snapshot());
+        if cfg!(feature = "profiling") {
+            log_profile(&profiling::snapshot());
+        }
+    }
+}
+
+// ============================================================================
+// SUPERVISOR TASK - Watches task liveness and drives the heartbeat LED
+// ============================================================================
+
+/// Check the pipeline tasks once per heartbeat and log when one stalls or
+/// recovers (see liveness.rs)
+#[embassy_executor::task]
+async fn supervisor_task(mut led: Output<'static>) {
+    let mut stalled = [false; Task::COUNT];
+    loop {
+        for (i, task) in Task::ALL.into_iter().enumerate() {
+            let now_stalled = liveness::is_stalled(task);
+            if now_stalled && !stalled[i] {
+                defmt::error!("Task {:?} stalled", task);
+            } else if !now_stalled && stalled[i] {
+                log::warn!("Task {:?} recovered", task);
+            }
+            stalled[i] = now_stalled;
+        }
+
+        if stalled.contains(&true) {
+            for _ in 0..HEARTBEAT_PERIOD_MS / (2 * STALL_BLINK_MS) {
+                led.toggle();
+                Timer::after_millis(STALL_BLINK_MS).await;
+                led.toggle();
+                Timer::after_millis(STALL_BLINK_MS).await;
+            }
+        } else {
+            led.set_high();
+            Timer::after_millis(HEARTBEAT_ON_MS).await;
+            led.set_low();
+            Timer::after_millis(HEARTBEAT_PERIOD_MS - HEARTBEAT_ON_MS).await;
+        }
+    }
+}
+
+// ============================================================================
+// METER TASK - Drives the LED strip activity meter
+// ============================================================================
+
+/// Show the input levels on the LED strip (see meter.rs)
+#[cfg(feature = "led-meter")]
+#[embassy_executor::task]
+async fn meter_task(mut strip: Ws2812<'static, PIO0, 0>) {
+    let mut leds = [Rgb::default(); LED_METER_SEGMENT * UartChannel::COUNT];
+    loop {
+        meter::render(&LED_METER_COLORS, &mut leds);
+        strip.write(&leds).await;
+        Timer::after_millis(meter::FRAME_MS).await;
+    }
+}
+
+// ============================================================================
+// HOTPLUG TASK - Notices inputs gone silent
+// ============================================================================
+
+/// Treat cable inputs silent for `idle` as unplugged (see hotplug.rs)
+#[embassy_executor::task]
+async fn hotplug_task(idle: Duration) {
+    loop {
+        Timer::after(idle / 4).await;
+        for channel in hotplug::unplug_silent(idle, Instant::now()) {
+            log::warn!(
+                "{:?} silent for {} ms, unplugged",
+                channel,
+                idle.as_millis()
+            );
+            CHANNEL
+                .send(ChannelMessage::Control(ControlMessage::InputLost(channel)))
+                .await;
+        }
+    }
+}
+
+// ============================================================================
+// WATCHDOG TASK - Keeps the hardware watchdog from rebooting the board
+// ============================================================================
+
+/// Feed the watchdog for as long as the executor keeps running tasks
+///
+/// The watchdog pauses while a debugger halts the core, so stepping through
+/// code on the bench does not reset the board.
+#[embassy_executor::task]
+async fn watchdog_task(mut watchdog: Watchdog) {
+    watchdog.pause_on_debug(true);
+    watchdog.start(Duration::from_millis(WATCHDOG_TIMEOUT_MS));
+    loop {
+        Timer::after_millis(WATCHDOG_FEED_INTERVAL_MS).await;
+        watchdog.feed();
+    }
+}
+
+/// Step the selector to the next input on each press of the selector button
+#[embassy_executor::task]
+async fn selector_button(mut button: Input<'static>) {
+    loop {
+        button.wait_for_falling_edge().await;
+        // Debounce, and ignore glitches shorter than that
+        Timer::after_millis(SELECTOR_DEBOUNCE_MS).await;
+        if button.is_low() {
+            CHANNEL
+                .send(ChannelMessage::Control(ControlMessage::SelectNextInput))
+                .await;
+            button.wait_for_high().await;
+            Timer::after_millis(SELECTOR_DEBOUNCE_MS).await;
+        }
+    }
+}
+
+// ============================================================================
+// BYPASS TASK - Forwards input 1 to the output byte-for-byte
+// ============================================================================
+
+/// Copy raw bytes from UART0 RX to UART0 TX without parsing or merging
+///
+/// This is the fail-safe path selected by the bypass switch. It owns both
+/// halves of UART0, so none of the parser, channel or merge logic runs and a
+/// misbehaving merge pipeline cannot affect the output. Whatever arrives on
+/// input 1 (including SysEx and malformed data) is forwarded as soon as the
+/// buffered RX hands it over.
+#[embassy_executor::task]
+async fn bypass_uart(
+    mut rx: BufferedUartRx<'static, UART0>,
+    mut tx: BufferedUartTx<'static, UART0>,
+) {
+    loop {
+        let buf = match rx.fill_buf().await {
+            Ok(buf) => buf,
+            Err(_) => {
+                // Nothing to recover in bypass mode, just keep forwarding
+                defmt::error!("Bypass read error");
+                continue;
+            }
+        };
+
+        let len = buf.len();
+        if tx.write_all(buf).await.is_err() {
+            defmt::error!("Bypass write error");
+        }
+        rx.consume(len);
+    }
+}
+
+// ============================================================================
+// THRU TASK - Mirrors input 1 to both outputs through their filters
+// ============================================================================
+
+/// Forward input 1 to UART0 TX and UART1 TX, each through its filter
+///
+/// Thru-box or splitter mode, selected by the thru jumper (see `thru`). Neither the merge
+/// channel nor `write_uart` run; the outputs are written here directly.
+#[embassy_executor::task]
+async fn thru_box(
+    rx: BufferedUartRx<'static, UART0>,
+    mut tx0: BufferedUartTx<'static, UART0>,
+    mut tx1: BufferedUartTx<'static, UART1>,
+) {
+    let mut midi_uart = MidiUart::new(rx, UartChannel::Zero);
+    midi_uart.set_resync_policy(RESYNC_POLICY);
+    midi_uart.set_undefined_realtime(UNDEFINED_REALTIME);
+    midi_uart.set_normalize_note_off(NORMALIZE_NOTE_OFF);
+    midi_uart.set_expand_running_status(EXPAND_RUNNING_STATUS);
+    midi_uart.set_running_status_expiry(RUNNING_STATUS_EXPIRY);
+    midi_uart.set_byte_timeout(BYTE_TIMEOUTS[UartChannel::Zero.index()]);
+    let mut mergers = [Merger::<1>::default(), Merger::<1>::default()];
+    loop {
+        let message = match midi_uart.read().await {
+            Ok(message) => message.message,
+            Err(error) => {
+                match error {
+                    UartMidiError::UartError(_) => {
+                        defmt::error!("Thru read error");
+                        stats::record_transport_error(UartChannel::Zero);
+                    }
+                    UartMidiError::MessageError(err) => {
+                        log_message_error(&err);
+                        stats::record_parse_error(UartChannel::Zero);
+                    }
+                }
+                midi_uart.reset_parser();
+                mergers.iter_mut().for_each(|merger| merger.invalidate(0));
+                continue;
+            }
+        };
+        stats::record_message(UartChannel::Zero, midi_uart.resync_count());
+
+        for (output, merger) in mergers.iter_mut().enumerate() {
+            let passes = message
+                .status_byte(merger.status(0))
+                .is_some_and(|status| OUTPUT_FILTERS[output].passes(status))
+                && (OUTPUT_CLOCK[output] || !thru::is_clock(&message));
+            if !passes {
+                merger.skip(0, &message);
+                continue;
+            }
+            let Some(bytes) = merger.prepare(0, &message) else {
+                continue;
+            };
+            let written = match output {
+                0 => tx0.write_all(&bytes).await,
+                _ => tx1.write_all(&bytes).await,
+            };
+            if written.is_ok() {
+                merger.sent(0, &message);
+            } else {
+                defmt::error!("Thru write error on output {}", output);
+                stats::record_tx_error();
+                merger.interrupt();
+            }
+        }
+    }
 }
 
 // ============================================================================
@@ -296,10 +2120,38 @@ async fn read_uart1(usart: BufferedUartRx<'static, UART1>) {
 
 #[embassy_executor::main]
 async fn main(spawner: Spawner) {
-    defmt::info!("Initializing...");
+    log::info!("Initializing...");
 
     let peripherals = embassy_rp::init(Default::default());
 
+    // Verbose logging strap on GPIO22 (active low, closes to ground)
+    //
+    // Raises the runtime log level to debug from power-up, e.g. for a release
+    // build on the bench. It can also be changed later over SysEx.
+    let verbose_strap = Input::new(peripherals.PIN_22, Pull::Up);
+    if verbose_strap.is_low() {
+        log::set_level(LogLevel::Debug);
+    }
+    defmt::println!("Log level {:?}", log::level());
+    monitor::set_enabled(MONITOR_AT_BOOT || MONITOR_ONLY);
+    monitor::set_decoded(MONITOR_ONLY);
+
+    #[cfg(feature = "debug-pulses")]
+    pulse::init([
+        Output::new(peripherals.PIN_6, Level::Low),
+        Output::new(peripherals.PIN_7, Level::Low),
+        Output::new(peripherals.PIN_8, Level::Low),
+        Output::new(peripherals.PIN_9, Level::Low),
+    ]);
+
+    // Report how and why the previous run ended, then arm the watchdog. It
+    // also guards bypass mode and the self-test, so it starts before either.
+    defmt::println!("Reset reason: {:?}", reset::init());
+    crash_log::check_boot();
+    spawner
+        .spawn(watchdog_task(Watchdog::new(peripherals.WATCHDOG)))
+        .expect("Failed to spawn watchdog_task task");
+
     // Bind UART interrupts to handlers
     // BufferedUart uses interrupts (not DMA) to transfer data between hardware
     // and software buffers, which is more efficient for byte-by-byte protocols
@@ -307,6 +2159,7 @@ async fn main(spawner: Spawner) {
     bind_interrupts!(struct Irqs {
         UART0_IRQ => BufferedInterruptHandler<UART0>;
         UART1_IRQ => BufferedInterruptHandler<UART1>;
+        I2C0_IRQ => embassy_rp::i2c::InterruptHandler<I2C0>;
     });
 
     // MIDI standard baud rate: 31,250 bits/sec
@@ -314,6 +2167,12 @@ async fn main(spawner: Spawner) {
     let mut uart_config = Config::default();
     uart_config.baudrate = 31250;
 
+    // Each input gets its own copy so RX inversion can be set per port
+    let mut uart0_config = uart_config;
+    uart0_config.invert_rx = UART0_INVERT_RX;
+    let mut uart1_config = uart_config;
+    uart1_config.invert_rx = UART1_INVERT_RX;
+
     // UART0: Bidirectional (receives input 1, transmits merged output)
     // Uses BufferedUart for efficient interrupt-driven I/O with background buffering
     //
@@ -323,49 +2182,237 @@ async fn main(spawner: Spawner) {
     // 3. Application reads from software buffer asynchronously (no waiting for hardware)
     // 4. This decouples hardware timing from application logic
     //
-    // Safety: We use unsafe to pass static mut buffers. This is safe because:
-    // - Each buffer is used by only one UART instance
-    // - BufferedUart takes ownership and manages exclusive access
+    // Each buffer can be taken only once, which hands BufferedUart exclusive
+    // access for the rest of the program
     let usart0 = BufferedUart::new(
-        peripherals.UART0,  // Hardware peripheral
-        Irqs,               // Interrupt bindings
-        peripherals.PIN_12, // TX pin (output to MIDI OUT)
-        peripherals.PIN_13, // RX pin (input from MIDI IN 1)
-        // Safe: Each static buffer is used by only one UART instance
-        // Using addr_of_mut!() to avoid direct mutable static reference
-        unsafe { &mut *core::ptr::addr_of_mut!(UART0_TX_BUF) }, // TX buffer for outgoing data
-        unsafe { &mut *core::ptr::addr_of_mut!(UART0_RX_BUF) }, // RX buffer for incoming data
-        uart_config,
+        peripherals.UART0,   // Hardware peripheral
+        Irqs,                // Interrupt bindings
+        peripherals.PIN_12,  // TX pin (output to MIDI OUT)
+        peripherals.PIN_13,  // RX pin (input from MIDI IN 1)
+        UART0_TX_BUF.take(), // TX buffer for outgoing data
+        UART0_RX_BUF.take(), // RX buffer for incoming data
+        uart0_config,
     );
 
     // Split UART0 into separate TX and RX handles
     // This allows independent operation: one task writes, another reads
-    let (usart0_tx, usart0_rx) = usart0.split();
+    let (mut usart0_tx, mut usart0_rx) = usart0.split();
+
+    // Bypass switch on GPIO15, see below; it wins over the thru jumper
+    let bypass_switch = Input::new(peripherals.PIN_15, Pull::Up);
+
+    // Thru jumper on GPIO11 (active low, closes to ground)
+    //
+    // Sampled once at power-up. When it is closed, the merger becomes a thru
+    // box: input 1 is mirrored to UART0 TX and, through UART1 TX on GPIO4, to
+    // a second output, each behind its filter in `THRU_FILTERS`, or split by
+    // channel with `SPLITTER_MODE`. Input 2 is not used.
+    let thru_jumper = Input::new(peripherals.PIN_11, Pull::Up);
+    if thru_jumper.is_low() && bypass_switch.is_high() {
+        let mode = if SPLITTER_MODE {
+            "splitting input 1 by channel"
+        } else {
+            "mirroring input 1 to both outputs"
+        };
+        log::warn!("Thru jumper closed, {}", mode);
+        let usart1_tx = BufferedUartTx::new(
+            peripherals.UART1,
+            Irqs,
+            peripherals.PIN_4,
+            UART1_TX_BUF.take(),
+            uart_config,
+        );
+        spawner
+            .spawn(thru_box(usart0_rx, usart0_tx, usart1_tx))
+            .expect("Failed to spawn thru_box task");
+        return;
+    }
 
     // UART1: Receive-only (input 2)
     // We only need RX for this input, so we create a BufferedUartRx directly
     // instead of creating a full BufferedUart and splitting it
-    let usart1_rx = BufferedUartRx::new(
-        peripherals.UART1, // Hardware peripheral
-        Irqs,              // Interrupt bindings
-        peripherals.PIN_5, // RX pin (input from MIDI IN 2)
-        // Safe: Each static buffer is used by only one UART instance
-        // Using addr_of_mut!() to avoid direct mutable static reference
-        unsafe { &mut *core::ptr::addr_of_mut!(UART1_RX_BUF) }, // RX buffer for incoming data
-        uart_config,
+    let mut usart1_rx = BufferedUartRx::new(
+        peripherals.UART1,   // Hardware peripheral
+        Irqs,                // Interrupt bindings
+        peripherals.PIN_5,   // RX pin (input from MIDI IN 2)
+        UART1_RX_BUF.take(), // RX buffer for incoming data
+        uart1_config,
+    );
+
+    // I2C0: MIDI input as an I2C target, for I2C MIDI controllers sharing
+    // the bus (SDA on GPIO16, SCL on GPIO17)
+    let mut i2c_config = embassy_rp::i2c_slave::Config::default();
+    i2c_config.addr = I2C_MIDI_ADDRESS;
+    let i2c0 = I2cSlave::new(
+        peripherals.I2C0,
+        peripherals.PIN_17, // SCL pin
+        peripherals.PIN_16, // SDA pin
+        Irqs,
+        i2c_config,
     );
 
-    defmt::info!("Initialized.");
+    // SPI0: Bridge to a co-processor MCU
+    // (SCK on GPIO18, MOSI on GPIO19, MISO on GPIO20, CS on GPIO21)
+    let mut spi_config = embassy_rp::spi::Config::default();
+    spi_config.frequency = SPI_BRIDGE_FREQUENCY;
+    let spi0 = Spi::new(
+        peripherals.SPI0,
+        peripherals.PIN_18, // SCK pin
+        peripherals.PIN_19, // MOSI pin
+        peripherals.PIN_20, // MISO pin
+        peripherals.DMA_CH0,
+        peripherals.DMA_CH1,
+        spi_config,
+    );
+    let spi_bridge = SpiBridge::new(spi0, Output::new(peripherals.PIN_21, Level::High));
+
+    // Bypass switch on GPIO15 (active low, closes to ground)
+    //
+    // The switch is sampled once at power-up. When it is closed, input 1 is
+    // wired straight to the output and the merge tasks are never spawned, so
+    // flipping it and power-cycling is the emergency fallback during a show.
+    if bypass_switch.is_low() {
+        log::warn!("Bypass switch engaged, forwarding input 1 directly to output");
+        spawner
+            .spawn(bypass_uart(usart0_rx, usart0_tx))
+            .expect("Failed to spawn bypass_uart task");
+        return;
+    }
+
+    // Self-test jumper on GPIO14 (active low, closes to ground)
+    //
+    // With the jumper closed and one output patched into each input, the
+    // power-on self-test runs and reports on the on-board LED (GPIO25)
+    // before normal operation starts.
+    let mut led = Output::new(peripherals.PIN_25, Level::Low);
+    let selftest_jumper = Input::new(peripherals.PIN_14, Pull::Up);
+    if selftest_jumper.is_low() {
+        log::info!("Running self-test...");
+        let result = selftest::run(&mut usart0_tx, &mut usart0_rx, &mut usart1_rx).await;
+        selftest::show_result(&mut led, result).await;
+    }
+
+    log::info!("Initialized.");
 
     // Spawn async tasks
     // Each task runs concurrently, scheduled by the Embassy executor
+    let (inputs, [queue0, queue1, queue_i2c, queue_spi]) = queues::split();
+    #[cfg(feature = "split-readers")]
+    {
+        spawner
+            .spawn(read_uart0(usart0_rx, queue0))
+            .expect("Failed to spawn read_uart0 task");
+        spawner
+            .spawn(read_uart1(usart1_rx, queue1))
+            .expect("Failed to spawn read_uart1 task");
+    }
+    #[cfg(not(feature = "split-readers"))]
+    spawner
+        .spawn(read_uarts(usart0_rx, usart1_rx, queue0, queue1))
+        .expect("Failed to spawn read_uarts task");
+    spawner
+        .spawn(read_i2c(i2c0, queue_i2c))
+        .expect("Failed to spawn read_i2c task");
+    spawner
+        .spawn(spi_bridge_task(spi_bridge, queue_spi))
+        .expect("Failed to spawn spi_bridge_task task");
     spawner
-        .spawn(read_uart0(usart0_rx))
-        .expect("Failed to spawn read_uart0 task");
+        .spawn(latency_probe_task())
+        .expect("Failed to spawn latency_probe_task task");
     spawner
-        .spawn(read_uart1(usart1_rx))
-        .expect("Failed to spawn read_uart1 task");
+        .spawn(queue_report_task())
+        .expect("Failed to spawn queue_report_task task");
     spawner
-        .spawn(write_uart(usart0_tx))
+        .spawn(supervisor_task(led))
+        .expect("Failed to spawn supervisor_task task");
+    if let Some(interval) = TELEMETRY_INTERVAL {
+        spawner
+            .spawn(telemetry_task(interval))
+            .expect("Failed to spawn telemetry_task task");
+    }
+    #[cfg(feature = "led-meter")]
+    if LED_METER_SEGMENT != 0 {
+        bind_interrupts!(struct PioIrqs {
+            PIO0_IRQ_0 => embassy_rp::pio::InterruptHandler<PIO0>;
+        });
+        let Pio {
+            mut common, sm0, ..
+        } = Pio::new(peripherals.PIO0, PioIrqs);
+        let strip = Ws2812::new(&mut common, sm0, peripherals.PIN_2);
+        spawner
+            .spawn(meter_task(strip))
+            .expect("Failed to spawn meter_task task");
+    }
+    #[cfg(feature = "timecode")]
+    if let Some((bpm, offset)) = MTC_TO_CLOCK {
+        log::info!("MTC clock at {} BPM from {:?}", bpm, offset);
+        spawner
+            .spawn(mtc_clock_task(bpm, offset))
+            .expect("Failed to spawn mtc_clock_task task");
+    }
+    #[cfg(feature = "timecode")]
+    if let Some((rate, offset)) = CLOCK_TO_MTC {
+        log::info!("MTC generation at {:?} from {:?}", rate, offset);
+        spawner
+            .spawn(mtc_generator_task(rate, offset))
+            .expect("Failed to spawn mtc_generator_task task");
+    }
+    #[cfg(feature = "random-cc")]
+    if let Some(random_cc) = RANDOM_CC {
+        log::info!(
+            "Random CC {} on channel {}",
+            random_cc.controller,
+            random_cc.channel
+        );
+        spawner
+            .spawn(random_cc_task(random_cc))
+            .expect("Failed to spawn random_cc_task task");
+    }
+    if let (true, Some(idle)) = (HOTPLUG_DETECTION, HOTPLUG_IDLE) {
+        spawner
+            .spawn(hotplug_task(idle))
+            .expect("Failed to spawn hotplug_task task");
+    }
+    if SELECTOR_MODE {
+        spawner
+            .spawn(selector_button(Input::new(peripherals.PIN_10, Pull::Up)))
+            .expect("Failed to spawn selector_button task");
+    }
+    interrupt::SWI_IRQ_1.set_priority(Priority::P2);
+    let high_spawner = EXECUTOR_HIGH.start(interrupt::SWI_IRQ_1);
+    *OUTPUT.lock().await = Some(usart0_tx);
+    if ANNOUNCE_AT_BOOT {
+        log::info!("Announcing firmware {}", env!("CARGO_PKG_VERSION"));
+        if write_output(&sysex::identity_reply()).await.is_err() {
+            defmt::error!("Failed to write identity announcement");
+        }
+    }
+    if let Some(reset) = BOOT_RESET {
+        // Written before the write task starts, so it goes out first
+        log::info!("Sending {:?}", reset);
+        if write_output(reset.message()).await.is_err() {
+            defmt::error!("Failed to send {:?}", reset);
+        }
+    }
+    if !BOOT_SNAPSHOT.is_empty() {
+        log::info!("Sending the boot snapshot, {} bytes", BOOT_SNAPSHOT.len());
+        if write_injected(BOOT_SNAPSHOT).await.is_err() {
+            defmt::error!("Failed to send the boot snapshot");
+        }
+    }
+    if PITCH_BEND_SETUP {
+        log::info!(
+            "Setting pitch bend range to {} semitones",
+            PITCH_BEND_DESTINATION
+        );
+        if write_injected(&bend::range_setup(PITCH_BEND_DESTINATION))
+            .await
+            .is_err()
+        {
+            defmt::error!("Failed to send pitch bend range");
+        }
+    }
+    high_spawner
+        .spawn(write_uart(inputs))
         .expect("Failed to spawn write_uart task");
 }
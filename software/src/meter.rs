@@ -0,0 +1,86 @@
+//! LED strip activity meter
+//!
+//! With `LED_METER` set, a WS2812 strip on GPIO2 shows the activity of each
+//! input on its own segment of `LED_METER_SEGMENT` LEDs, lit from the bottom
+//! like a VU meter. A Note On lifts its input's level to the velocity, any
+//! other message nudges it up, so a busy input glows even without notes.
+//! Levels fall back to dark over about half a second.
+//!
+//! The read tasks only update a level under a short critical section; the
+//! strip is written by `meter_task` at its own pace, so a slow strip never
+//! holds up the MIDI path.
+
+use crate::midi_uart::UartChannel;
+use crate::ws2812::Rgb;
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use midi_core::event::Kind;
+use midi_core::parser::MidiMessage;
+
+/// Milliseconds between strip updates
+pub const FRAME_MS: u64 = 20;
+
+/// Level added by a message that isn't a Note On
+const MESSAGE_STEP: u8 = 12;
+
+/// Level lost per frame, as a right shift of the level
+const DECAY_SHIFT: u32 = 3;
+
+/// Highest level, a Note On at full velocity
+const FULL: u8 = 127;
+
+static LEVELS: Mutex<CriticalSectionRawMutex, RefCell<[u8; UartChannel::COUNT]>> =
+    Mutex::new(RefCell::new([0; UartChannel::COUNT]));
+
+/// Count a message from an input
+pub fn record(channel: UartChannel, message: &MidiMessage) {
+    let velocity = match (message.kind(None), message.data()) {
+        (Some(Kind::NoteOn), [_, velocity]) if *velocity > 0 => Some(*velocity),
+        _ => None,
+    };
+    LEVELS.lock(|levels| {
+        let level = &mut levels.borrow_mut()[channel.index()];
+        *level = match velocity {
+            Some(velocity) => (*level).max(velocity),
+            None => level.saturating_add(MESSAGE_STEP).min(FULL),
+        };
+    });
+}
+
+/// Take the levels for a frame and let them decay
+fn frame() -> [u8; UartChannel::COUNT] {
+    LEVELS.lock(|levels| {
+        let mut levels = levels.borrow_mut();
+        let current = *levels;
+        for level in levels.iter_mut() {
+            *level = level.saturating_sub((*level >> DECAY_SHIFT).max(1));
+        }
+        current
+    })
+}
+
+/// Colors of the whole strip for the current levels
+///
+/// Each input's segment is lit from its first LED up; the LED at the top of
+/// the bar is dimmed by the remainder, so the bar moves smoothly.
+///
+/// # Arguments
+/// * `colors` - Color of each input's segment
+/// * `strip` - The strip, `LED_METER_SEGMENT` LEDs per input
+pub fn render(colors: &[Rgb; UartChannel::COUNT], strip: &mut [Rgb]) {
+    let levels = frame();
+    let segment = strip.len() / UartChannel::COUNT;
+    for (i, leds) in strip.chunks_mut(segment.max(1)).enumerate() {
+        let Some(&level) = levels.get(i) else {
+            break;
+        };
+        // Level in 1/255ths of one LED
+        let mut lit = level as u32 * segment as u32 * 255 / FULL as u32;
+        for led in leds {
+            let brightness = lit.min(255) as u8;
+            *led = colors[i].dimmed(brightness);
+            lit -= brightness as u32;
+        }
+    }
+}
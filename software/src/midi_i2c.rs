@@ -0,0 +1,143 @@
+use crate::midi_uart::{UartChannel, UartMidiMessage};
+use embassy_rp::i2c::Instance;
+use embassy_rp::i2c_slave::{Command, Error, I2cSlave};
+use embassy_time::Duration;
+use midi_core::parser::{MidiMessageError, MidiParser, ResyncPolicy, UndefinedRealtime};
+
+/// Size of the receive buffer for a single I2C write transaction
+///
+/// Controllers usually write one MIDI message per transaction, but some batch
+/// several. Longer writes are still accepted: the driver reports them as
+/// `PartialWrite` and we parse whatever fitted in the buffer.
+const I2C_RX_BUF_LEN: usize = 32;
+
+pub enum I2cMidiError {
+    I2cError(Error),
+    MessageError(MidiMessageError),
+}
+
+/// MIDI input over I2C, with the merger acting as an I2C target (slave)
+///
+/// Eurorack and Teensy style "I2C MIDI" controllers write raw MIDI bytes to
+/// the target address, so each write transaction is simply a chunk of the
+/// MIDI byte stream. The bytes are fed through the same `MidiParser` as the
+/// UART inputs and the messages are tagged with `UartChannel::I2c`, which lets
+/// the merge task treat this port like any other input.
+pub struct MidiI2c<'a, T: Instance> {
+    i2c: I2cSlave<'a, T>,
+    parser: MidiParser,
+    buf: [u8; I2C_RX_BUF_LEN],
+    len: usize,
+    pos: usize,
+}
+
+impl<'a, T: Instance> MidiI2c<'a, T> {
+    /// Create a new MIDI I2C reader
+    ///
+    /// # Arguments
+    /// * `i2c` - I2cSlave instance configured with the merger's target address
+    pub fn new(i2c: I2cSlave<'a, T>) -> Self {
+        let mut parser = MidiParser::default();
+        parser.set_log_filter(crate::log::enabled);
+        Self {
+            i2c,
+            parser,
+            buf: [0u8; I2C_RX_BUF_LEN],
+            len: 0,
+            pos: 0,
+        }
+    }
+
+    /// Reset the MIDI parser to clean state
+    ///
+    /// Also drops any bytes left over from the current write transaction, as
+    /// they belong to the message stream that caused the error.
+    pub fn reset_parser(&mut self) {
+        self.parser.reset();
+        self.pos = self.len;
+    }
+
+    /// Number of times this input's parser entered resync mode
+    pub fn resync_count(&self) -> u32 {
+        self.parser.resync_count()
+    }
+
+    /// Change how this input's parser recovers from bad bytes
+    pub fn set_resync_policy(&mut self, policy: ResyncPolicy) {
+        self.parser.set_policy(policy);
+    }
+
+    /// Change what this input's parser does with 0xF9 and 0xFD
+    pub fn set_undefined_realtime(&mut self, handling: UndefinedRealtime) {
+        self.parser.set_undefined_realtime(handling);
+    }
+
+    /// Change the longest gap between the bytes of a message on this input
+    pub fn set_byte_timeout(&mut self, timeout: Duration) {
+        self.parser.set_byte_timeout(timeout);
+    }
+
+    /// Turn this input's velocity 0 Note Ons into Note Offs
+    pub fn set_normalize_note_off(&mut self, normalize: bool) {
+        self.parser.set_normalize_note_off(normalize);
+    }
+
+    /// Give this input's running status messages their status byte
+    pub fn set_expand_running_status(&mut self, expand: bool) {
+        self.parser.set_expand_running_status(expand);
+    }
+
+    /// Forget this input's running status after a silence, `None` keeps it
+    pub fn set_running_status_expiry(&mut self, expiry: Option<Duration>) {
+        self.parser.set_running_status_expiry(expiry);
+    }
+
+    /// Read the next complete MIDI message from the I2C port
+    ///
+    /// Bytes left over from the previous write transaction are parsed first;
+    /// once they run out we wait for the controller's next write.
+    ///
+    /// # Returns
+    /// * `Ok(UartMidiMessage)` - A complete MIDI message tagged with `UartChannel::I2c`
+    /// * `Err(I2cMidiError)` - I2C bus error or invalid MIDI data
+    pub async fn read(&mut self) -> Result<UartMidiMessage, I2cMidiError> {
+        loop {
+            while self.pos < self.len {
+                let byte = self.buf[self.pos];
+                self.pos += 1;
+
+                match self.parser.feed_byte(byte) {
+                    Ok(Some(message)) => {
+                        return Ok(UartMidiMessage {
+                            message,
+                            uart_channel: UartChannel::I2c,
+                        });
+                    }
+                    Ok(None) => {}
+                    Err(err) => return Err(I2cMidiError::MessageError(err)),
+                }
+            }
+
+            self.pos = 0;
+            self.len = 0;
+
+            match self.i2c.listen(&mut self.buf).await {
+                Ok(Command::Write(len)) | Err(Error::PartialWrite(len)) => {
+                    self.len = len;
+                }
+                Ok(Command::Read) | Ok(Command::WriteRead(_)) => {
+                    // Input only: there is nothing to read back, so clock out
+                    // zeros until the controller gives up
+                    self.i2c
+                        .respond_till_stop(0)
+                        .await
+                        .map_err(I2cMidiError::I2cError)?;
+                }
+                Ok(Command::GeneralCall(_)) | Err(Error::PartialGeneralCall(_)) => {
+                    // General calls are not addressed to us, ignore them
+                }
+                Err(err) => return Err(I2cMidiError::I2cError(err)),
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! RTT MIDI monitor
+//!
+//! When enabled, every message parsed on an input and every message written
+//! to the output is printed over RTT as one line:
+//!
+//! ```text
+//! MON <timestamp_us> <source> [<hex bytes>]
+//! MON 1234567 IN0 [90, 3c, 64]
+//! MON 1234890 OUT [90, 3c, 64]
+//! MON 1235000 IN1 ERR Parse
+//! ```
+//!
+//! The timestamp is microseconds since boot. Sources are `IN0`, `IN1`, `I2C`,
+//! `SPI` and `OUT`. Output lines show the bytes actually put on the wire,
+//! including status bytes injected for running status. Input errors are
+//! printed as `ERR` lines. Monitor lines are printed regardless of the
+//! runtime log level.
+//!
+//! In monitor-only mode (`MONITOR_ONLY`) messages are decoded instead, with
+//! running status resolved per source:
+//!
+//! ```text
+//! MON 1234567 IN0 Note On ch1 60 vel 100
+//! ```
+
+use crate::midi_uart::UartChannel;
+use crate::trace::ErrorKind;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use defmt::Format;
+use embassy_time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Input(UartChannel),
+    Output,
+}
+
+impl Source {
+    const COUNT: usize = UartChannel::COUNT + 1;
+
+    fn index(self) -> usize {
+        match self {
+            Source::Input(channel) => channel.index(),
+            Source::Output => UartChannel::COUNT,
+        }
+    }
+}
+
+impl Format for Source {
+    fn format(&self, fmt: defmt::Formatter) {
+        let tag = match self {
+            Source::Input(UartChannel::Zero) => "IN0",
+            Source::Input(UartChannel::One) => "IN1",
+            Source::Input(UartChannel::I2c) => "I2C",
+            Source::Input(UartChannel::Spi) => "SPI",
+            Source::Output => "OUT",
+        };
+        defmt::write!(fmt, "{=str}", tag)
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static DECODED: AtomicBool = AtomicBool::new(false);
+
+/// Running status of each source, 0 for none
+static STATUS: [AtomicU8; Source::COUNT] = [const { AtomicU8::new(0) }; Source::COUNT];
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Print decoded messages instead of hex bytes
+pub fn set_decoded(decoded: bool) {
+    DECODED.store(decoded, Ordering::Relaxed);
+}
+
+/// Print a monitor line if the monitor is enabled
+pub fn record(source: Source, bytes: &[u8]) {
+    if !ENABLED.load(Ordering::Relaxed) || bytes.is_empty() {
+        return;
+    }
+    let status = track_status(source, bytes);
+    let timestamp = Instant::now().as_micros();
+    if DECODED.load(Ordering::Relaxed) {
+        defmt::println!(
+            "MON {=u64} {} {}",
+            timestamp,
+            source,
+            Decoded { status, bytes }
+        );
+    } else {
+        defmt::println!("MON {=u64} {} {=[u8]:x}", timestamp, source, bytes);
+    }
+}
+
+/// Print an input error line if the monitor is enabled
+pub fn record_error(channel: UartChannel, kind: ErrorKind) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    // The parser was reset, the input must send a status byte again
+    STATUS[Source::Input(channel).index()].store(0, Ordering::Relaxed);
+    let kind = match kind {
+        ErrorKind::Transport => "Transport",
+        ErrorKind::Parse => "Parse",
+    };
+    defmt::println!(
+        "MON {=u64} {} ERR {=str}",
+        Instant::now().as_micros(),
+        Source::Input(channel),
+        kind
+    );
+}
+
+/// Update the running status of a source, returns the message's status
+fn track_status(source: Source, bytes: &[u8]) -> Option<u8> {
+    let status = &STATUS[source.index()];
+    match bytes[0] {
+        // Voice messages set running status
+        byte @ 0x80..=0xEF => status.store(byte, Ordering::Relaxed),
+        // SysEx and System Common cancel it, realtime leaves it
+        0xF0..=0xF7 => status.store(0, Ordering::Relaxed),
+        0xF8..=0xFF => {}
+        // Running status message
+        _ => return Some(status.load(Ordering::Relaxed)).filter(|status| *status != 0),
+    }
+    Some(bytes[0])
+}
+
+/// A message in words
+struct Decoded<'a> {
+    status: Option<u8>,
+    /// The message, with or without its status byte
+    bytes: &'a [u8],
+}
+
+impl Format for Decoded<'_> {
+    fn format(&self, fmt: defmt::Formatter) {
+        let Some(status) = self.status else {
+            defmt::write!(fmt, "? {=[u8]:x}", self.bytes);
+            return;
+        };
+        let data = match self.bytes[0] {
+            0x80..=0xFF => &self.bytes[1..],
+            _ => self.bytes,
+        };
+        let channel = (status & 0x0F) + 1;
+        match (status & 0xF0, data) {
+            (0x80, [note, velocity]) => {
+                defmt::write!(
+                    fmt,
+                    "Note Off ch{=u8} {=u8} vel {=u8}",
+                    channel,
+                    note,
+                    velocity
+                )
+            }
+            (0x90, [note, velocity]) => {
+                defmt::write!(
+                    fmt,
+                    "Note On ch{=u8} {=u8} vel {=u8}",
+                    channel,
+                    note,
+                    velocity
+                )
+            }
+            (0xA0, [note, pressure]) => {
+                defmt::write!(
+                    fmt,
+                    "Poly Pressure ch{=u8} {=u8} {=u8}",
+                    channel,
+                    note,
+                    pressure
+                )
+            }
+            (0xB0, [controller, value]) => {
+                defmt::write!(
+                    fmt,
+                    "Control Change ch{=u8} {=u8} {=u8}",
+                    channel,
+                    controller,
+                    value
+                )
+            }
+            (0xC0, [program]) => {
+                defmt::write!(fmt, "Program Change ch{=u8} {=u8}", channel, program)
+            }
+            (0xD0, [pressure]) => {
+                defmt::write!(fmt, "Channel Pressure ch{=u8} {=u8}", channel, pressure)
+            }
+            (0xE0, [lsb, msb]) => {
+                let value = ((*msb as i16) << 7 | *lsb as i16) - 8192;
+                defmt::write!(fmt, "Pitch Bend ch{=u8} {=i16}", channel, value)
+            }
+            (0xF0, _) => match (status, data) {
+                (0xF0, _) => defmt::write!(fmt, "SysEx {=[u8]:x}", self.bytes),
+                (0xF1, [value]) => defmt::write!(fmt, "MTC Quarter Frame {=u8}", value),
+                (0xF2, [lsb, msb]) => {
+                    defmt::write!(
+                        fmt,
+                        "Song Position {=u16}",
+                        (*msb as u16) << 7 | *lsb as u16
+                    )
+                }
+                (0xF3, [song]) => defmt::write!(fmt, "Song Select {=u8}", song),
+                (0xF6, []) => defmt::write!(fmt, "Tune Request"),
+                (0xF8, []) => defmt::write!(fmt, "Clock"),
+                (0xFA, []) => defmt::write!(fmt, "Start"),
+                (0xFB, []) => defmt::write!(fmt, "Continue"),
+                (0xFC, []) => defmt::write!(fmt, "Stop"),
+                (0xFE, []) => defmt::write!(fmt, "Active Sensing"),
+                (0xFF, []) => defmt::write!(fmt, "System Reset"),
+                _ => defmt::write!(fmt, "? {=[u8]:x}", self.bytes),
+            },
+            _ => defmt::write!(fmt, "? {=[u8]:x}", self.bytes),
+        }
+    }
+}
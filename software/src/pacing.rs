@@ -0,0 +1,138 @@
+//! Output pacing for slow receivers
+//!
+//! Some vintage receivers choke on sustained full-bandwidth MIDI. With a
+//! minimum gap configured, `write_uart` starts messages no closer together
+//! than the gap (1_000_000 / gap messages per second). Realtime messages are
+//! not paced, they are single bytes and timing critical.
+//!
+//! Pacing lets the channel fill up during dense passages. Once the backlog
+//! reaches `THIN_BACKLOG`, thin-able messages are dropped instead of queued
+//! behind the gap: aftertouch, pitch bend and continuous controllers, where
+//! the next value supersedes the dropped one. Notes, program changes,
+//! switches (sustain etc.), bank select, Data Entry and RPN/NRPN/mode
+//! controllers are always sent.
+//!
+//! A 14-bit controller is thinned as a pair: once an MSB (1-31) is dropped,
+//! its LSB (33-63) is dropped too, until the input's next MSB of that
+//! controller goes out, so no LSB is applied to a stale MSB. The LSB after
+//! an MSB that went out is always sent.
+
+use crate::midi_uart::UartChannel;
+use embassy_time::{Duration, Instant, Timer};
+use midi_core::event::Event;
+use midi_core::parser::MidiMessage;
+
+/// Channel backlog at which thin-able messages are dropped
+pub const THIN_BACKLOG: usize = 16;
+
+/// Thinning state of a 14-bit controller pair, one bit per MSB controller
+#[derive(Clone, Copy, Default)]
+struct Pairs {
+    /// MSB dropped, its LSBs are dropped with it
+    dropped: u32,
+    /// MSB sent, its LSB is sent whatever the backlog
+    lsb_due: u32,
+}
+
+pub struct Pacer {
+    gap: Duration,
+    next: Instant,
+    /// Pairs of each input, by MIDI channel
+    pairs: [[Pairs; 16]; UartChannel::COUNT],
+}
+
+impl Pacer {
+    /// # Arguments
+    /// * `gap_us` - Minimum time between message starts, 0 disables pacing
+    pub fn new(gap_us: u64) -> Self {
+        Self {
+            gap: Duration::from_micros(gap_us),
+            next: Instant::MIN,
+            pairs: [[Pairs::default(); 16]; UartChannel::COUNT],
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.gap > Duration::from_ticks(0)
+    }
+
+    /// Whether `message` should be dropped to relieve the backlog
+    ///
+    /// Every message of the input that may be thinned goes through here, the
+    /// ones not dropped are taken as sent.
+    ///
+    /// # Arguments
+    /// * `input` - The input the message came from
+    /// * `cached_status` - The input's last status, resolves running status
+    /// * `backlog` - Messages still queued for the output
+    pub fn should_thin(
+        &mut self,
+        message: &MidiMessage,
+        input: UartChannel,
+        cached_status: Option<u8>,
+        backlog: usize,
+    ) -> bool {
+        if !self.enabled() {
+            return false;
+        }
+        let backlogged = backlog >= THIN_BACKLOG;
+        match message.event(cached_status) {
+            Some(
+                Event::PolyPressure { .. }
+                | Event::ChannelPressure { .. }
+                | Event::PitchBend { .. },
+            ) => backlogged,
+            Some(Event::ControlChange {
+                channel,
+                controller,
+                ..
+            }) => {
+                let pairs = &mut self.pairs[input.index()][usize::from(channel)];
+                thin_controller(pairs, controller, backlogged)
+            }
+            _ => false,
+        }
+    }
+
+    /// Wait until the next message may start
+    pub async fn wait(&mut self, message: &MidiMessage) {
+        if !self.enabled() || matches!(message, MidiMessage::SystemRealtime(_)) {
+            return;
+        }
+        Timer::at(self.next).await;
+        self.next = Instant::now() + self.gap;
+    }
+}
+
+/// Continuous controllers, except bank select (0/32), Data Entry (6/38),
+/// switches, RPN/NRPN and channel mode messages (64-127)
+fn is_thinnable(controller: u8) -> bool {
+    matches!(controller, 1..=5 | 7..=31 | 33..=37 | 39..=63)
+}
+
+/// Whether to drop a controller, keeping 14-bit pairs together
+fn thin_controller(pairs: &mut Pairs, controller: u8, backlogged: bool) -> bool {
+    if !is_thinnable(controller) {
+        return false;
+    }
+    if controller < 32 {
+        let bit = 1 << controller;
+        pairs.lsb_due &= !bit;
+        pairs.dropped &= !bit;
+        if backlogged {
+            pairs.dropped |= bit;
+        } else {
+            pairs.lsb_due |= bit;
+        }
+        return backlogged;
+    }
+    let bit = 1 << (controller - 32);
+    if pairs.dropped & bit != 0 {
+        return true;
+    }
+    if pairs.lsb_due & bit != 0 {
+        pairs.lsb_due &= !bit;
+        return false;
+    }
+    backlogged
+}
@@ -0,0 +1,157 @@
+//! Contiguous 14-bit controller pairs
+//!
+//! 14-bit controllers are sent as an MSB (controllers 0-31) followed by the
+//! LSB (controller + 32). Receivers combine the two, so a message from another
+//! input landing between them can glitch the value, e.g. when a filter sweep
+//! on one input meets a busy keyboard on the other.
+//!
+//! After `write_uart` sends an MSB, `PairHold` holds back messages from the
+//! other inputs until the LSB from the same input arrives, that input sends
+//! anything else, or `hold_us` passes. Held messages are then sent in their
+//! original order. Realtime messages are never held.
+//!
+//! Most controllers in 0-31 (mod wheel, volume, pan, expression) never get
+//! an LSB, so holding after every one of them would only delay the other
+//! inputs. An MSB is held for once its input has sent the LSB of that
+//! controller: the first pair of a controller goes out unheld and teaches
+//! `PairHold` that the input sends it as 14-bit.
+//!
+//! RPN and NRPN edits (CC 101/100 or 99/98, then Data Entry 6 and 38) are
+//! held together the same way, one step at a time, so the parameter an
+//! input selected is still selected when its value arrives. A sequence
+//! stops being held once the input sends anything else, or when the held
+//! queue fills up.
+
+use crate::midi_uart::UartChannel;
+use crate::queues::Inputs;
+use crate::{ChannelMessage, ControlMessage};
+use embassy_time::{with_deadline, Duration, Instant};
+use heapless::Deque;
+use midi_core::event::Kind;
+use midi_core::parameter::ParameterAssembler;
+use midi_core::parser::MidiMessage;
+
+/// Messages held back at most, the hold ends early when this many are waiting
+const HOLD_LEN: usize = 8;
+
+/// MSB sent, waiting for its LSB
+struct Pending {
+    channel: UartChannel,
+    deadline: Instant,
+}
+
+pub struct PairHold {
+    hold: Duration,
+    pending: Option<Pending>,
+    held: Deque<ChannelMessage, HOLD_LEN>,
+    /// RPN/NRPN sequences of each input, as sent
+    parameters: [ParameterAssembler; UartChannel::COUNT],
+    /// Controllers 0-31 each input has sent an LSB for, one bit each
+    paired: [u32; UartChannel::COUNT],
+}
+
+impl PairHold {
+    /// # Arguments
+    /// * `hold_us` - Longest wait for an LSB, 0 disables pair handling
+    pub fn new(hold_us: u64) -> Self {
+        Self {
+            hold: Duration::from_micros(hold_us),
+            pending: None,
+            held: Deque::new(),
+            parameters: Default::default(),
+            paired: [0; UartChannel::COUNT],
+        }
+    }
+
+    /// Next message for the output, held messages first once a pair is done
+    pub async fn next(&mut self, inputs: &mut Inputs) -> ChannelMessage {
+        loop {
+            let Some(pending) = &self.pending else {
+                if let Some(message) = self.held.pop_front() {
+                    return message;
+                }
+                return inputs.receive().await;
+            };
+
+            let Ok(message) = with_deadline(pending.deadline, inputs.receive()).await else {
+                // No LSB in time, carry on with the held messages
+                self.pending = None;
+                continue;
+            };
+
+            let pair_channel = pending.channel;
+            if is_realtime(&message) {
+                return message;
+            }
+            if from_channel(&message) == Some(pair_channel) {
+                // The LSB or anything else from the same input ends the pair
+                self.pending = None;
+                return message;
+            }
+
+            // Never fails, the hold ends as soon as the queue is full
+            let _ = self.held.push_back(message);
+            if self.held.is_full() {
+                self.pending = None;
+            }
+        }
+    }
+
+    /// Report a message written to the output
+    ///
+    /// # Arguments
+    /// * `status` - The status the message was sent with, resolves running status
+    pub fn sent(&mut self, message: &MidiMessage, channel: UartChannel, status: Option<u8>) {
+        if self.hold == Duration::from_ticks(0) {
+            return;
+        }
+        let controller = match (message.kind(status), message.data().first()) {
+            (Some(Kind::ControlChange), Some(controller)) => Some(*controller),
+            _ => None,
+        };
+        let paired = &mut self.paired[channel.index()];
+        if let Some(lsb @ 32..=63) = controller {
+            *paired |= 1 << (lsb - 32);
+        }
+        let is_msb = controller.is_some_and(|msb| msb < 32 && *paired & 1 << msb != 0);
+
+        let parameters = &mut self.parameters[channel.index()];
+        if let Some(event) = message.event(status) {
+            parameters.feed(&event);
+        }
+        if parameters.in_progress() && !self.held.is_full() {
+            // Held messages wait until the sequence is complete
+            self.pending = Some(Pending {
+                channel,
+                deadline: Instant::now() + self.hold,
+            });
+            return;
+        }
+        if !self.held.is_empty() {
+            // Held messages go out first, a new hold would only delay them
+            return;
+        }
+        if is_msb {
+            self.pending = Some(Pending {
+                channel,
+                deadline: Instant::now() + self.hold,
+            });
+        }
+    }
+}
+
+fn is_realtime(message: &ChannelMessage) -> bool {
+    matches!(
+        message,
+        ChannelMessage::Midi(midi) if matches!(midi.message, MidiMessage::SystemRealtime(_))
+    )
+}
+
+/// The input a message is about, if any
+fn from_channel(message: &ChannelMessage) -> Option<UartChannel> {
+    match message {
+        ChannelMessage::Midi(midi) => Some(midi.uart_channel),
+        ChannelMessage::Control(ControlMessage::InvalidateRunningStatus(channel)) => Some(*channel),
+        ChannelMessage::Control(_) => None,
+    }
+}
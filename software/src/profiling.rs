@@ -0,0 +1,212 @@
+//! Per-message processing time
+//!
+//! With the `profiling` feature, every message from an input is timed from
+//! the moment its read task completes it, right after its last byte, until
+//! its last byte is handed to the output UART. Messages that go out in a
+//! burst are done when the burst is written. Messages `write_uart` drops
+//! are not counted.
+//!
+//! Each input keeps its minimum, maximum and mean, and a histogram of all
+//! inputs together shows the spread. The results are logged with the queue
+//! levels every minute and sent on SysEx request `0E`. Without the feature
+//! the hooks compile to nothing and the report is all zeros.
+
+use crate::midi_uart::UartChannel;
+#[cfg(not(feature = "profiling"))]
+use embassy_time::Instant;
+
+/// Processing time histogram buckets: under 64 µs, then doubling up to
+/// 4096 µs and over
+pub const BUCKETS: usize = 8;
+
+/// Processing times of one input, in microseconds
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InputProfile {
+    pub samples: u32,
+    pub min_us: u32,
+    pub max_us: u32,
+    pub mean_us: u32,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Profile {
+    pub inputs: [InputProfile; UartChannel::COUNT],
+    /// Messages of all inputs per bucket
+    pub histogram: [u32; BUCKETS],
+}
+
+#[cfg(feature = "profiling")]
+mod timing {
+    use super::{InputProfile, Profile, BUCKETS};
+    use crate::config::INPUT_QUEUE_DEPTH;
+    use crate::midi_uart::UartChannel;
+    use crate::OUTPUT_BURST_LEN;
+    use core::cell::RefCell;
+    use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+    use embassy_sync::blocking_mutex::Mutex;
+    use embassy_time::Instant;
+    use heapless::{Deque, Vec};
+
+    /// Messages of an input between its read task and `write_uart`: its
+    /// queue, one waiting to be queued, those held by `PairHold` and the one
+    /// being written
+    const IN_FLIGHT: usize = INPUT_QUEUE_DEPTH + 10;
+
+    /// Histogram bucket of a processing time
+    fn bucket(duration_us: u32) -> usize {
+        ((u32::BITS - (duration_us >> 6).leading_zeros()) as usize).min(BUCKETS - 1)
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    struct Accumulator {
+        samples: u32,
+        min_us: u32,
+        max_us: u32,
+        total_us: u64,
+    }
+
+    impl Accumulator {
+        const fn new() -> Self {
+            Self {
+                samples: 0,
+                min_us: u32::MAX,
+                max_us: 0,
+                total_us: 0,
+            }
+        }
+
+        fn add(&mut self, duration_us: u32) {
+            self.samples = self.samples.wrapping_add(1);
+            self.min_us = self.min_us.min(duration_us);
+            self.max_us = self.max_us.max(duration_us);
+            self.total_us += duration_us as u64;
+        }
+
+        fn profile(&self) -> InputProfile {
+            if self.samples == 0 {
+                return InputProfile::default();
+            }
+            InputProfile {
+                samples: self.samples,
+                min_us: self.min_us,
+                max_us: self.max_us,
+                mean_us: (self.total_us / self.samples as u64) as u32,
+            }
+        }
+    }
+
+    struct Timing {
+        /// Completion times of the messages on their way, per input
+        received: [Deque<Instant, IN_FLIGHT>; UartChannel::COUNT],
+        /// Messages in the burst not written yet, with their input
+        bursting: Vec<(usize, Instant), OUTPUT_BURST_LEN>,
+        inputs: [Accumulator; UartChannel::COUNT],
+        histogram: [u32; BUCKETS],
+    }
+
+    impl Timing {
+        fn add(&mut self, input: usize, received: Instant, now: Instant) {
+            let duration_us = now.saturating_duration_since(received).as_micros() as u32;
+            self.inputs[input].add(duration_us);
+            let count = &mut self.histogram[bucket(duration_us)];
+            *count = count.wrapping_add(1);
+        }
+    }
+
+    static TIMING: Mutex<CriticalSectionRawMutex, RefCell<Timing>> =
+        Mutex::new(RefCell::new(Timing {
+            received: [const { Deque::new() }; UartChannel::COUNT],
+            bursting: Vec::new(),
+            inputs: [Accumulator::new(); UartChannel::COUNT],
+            histogram: [0; BUCKETS],
+        }));
+
+    /// Note a message completed by a read task, as it is queued
+    pub fn received(channel: UartChannel) {
+        let now = Instant::now();
+        TIMING.lock(|timing| {
+            // Never full, the queues can't hold more messages
+            let _ = timing.borrow_mut().received[channel.index()].push_back(now);
+        });
+    }
+
+    /// Completion time of the next message `write_uart` takes from an input
+    pub fn taken(channel: UartChannel) -> Option<Instant> {
+        TIMING.lock(|timing| timing.borrow_mut().received[channel.index()].pop_front())
+    }
+
+    /// Note a message handed to the output UART
+    pub fn written(channel: UartChannel, received: Option<Instant>) {
+        let Some(received) = received else {
+            return;
+        };
+        let now = Instant::now();
+        TIMING.lock(|timing| timing.borrow_mut().add(channel.index(), received, now));
+    }
+
+    /// Note a message added to the output burst
+    pub fn bursting(channel: UartChannel, received: Option<Instant>) {
+        let Some(received) = received else {
+            return;
+        };
+        TIMING.lock(|timing| {
+            // The burst holds fewer messages than bytes
+            let _ = timing
+                .borrow_mut()
+                .bursting
+                .push((channel.index(), received));
+        });
+    }
+
+    /// Note the burst handed to the output UART
+    pub fn burst_written() {
+        let now = Instant::now();
+        TIMING.lock(|timing| {
+            let mut timing = timing.borrow_mut();
+            while let Some((input, received)) = timing.bursting.pop() {
+                timing.add(input, received, now);
+            }
+        });
+    }
+
+    /// Processing times so far
+    pub fn snapshot() -> Profile {
+        TIMING.lock(|timing| {
+            let timing = timing.borrow();
+            Profile {
+                inputs: timing.inputs.map(|input| input.profile()),
+                histogram: timing.histogram,
+            }
+        })
+    }
+}
+
+#[cfg(feature = "profiling")]
+pub use timing::{burst_written, bursting, received, snapshot, taken, written};
+
+#[cfg(not(feature = "profiling"))]
+#[inline(always)]
+pub fn received(_channel: UartChannel) {}
+
+#[cfg(not(feature = "profiling"))]
+#[inline(always)]
+pub fn taken(_channel: UartChannel) -> Option<Instant> {
+    None
+}
+
+#[cfg(not(feature = "profiling"))]
+#[inline(always)]
+pub fn written(_channel: UartChannel, _received: Option<Instant>) {}
+
+#[cfg(not(feature = "profiling"))]
+#[inline(always)]
+pub fn bursting(_channel: UartChannel, _received: Option<Instant>) {}
+
+#[cfg(not(feature = "profiling"))]
+#[inline(always)]
+pub fn burst_written() {}
+
+#[cfg(not(feature = "profiling"))]
+pub fn snapshot() -> Profile {
+    Profile::default()
+}
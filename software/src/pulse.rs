@@ -0,0 +1,54 @@
+//! GPIO event pulses for logic-analyzer debugging
+//!
+//! With the `debug-pulses` feature, pipeline events toggle dedicated pins, so
+//! timing through the merger can be measured alongside the MIDI lines. Every
+//! edge is one event:
+//!
+//! | Pin    | Event                          |
+//! |--------|--------------------------------|
+//! | GPIO6  | Message parsed on input 1      |
+//! | GPIO7  | Message parsed on input 2      |
+//! | GPIO8  | Message written to the output  |
+//! | GPIO9  | Parse error on any input       |
+//!
+//! Without the feature, `toggle()` compiles to nothing.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Input0 = 0,
+    Input1 = 1,
+    Output = 2,
+    ParseError = 3,
+}
+
+#[cfg(feature = "debug-pulses")]
+mod pins {
+    use super::Event;
+    use core::cell::RefCell;
+    use embassy_rp::gpio::Output;
+    use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+    use embassy_sync::blocking_mutex::Mutex;
+
+    static PINS: Mutex<CriticalSectionRawMutex, RefCell<Option<[Output<'static>; 4]>>> =
+        Mutex::new(RefCell::new(None));
+
+    /// Hand over the pulse pins, in `Event` order
+    pub fn init(pins: [Output<'static>; 4]) {
+        PINS.lock(|p| *p.borrow_mut() = Some(pins));
+    }
+
+    pub fn toggle(event: Event) {
+        PINS.lock(|p| {
+            if let Some(pins) = p.borrow_mut().as_mut() {
+                pins[event as usize].toggle();
+            }
+        });
+    }
+}
+
+#[cfg(feature = "debug-pulses")]
+pub use pins::{init, toggle};
+
+#[cfg(not(feature = "debug-pulses"))]
+#[inline(always)]
+pub fn toggle(_event: Event) {}
@@ -0,0 +1,78 @@
+//! Quantized transport relay
+//!
+//! With `QUANTIZE_TRANSPORT` set, `write_uart` holds a Start or Continue that
+//! arrives while a clock is running and releases it right before the clock
+//! that begins the next bar, so a late press of Play on the master still
+//! brings the slaves in on the downbeat. Stop cancels a held Start. Without a
+//! running clock there is no bar to wait for, and Start goes out at once.
+//!
+//! Bars are counted from the last Start that went out, or from the first
+//! clock after boot.
+
+/// What to do with a realtime message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relay {
+    Pass,
+    /// Held until the next bar
+    Hold,
+    /// Write this held byte first, then the message
+    ReleaseFirst(u8),
+}
+
+pub struct TransportQuantizer {
+    clocks_per_bar: Option<u32>,
+    /// Position of the next clock within the bar
+    phase: u32,
+    held: Option<u8>,
+}
+
+impl TransportQuantizer {
+    /// # Arguments
+    /// * `clocks_per_bar` - Bar length in clocks, e.g. 96 for 4/4; `None`
+    ///   passes transport straight through
+    pub fn new(clocks_per_bar: Option<u32>) -> Self {
+        Self {
+            clocks_per_bar,
+            phase: 0,
+            held: None,
+        }
+    }
+
+    /// Route a realtime byte on its way to the output
+    ///
+    /// # Arguments
+    /// * `clock_running` - A clock is arriving, so there is a bar to wait for
+    pub fn relay(&mut self, byte: u8, clock_running: bool) -> Relay {
+        let Some(clocks_per_bar) = self.clocks_per_bar else {
+            return Relay::Pass;
+        };
+        match byte {
+            0xFA | 0xFB if clock_running => {
+                // A second press replaces the first
+                self.held = Some(byte);
+                Relay::Hold
+            }
+            0xFA => {
+                // The next clock is the downbeat
+                self.phase = 0;
+                Relay::Pass
+            }
+            0xFC => {
+                self.held = None;
+                Relay::Pass
+            }
+            0xF8 => {
+                let downbeat = self.phase == 0;
+                self.phase = (self.phase + 1) % clocks_per_bar;
+                match self.held.take() {
+                    Some(held) if downbeat => Relay::ReleaseFirst(held),
+                    held => {
+                        self.held = held;
+                        Relay::Pass
+                    }
+                }
+            }
+            _ => Relay::Pass,
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Per-input queues between the read tasks and `write_uart`
+//!
+//! Each input has its own single-producer single-consumer queue: the task
+//! reading the input pushes, `write_uart` pops. The read tasks no longer
+//! contend for one shared channel, a full queue only makes its own reader
+//! wait, and `write_uart` takes the inputs in turn, so one flooding input
+//! can't hold the others back behind its backlog. The queues themselves are
+//! `midi_core::queues`; this module keeps them in statics.
+//!
+//! While a SysEx is streamed, the readers of the other inputs drop what
+//! doesn't fit in their queues instead of waiting, so the shared UART
+//! reader keeps reading the streaming input (see `midi_core::queues`).
+//!
+//! Everything a read task queues goes through its input's queue, so running
+//! status invalidations after an error stay behind the messages parsed
+//! before it. Control messages from the other tasks (internal sources, the
+//! selector button, latency probes) still share `CHANNEL`, which `write_uart`
+//! serves first.
+
+use crate::config::INPUT_QUEUE_DEPTH;
+use crate::midi_uart::{UartChannel, UartMidiMessage};
+use crate::{stats, ChannelMessage, ControlMessage, CHANNEL};
+use embassy_futures::select::{select, Either};
+use heapless::spsc::Queue;
+use midi_core::parser::MidiMessage;
+use midi_core::queues::{self, Flow, Queued, Receivers};
+use static_cell::ConstStaticCell;
+
+/// A heapless queue of N slots holds N - 1 messages
+const QUEUE_LEN: usize = INPUT_QUEUE_DEPTH + 1;
+
+static QUEUES: [ConstStaticCell<Queue<ChannelMessage, QUEUE_LEN>>; UartChannel::COUNT] =
+    [const { ConstStaticCell::new(Queue::new()) }; UartChannel::COUNT];
+
+static FLOW: Flow = Flow::new();
+
+impl Queued for ChannelMessage {
+    fn is_realtime(&self) -> bool {
+        matches!(
+            self,
+            ChannelMessage::Midi(UartMidiMessage {
+                message: MidiMessage::SystemRealtime(_),
+                ..
+            })
+        )
+    }
+
+    fn lost(channel: UartChannel) -> Self {
+        ChannelMessage::Control(ControlMessage::InvalidateRunningStatus(channel))
+    }
+}
+
+/// Sending end of an input's queue, owned by the task reading the input
+pub struct InputSender(queues::InputSender<'static, ChannelMessage, QUEUE_LEN>);
+
+impl InputSender {
+    /// Queue a message, waiting while the queue is full
+    ///
+    /// While another input's SysEx is streamed, a message that doesn't fit
+    /// is dropped and counted as a queue drop.
+    pub async fn send(&mut self, message: ChannelMessage) {
+        if self.0.send(message).await.is_err() {
+            stats::record_queue_drop();
+        }
+    }
+}
+
+/// Receiving end of the input queues and `CHANNEL`, owned by `write_uart`
+pub struct Inputs {
+    receivers: Receivers<'static, ChannelMessage, QUEUE_LEN>,
+}
+
+impl Inputs {
+    fn try_receive(&mut self) -> Option<ChannelMessage> {
+        if let Ok(message) = CHANNEL.try_receive() {
+            return Some(message);
+        }
+        self.receivers.try_receive()
+    }
+
+    /// Next message: control messages first, then one from each input in turn
+    pub async fn receive(&mut self) -> ChannelMessage {
+        self.receivers.end_stream();
+        loop {
+            if let Some(message) = self.try_receive() {
+                return message;
+            }
+            if let Either::First(message) = select(CHANNEL.receive(), self.receivers.queued()).await
+            {
+                return message;
+            }
+        }
+    }
+
+    /// Next message of one input, or a realtime message from another
+    ///
+    /// Used while a SysEx of that input is being streamed to the output.
+    /// Control messages and the rest of the other inputs wait, what doesn't
+    /// fit in the other inputs' queues meanwhile is dropped.
+    pub async fn receive_from(&mut self, channel: UartChannel) -> ChannelMessage {
+        self.receivers.receive_from(channel).await
+    }
+
+    /// Messages waiting in all queues
+    pub fn len(&self) -> usize {
+        CHANNEL.len() + self.receivers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Total capacity of the input queues and `CHANNEL`
+pub fn capacity() -> usize {
+    CHANNEL.capacity() + UartChannel::COUNT * INPUT_QUEUE_DEPTH
+}
+
+/// Split the input queues into their two ends, once at boot
+pub fn split() -> (Inputs, [InputSender; UartChannel::COUNT]) {
+    let (receivers, senders) = queues::split(QUEUES.each_ref().map(ConstStaticCell::take), &FLOW);
+    (Inputs { receivers }, senders.map(InputSender))
+}
@@ -0,0 +1,106 @@
+//! Escalation of endless resyncs to a UART receiver restart
+//!
+//! A few resyncs heal by themselves. A steady stream of them, from a baud
+//! mismatch, a floating input or a receiver stuck after a glitch, never does:
+//! the parser resyncs forever and nothing gets through. With `UART_REINIT`
+//! set, a UART input whose parser resyncs that many times within the window
+//! has its receiver restarted: receiving is switched off, whatever the FIFO
+//! and the receive buffer hold is thrown away, the error flags are cleared
+//! and receiving is switched back on, after which the reader starts over with
+//! a fresh parser.
+//!
+//! UART0 also transmits the merged output, so only the receive side is
+//! touched; the transmitter keeps running throughout.
+
+use crate::midi_uart::UartChannel;
+use core::cell::RefCell;
+use embassy_rp::pac;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::{Duration, Instant};
+use embedded_io_async::{BufRead, ReadReady};
+
+/// How many resyncs within which time mean the receiver is at fault
+#[derive(Debug, Clone, Copy)]
+pub struct Escalation {
+    pub resyncs: u32,
+    pub window: Duration,
+}
+
+/// Resyncs of an input in the current window
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    /// Resync count of the parser when last seen
+    seen: u32,
+    resyncs: u32,
+    start: Instant,
+}
+
+impl Window {
+    const fn new() -> Self {
+        Self {
+            seen: 0,
+            resyncs: 0,
+            start: Instant::from_ticks(0),
+        }
+    }
+}
+
+static WINDOWS: Mutex<CriticalSectionRawMutex, RefCell<[Window; UartChannel::COUNT]>> =
+    Mutex::new(RefCell::new([Window::new(); UartChannel::COUNT]));
+
+/// Count the new resyncs of an input's parser
+///
+/// Returns true when they reach the escalation threshold, and starts a new
+/// window.
+///
+/// # Arguments
+/// * `resyncs` - Current resync count of the input's parser
+pub fn escalates(escalation: Escalation, channel: UartChannel, resyncs: u32, now: Instant) -> bool {
+    WINDOWS.lock(|windows| {
+        let window = &mut windows.borrow_mut()[channel.index()];
+        let new = resyncs.wrapping_sub(window.seen);
+        window.seen = resyncs;
+        if new == 0 {
+            return false;
+        }
+        if window.resyncs == 0 || now - window.start > escalation.window {
+            window.resyncs = 0;
+            window.start = now;
+        }
+        window.resyncs = window.resyncs.saturating_add(new);
+        if window.resyncs >= escalation.resyncs {
+            window.resyncs = 0;
+            return true;
+        }
+        false
+    })
+}
+
+/// Restart the receiver of a UART input
+///
+/// # Arguments
+/// * `usart` - The input's buffered receiver, emptied of what it holds
+pub async fn restart_receiver<R: BufRead + ReadReady>(channel: UartChannel, usart: &mut R) {
+    let regs = match channel {
+        UartChannel::Zero => pac::UART0,
+        UartChannel::One => pac::UART1,
+        _ => return,
+    };
+    regs.uartcr().modify(|w| w.set_rxe(false));
+    // Bytes still in the FIFO are read past the receive interrupt, which
+    // may take some of them into the buffer first; that is emptied next
+    while !regs.uartfr().read().rxfe() {
+        let _ = regs.uartdr().read();
+    }
+    while usart.read_ready().unwrap_or(false) {
+        // An error pending in the buffer is returned instead of the bytes
+        let Ok(len) = usart.fill_buf().await.map(|bytes| bytes.len()) else {
+            continue;
+        };
+        usart.consume(len);
+    }
+    // Any write clears the framing, parity, break and overrun flags
+    regs.uartrsr().write_value(pac::uart::regs::Uartrsr(0));
+    regs.uartcr().modify(|w| w.set_rxe(true));
+}
@@ -0,0 +1,71 @@
+//! Reset reason of the current run
+//!
+//! Read once at boot from the watchdog and chip reset registers, so a
+//! spontaneous reboot (watchdog) can be told apart from a power problem. The
+//! RP2040 has no separate brownout flag: the brownout detector triggers the
+//! same reset as power-on.
+
+use core::sync::atomic::{AtomicU8, Ordering};
+use defmt::Format;
+use embassy_rp::pac;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+#[repr(u8)]
+pub enum ResetReason {
+    /// Anything the registers don't record, e.g. a debugger's SYSRESETREQ
+    Unknown = 0,
+    /// Power-on or brownout
+    PowerOn = 1,
+    /// RUN pin pulled low
+    RunPin = 2,
+    /// Rescue reset through the debug port
+    Debug = 3,
+    /// Watchdog timeout
+    Watchdog = 4,
+    /// Reset forced through the watchdog, i.e. requested by software
+    Software = 5,
+}
+
+impl ResetReason {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => ResetReason::PowerOn,
+            2 => ResetReason::RunPin,
+            3 => ResetReason::Debug,
+            4 => ResetReason::Watchdog,
+            5 => ResetReason::Software,
+            _ => ResetReason::Unknown,
+        }
+    }
+}
+
+static REASON: AtomicU8 = AtomicU8::new(ResetReason::Unknown as u8);
+
+/// Read and remember the reset reason
+///
+/// Must run at boot, before the watchdog is started.
+pub fn init() -> ResetReason {
+    let watchdog = pac::WATCHDOG.reason().read();
+    let chip = pac::VREG_AND_CHIP_RESET.chip_reset().read();
+    // A watchdog reset leaves the chip reset flags of the previous reset in
+    // place, so the watchdog reason takes precedence
+    let reason = if watchdog.timer() {
+        ResetReason::Watchdog
+    } else if watchdog.force() {
+        ResetReason::Software
+    } else if chip.had_psm_restart() {
+        ResetReason::Debug
+    } else if chip.had_run() {
+        ResetReason::RunPin
+    } else if chip.had_por() {
+        ResetReason::PowerOn
+    } else {
+        ResetReason::Unknown
+    };
+    REASON.store(reason as u8, Ordering::Relaxed);
+    reason
+}
+
+pub fn reason() -> ResetReason {
+    ResetReason::from_u8(REASON.load(Ordering::Relaxed))
+}
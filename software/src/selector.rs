@@ -0,0 +1,90 @@
+//! A/B input selector mode
+//!
+//! With `SELECTOR_MODE` on, nothing is merged: only the selected input
+//! reaches the output, for setups where a single controller should ever be
+//! live. Input 1 is selected at boot. The selector button steps through the
+//! inputs, and a Control Change on the selector controller, from any input,
+//! selects one by value; the selecting CC is not forwarded.
+//!
+//! On every switch `write_uart` sends All Notes Off on all 16 channels, so
+//! no note hangs from the input switched away, and starts the next message
+//! with a status byte.
+
+use crate::midi_uart::UartChannel;
+use midi_core::event::Event;
+use midi_core::parser::MidiMessage;
+
+/// All Notes Off (CC 123) on every channel
+pub const ALL_NOTES_OFF: [u8; 48] = {
+    let mut bytes = [0; 48];
+    let mut channel = 0;
+    while channel < 16 {
+        bytes[channel * 3] = 0xB0 | channel as u8;
+        bytes[channel * 3 + 1] = 123;
+        channel += 1;
+    }
+    bytes
+};
+
+pub struct Selector {
+    enabled: bool,
+    controller: Option<u8>,
+    selected: UartChannel,
+}
+
+impl Selector {
+    /// # Arguments
+    /// * `enabled` - Selector mode on, otherwise every input passes
+    /// * `controller` - Control Change number that selects an input
+    pub fn new(enabled: bool, controller: Option<u8>) -> Self {
+        Self {
+            enabled,
+            controller,
+            selected: UartChannel::Zero,
+        }
+    }
+
+    /// Whether messages from an input go to the output
+    pub fn passes(&self, channel: UartChannel) -> bool {
+        !self.enabled || channel == self.selected
+    }
+
+    /// Input a message selects, if it is a selector Control Change
+    ///
+    /// The value range is split evenly between the inputs: with four inputs
+    /// 0-31 selects input 1, 32-63 input 2, and so on.
+    ///
+    /// # Arguments
+    /// * `cached_status` - The input's last status, resolves running status
+    pub fn selected_by(
+        &self,
+        message: &MidiMessage,
+        cached_status: Option<u8>,
+    ) -> Option<UartChannel> {
+        let controller = self.controller.filter(|_| self.enabled)?;
+        match message.event(cached_status)? {
+            Event::ControlChange {
+                controller: number,
+                value,
+                ..
+            } if number == controller => {
+                Some(UartChannel::ALL[value as usize * UartChannel::COUNT / 128])
+            }
+            _ => None,
+        }
+    }
+
+    /// The input after the selected one, for the selector button
+    pub fn next(&self) -> UartChannel {
+        UartChannel::ALL[(self.selected.index() + 1) % UartChannel::COUNT]
+    }
+
+    /// Select an input, returns whether the selection changed
+    pub fn select(&mut self, channel: UartChannel) -> bool {
+        let changed = self.enabled && channel != self.selected;
+        if changed {
+            self.selected = channel;
+        }
+        changed
+    }
+}
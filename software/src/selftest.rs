@@ -0,0 +1,138 @@
+use crate::log;
+use crate::midi_uart::UartChannel;
+use crate::{ChannelMessage, ControlMessage, CHANNEL};
+use embassy_rp::gpio::Output;
+use embassy_rp::uart::{BufferedUartRx, BufferedUartTx, Instance};
+use embassy_time::{with_timeout, Duration, Instant, Timer};
+use embedded_io_async::{Read, Write};
+
+/// Bytes sent on the output and expected back on each input
+///
+/// A SysEx with our non-commercial header, so a receiver that happens to be
+/// connected during the test ignores it.
+const LOOPBACK_PATTERN: [u8; 8] = [0xF0, 0x7D, 0x4D, 0x4D, 0x7F, 0x55, 0x2A, 0xF7];
+
+/// How long to wait for the pattern to come back (8 bytes take ~2.6ms)
+const LOOPBACK_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Duration of the timer check and the accepted deviation from it
+const TIMER_CHECK: Duration = Duration::from_millis(20);
+const TIMER_TOLERANCE: Duration = Duration::from_millis(2);
+
+/// Individual self-test checks, numbered by their LED blink count
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Check {
+    /// A timer of known length fires and matches the time base
+    Timer = 1,
+    /// The message channel accepts exactly its capacity and drains in order
+    Queue = 2,
+    /// Output patched back into input 1 returns the test pattern
+    Uart0Loopback = 3,
+    /// Output patched back into input 2 returns the test pattern
+    Uart1Loopback = 4,
+}
+
+/// Run the power-on self-test
+///
+/// Meant for validating freshly assembled boards: patch one output into each
+/// input, close the self-test jumper and power up. The checks run before any
+/// task is spawned, so they have the UARTs and the channel to themselves.
+///
+/// # Returns
+/// * `Ok(())` - All checks passed
+/// * `Err(Check)` - The first check that failed
+pub async fn run(
+    tx: &mut BufferedUartTx<'static, impl Instance>,
+    rx0: &mut BufferedUartRx<'static, impl Instance>,
+    rx1: &mut BufferedUartRx<'static, impl Instance>,
+) -> Result<(), Check> {
+    check(Check::Timer, timer().await)?;
+    check(Check::Queue, queue())?;
+    // Both inputs listen to the same output, so one pattern serves both
+    let sent = tx.write_all(&LOOPBACK_PATTERN).await.is_ok();
+    check(Check::Uart0Loopback, sent && loopback(rx0).await)?;
+    check(Check::Uart1Loopback, sent && loopback(rx1).await)?;
+    Ok(())
+}
+
+fn check(check: Check, passed: bool) -> Result<(), Check> {
+    if passed {
+        log::info!("Self-test {:?}: pass", check);
+        Ok(())
+    } else {
+        defmt::error!("Self-test {:?}: FAIL", check);
+        Err(check)
+    }
+}
+
+async fn loopback(rx: &mut BufferedUartRx<'static, impl Instance>) -> bool {
+    let mut received = [0u8; LOOPBACK_PATTERN.len()];
+    match with_timeout(LOOPBACK_TIMEOUT, rx.read_exact(&mut received)).await {
+        Ok(Ok(())) => received == LOOPBACK_PATTERN,
+        _ => false,
+    }
+}
+
+fn queue() -> bool {
+    if !CHANNEL.is_empty() {
+        return false;
+    }
+
+    let mut sent = 0;
+    while CHANNEL
+        .try_send(ChannelMessage::Control(
+            ControlMessage::InvalidateRunningStatus(UartChannel::ALL[sent % UartChannel::COUNT]),
+        ))
+        .is_ok()
+    {
+        sent += 1;
+    }
+    if sent != CHANNEL.capacity() {
+        return false;
+    }
+
+    let mut received = 0;
+    while let Ok(message) = CHANNEL.try_receive() {
+        match message {
+            ChannelMessage::Control(ControlMessage::InvalidateRunningStatus(channel))
+                if channel == UartChannel::ALL[received % UartChannel::COUNT] => {}
+            _ => return false,
+        }
+        received += 1;
+    }
+    received == sent
+}
+
+async fn timer() -> bool {
+    // If the alarm never fires we hang here with the LED dark, which is as
+    // clear a failure as any blink pattern
+    let start = Instant::now();
+    Timer::after(TIMER_CHECK).await;
+    let elapsed = start.elapsed();
+    elapsed >= TIMER_CHECK && elapsed <= TIMER_CHECK + TIMER_TOLERANCE
+}
+
+/// Show the self-test result on the LED
+///
+/// Pass: three short blinks. Fail: the failed check's number as long blinks,
+/// repeated three times.
+pub async fn show_result(led: &mut Output<'static>, result: Result<(), Check>) {
+    match result {
+        Ok(()) => blink(led, 3, Duration::from_millis(100)).await,
+        Err(check) => {
+            for _ in 0..3 {
+                blink(led, check as u8, Duration::from_millis(400)).await;
+                Timer::after_secs(1).await;
+            }
+        }
+    }
+}
+
+async fn blink(led: &mut Output<'static>, count: u8, length: Duration) {
+    for _ in 0..count {
+        led.set_high();
+        Timer::after(length).await;
+        led.set_low();
+        Timer::after(length).await;
+    }
+}
@@ -0,0 +1,77 @@
+//! Controller state snapshot
+//!
+//! `write_uart` keeps the latest value of each controller in
+//! `SNAPSHOT_CONTROLLERS`, per channel, as it goes out on the output. SysEx
+//! `0C` captures those values as the snapshot, `0D` sends the snapshot back
+//! out, e.g. to restore a mixer or synth that lost its state after a power
+//! blip downstream. Controllers that never went out on a channel are left
+//! out of the snapshot.
+//!
+//! The snapshot lives in RAM until the next capture or a reset of the board.
+
+use crate::log;
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use heapless::Vec;
+
+/// Most controllers that can be tracked
+pub const MAX_CONTROLLERS: usize = 16;
+
+/// Longest recall, every tracked controller on every channel
+pub const RECALL_LEN: usize = 16 * MAX_CONTROLLERS * 3;
+
+type Values = [[Option<u8>; MAX_CONTROLLERS]; 16];
+
+struct State {
+    current: Values,
+    captured: Values,
+}
+
+static STATE: Mutex<CriticalSectionRawMutex, RefCell<State>> = Mutex::new(RefCell::new(State {
+    current: [[None; MAX_CONTROLLERS]; 16],
+    captured: [[None; MAX_CONTROLLERS]; 16],
+}));
+
+/// Note a Control Change going out on the output
+///
+/// # Arguments
+/// * `controllers` - The tracked controller numbers, at most `MAX_CONTROLLERS`
+pub fn track(controllers: &[u8], status: u8, controller: u8, value: u8) {
+    let Some(index) = controllers.iter().position(|&c| c == controller) else {
+        return;
+    };
+    STATE.lock(|state| {
+        state.borrow_mut().current[(status & 0x0F) as usize][index] = Some(value);
+    });
+}
+
+/// Capture the current controller values as the snapshot
+pub fn capture() {
+    STATE.lock(|state| {
+        let mut state = state.borrow_mut();
+        state.captured = state.current;
+        let values = state.captured.iter().flatten().flatten().count();
+        log::info!("Captured a snapshot of {} controller values", values);
+    });
+}
+
+/// The Control Change messages that restore the snapshot, each with its
+/// status byte
+pub fn recall(controllers: &[u8]) -> Vec<u8, RECALL_LEN> {
+    let mut message = Vec::new();
+    STATE.lock(|state| {
+        let state = state.borrow();
+        for (channel, values) in state.captured.iter().enumerate() {
+            for (controller, value) in controllers.iter().zip(values) {
+                if let Some(value) = value {
+                    // Capacity covers every controller on every channel
+                    message
+                        .extend_from_slice(&[0xB0 | channel as u8, *controller, *value])
+                        .unwrap();
+                }
+            }
+        }
+    });
+    message
+}
@@ -0,0 +1,115 @@
+use crate::log;
+use crate::midi_uart::{UartChannel, UartMidiMessage};
+use crate::stats;
+use embassy_rp::gpio::Output;
+use embassy_rp::spi::{Async, Instance, Spi};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+
+/// Length of every SPI transaction in bytes
+///
+/// A postcard-encoded `UartMidiMessage` is at most 6 bytes, COBS framing adds
+/// two more. The remainder of the frame is zero padding.
+const FRAME_LEN: usize = 12;
+
+/// Messages queued for the co-processor
+///
+/// Filled by `write_uart` after each message hits the wire and drained by the
+/// bridge task. The bridge must never stall the DIN output, so `forward()`
+/// drops messages when the queue is full. `write_uart` runs on the interrupt
+/// executor, hence the critical section mutex.
+static BRIDGE_OUT: Channel<CriticalSectionRawMutex, UartMidiMessage, QUEUE_LEN> = Channel::new();
+
+/// Capacity of the queue towards the co-processor
+pub const QUEUE_LEN: usize = 16;
+
+/// Queue a merged message for the co-processor
+///
+/// Messages that came from the co-processor are not echoed back to it.
+pub fn forward(message: &UartMidiMessage) {
+    if message.uart_channel == UartChannel::Spi {
+        return;
+    }
+    if BRIDGE_OUT.try_send(message.clone()).is_err() {
+        log::warn!("SPI bridge queue full, dropping message");
+        stats::record_queue_drop();
+    }
+}
+
+/// Wait for the next message queued by `forward()`
+pub async fn next_outgoing() -> UartMidiMessage {
+    let message = BRIDGE_OUT.receive().await;
+    stats::record_bridge_level(BRIDGE_OUT.len() + 1);
+    message
+}
+
+pub enum SpiBridgeError {
+    Transfer(embassy_rp::spi::Error),
+    Encode,
+    Decode,
+}
+
+/// SPI link exchanging parsed MIDI messages with a co-processor MCU
+///
+/// The merger is the SPI controller. Every transaction is a fixed-size,
+/// full-duplex frame: we clock out one postcard/COBS-encoded `UartMidiMessage`
+/// (or an all-zero idle frame) and receive one from the co-processor at the
+/// same time. COBS guarantees an encoded frame never starts with 0x00, so a
+/// leading zero marks an idle frame in either direction.
+///
+/// Incoming messages are re-tagged with `UartChannel::Spi` so the merge task
+/// handles them like any other input, including running status.
+pub struct SpiBridge<'a, T: Instance> {
+    spi: Spi<'a, T, Async>,
+    cs: Output<'a>,
+}
+
+impl<'a, T: Instance> SpiBridge<'a, T> {
+    /// Create a new SPI bridge
+    ///
+    /// # Arguments
+    /// * `spi` - SPI controller connected to the co-processor
+    /// * `cs` - Chip select output, driven low for the duration of each frame
+    pub fn new(spi: Spi<'a, T, Async>, mut cs: Output<'a>) -> Self {
+        cs.set_high();
+        Self { spi, cs }
+    }
+
+    /// Exchange one frame with the co-processor
+    ///
+    /// # Arguments
+    /// * `outgoing` - Message to send, or `None` to send an idle frame (poll)
+    ///
+    /// # Returns
+    /// * `Ok(Some(UartMidiMessage))` - The co-processor sent a message
+    /// * `Ok(None)` - The co-processor had nothing to send
+    /// * `Err(SpiBridgeError)` - Transfer failed or a frame could not be (de)coded
+    pub async fn exchange(
+        &mut self,
+        outgoing: Option<&UartMidiMessage>,
+    ) -> Result<Option<UartMidiMessage>, SpiBridgeError> {
+        let mut tx = [0u8; FRAME_LEN];
+        if let Some(message) = outgoing {
+            postcard::to_slice_cobs(message, &mut tx).map_err(|_| SpiBridgeError::Encode)?;
+        }
+
+        let mut rx = [0u8; FRAME_LEN];
+        self.cs.set_low();
+        let result = self.spi.transfer(&mut rx, &tx).await;
+        self.cs.set_high();
+        result.map_err(SpiBridgeError::Transfer)?;
+
+        if rx[0] == 0 {
+            // Idle frame
+            return Ok(None);
+        }
+
+        let incoming: UartMidiMessage =
+            postcard::from_bytes_cobs(&mut rx).map_err(|_| SpiBridgeError::Decode)?;
+
+        Ok(Some(UartMidiMessage {
+            message: incoming.message,
+            uart_channel: UartChannel::Spi,
+        }))
+    }
+}
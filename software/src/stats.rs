@@ -0,0 +1,134 @@
+use crate::midi_uart::UartChannel;
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// Counters for a single input port
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct InputStats {
+    /// Complete MIDI messages received
+    pub messages: u32,
+    /// Transport errors (UART framing/overrun/break/parity, I2C aborts, SPI frames)
+    pub transport_errors: u32,
+    /// MIDI protocol errors reported by the parser
+    pub parse_errors: u32,
+    /// Parser resync events, including byte timeouts
+    pub resyncs: u32,
+}
+
+impl InputStats {
+    const fn new() -> Self {
+        Self {
+            messages: 0,
+            transport_errors: 0,
+            parse_errors: 0,
+            resyncs: 0,
+        }
+    }
+}
+
+/// Device-wide counters, aggregated from all tasks
+///
+/// All counters wrap around on overflow.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct Stats {
+    pub inputs: [InputStats; UartChannel::COUNT],
+    /// Messages dropped because a secondary queue (SPI bridge) was full, or
+    /// an input queue while another input's SysEx was streamed
+    pub queue_drops: u32,
+    /// Messages `write_uart` discarded (running status without a cached status,
+    /// thinning while the output is paced)
+    pub output_drops: u32,
+    /// Failed writes to the output UART
+    pub tx_errors: u32,
+    /// Highest occupancy of the main message channel seen so far
+    pub channel_high_water: u32,
+    /// Highest occupancy of the SPI bridge queue seen so far
+    pub bridge_high_water: u32,
+    /// GM System On, GS Reset and XG System On messages received on any input
+    pub module_resets: u32,
+}
+
+impl Stats {
+    const fn new() -> Self {
+        Self {
+            inputs: [InputStats::new(); UartChannel::COUNT],
+            queue_drops: 0,
+            output_drops: 0,
+            tx_errors: 0,
+            channel_high_water: 0,
+            bridge_high_water: 0,
+            module_resets: 0,
+        }
+    }
+}
+
+// Shared by the read, write and bridge tasks. A critical section mutex keeps
+// updates safe whatever executor or interrupt priority the tasks end up on,
+// and lets `snapshot()` return a consistent view for the status report.
+static STATS: Mutex<CriticalSectionRawMutex, RefCell<Stats>> =
+    Mutex::new(RefCell::new(Stats::new()));
+
+fn update(f: impl FnOnce(&mut Stats)) {
+    STATS.lock(|stats| f(&mut stats.borrow_mut()));
+}
+
+fn update_input(channel: UartChannel, f: impl FnOnce(&mut InputStats)) {
+    update(|stats| f(&mut stats.inputs[channel.index()]));
+}
+
+/// Count a received message and refresh the input's parser resync count
+pub fn record_message(channel: UartChannel, resyncs: u32) {
+    update_input(channel, |input| {
+        input.messages = input.messages.wrapping_add(1);
+        input.resyncs = resyncs;
+    });
+}
+
+pub fn record_transport_error(channel: UartChannel) {
+    update_input(channel, |input| {
+        input.transport_errors = input.transport_errors.wrapping_add(1)
+    });
+}
+
+pub fn record_parse_error(channel: UartChannel) {
+    update_input(channel, |input| {
+        input.parse_errors = input.parse_errors.wrapping_add(1)
+    });
+}
+
+pub fn record_queue_drop() {
+    update(|stats| stats.queue_drops = stats.queue_drops.wrapping_add(1));
+}
+
+pub fn record_output_drop() {
+    update(|stats| stats.output_drops = stats.output_drops.wrapping_add(1));
+}
+
+pub fn record_tx_error() {
+    update(|stats| stats.tx_errors = stats.tx_errors.wrapping_add(1));
+}
+
+pub fn record_module_reset() {
+    update(|stats| stats.module_resets = stats.module_resets.wrapping_add(1));
+}
+
+/// Record the occupancy of the main message channel
+///
+/// Called by the consumer right after taking a message, with the number of
+/// messages that were queued including that one. A queue only shrinks when
+/// the consumer takes from it, so this catches every peak.
+pub fn record_channel_level(level: usize) {
+    update(|stats| stats.channel_high_water = stats.channel_high_water.max(level as u32));
+}
+
+/// Record the occupancy of the SPI bridge queue, see `record_channel_level()`
+pub fn record_bridge_level(level: usize) {
+    update(|stats| stats.bridge_high_water = stats.bridge_high_water.max(level as u32));
+}
+
+/// Copy of all counters at this instant
+pub fn snapshot() -> Stats {
+    STATS.lock(|stats| *stats.borrow())
+}
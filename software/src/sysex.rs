@@ -0,0 +1,522 @@
+use crate::crash_log::{CrashRecord, PANIC_MESSAGE_LEN, RECENT_LEN};
+use crate::latency::LatencyStats;
+use crate::log::LogLevel;
+use crate::midi_uart::UartChannel;
+use crate::profiling::{Profile, BUCKETS};
+use crate::reset::ResetReason;
+use crate::stats::Stats;
+use crate::trace::{Trace, TRACE_LEN};
+use crate::DEVICE_ID;
+use defmt::Format;
+use heapless::Vec;
+
+/// Header of every SysEx message exchanged with the merger
+///
+/// 0x7D is the MIDI "non-commercial" manufacturer ID, followed by the product
+/// tag "MM" (MIDI Merger) so we don't react to other 0x7D users on the chain.
+/// The header is followed by the device ID, then the command.
+const HEADER: [u8; 4] = [0xF0, 0x7D, 0x4D, 0x4D];
+
+/// Device ID every merger on the chain answers to
+const BROADCAST_ID: u8 = 0x7F;
+
+/// Universal Non-Realtime Identity Request (`F0 7E <dev> 06 01 F7`) and the
+/// start of its reply
+const UNIVERSAL_NON_REALTIME: u8 = 0x7E;
+const GENERAL_INFORMATION: u8 = 0x06;
+const IDENTITY_REQUEST: u8 = 0x01;
+const IDENTITY_REPLY: u8 = 0x02;
+
+/// General MIDI System On (`F0 7E <dev> 09 01 F7`)
+const GENERAL_MIDI: u8 = 0x09;
+const GM_SYSTEM_ON: u8 = 0x01;
+
+/// Roland GS Reset and Yamaha XG System On, after the manufacturer ID and
+/// device number (0x1n)
+const ROLAND_ID: u8 = 0x41;
+const GS_RESET: [u8; 8] = [0x42, 0x12, 0x40, 0x00, 0x7F, 0x00, 0x41, SYSEX_END];
+const YAMAHA_ID: u8 = 0x43;
+const XG_SYSTEM_ON: [u8; 6] = [0x4C, 0x00, 0x00, 0x7E, 0x00, SYSEX_END];
+
+const SYSEX_END: u8 = 0xF7;
+
+// Command bytes. Replies use the request command with bit 6 set.
+const CMD_STATUS_REQUEST: u8 = 0x01;
+const CMD_STATUS_REPLY: u8 = 0x41;
+const CMD_LATENCY_START: u8 = 0x02;
+const CMD_LATENCY_STOP: u8 = 0x03;
+const CMD_LATENCY_REPORT: u8 = 0x43;
+const CMD_LATENCY_PROBE: u8 = 0x04;
+const CMD_SET_LOG_LEVEL: u8 = 0x05;
+const CMD_SET_MONITOR: u8 = 0x06;
+const CMD_CRASH_LOG_REQUEST: u8 = 0x07;
+const CMD_CRASH_LOG_REPLY: u8 = 0x47;
+const CMD_CRASH_LOG_CLEAR: u8 = 0x08;
+const CMD_TRACE_REQUEST: u8 = 0x09;
+const CMD_TRACE_REPLY: u8 = 0x49;
+const CMD_CALIBRATE_VELOCITY: u8 = 0x0A;
+const CMD_CLEAR_VELOCITY_CALIBRATION: u8 = 0x0B;
+const CMD_CAPTURE_SNAPSHOT: u8 = 0x0C;
+const CMD_RECALL_SNAPSHOT: u8 = 0x0D;
+const CMD_PROFILE_REQUEST: u8 = 0x0E;
+const CMD_PROFILE_REPLY: u8 = 0x4E;
+const CMD_CASCADE_TAG: u8 = 0x10;
+// Unsolicited, so outside the request/reply pairs
+const CMD_TELEMETRY: u8 = 0x60;
+
+/// Requests the merger answers over SysEx
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum Request {
+    /// `F0 7D 4D 4D <dev> 01 F7` - send the status report
+    Status,
+    /// `F0 7D 4D 4D <dev> 02 F7` - start the loopback latency test
+    LatencyStart,
+    /// `F0 7D 4D 4D <dev> 03 F7` - stop the latency test and send its report
+    LatencyStop,
+    /// `F0 7D 4D 4D <dev> 04 <timestamp> F7` - our own latency probe coming back
+    LatencyProbe(u32),
+    /// `F0 7D 4D 4D <dev> 05 <level> F7` - set the runtime log level
+    /// (0 error, 1 warn, 2 info, 3 debug)
+    SetLogLevel(LogLevel),
+    /// `F0 7D 4D 4D <dev> 06 <0|1> F7` - disable or enable the RTT monitor
+    SetMonitor(bool),
+    /// `F0 7D 4D 4D <dev> 07 F7` - send the crash log
+    CrashLog,
+    /// `F0 7D 4D 4D <dev> 08 F7` - clear the crash log
+    ClearCrashLog,
+    /// `F0 7D 4D 4D <dev> 09 F7` - dump the flight recorder and re-arm it
+    Trace,
+    /// `F0 7D 4D 4D <dev> 0A F7` - arm a velocity calibration
+    CalibrateVelocity,
+    /// `F0 7D 4D 4D <dev> 0B F7` - drop all velocity calibrations
+    ClearVelocityCalibration,
+    /// `F0 7D 4D 4D <dev> 0C F7` - capture the tracked controller values
+    CaptureSnapshot,
+    /// `F0 7D 4D 4D <dev> 0D F7` - send the captured controller values
+    RecallSnapshot,
+    /// `F0 7D 4D 4D <dev> 0E F7` - send the processing time profile
+    Profile,
+    /// `F0 7E <dev> 06 01 F7` - Universal Identity Request
+    Identity,
+}
+
+/// Recognize a captured SysEx message as a request addressed to the merger
+///
+/// Requests must carry our `DEVICE_ID` or `BROADCAST_ID`. Returns `None` for
+/// any other SysEx, which the caller handles as normal traffic.
+pub fn parse_request(data: &[u8]) -> Option<Request> {
+    if let [0xF0, UNIVERSAL_NON_REALTIME, device, GENERAL_INFORMATION, IDENTITY_REQUEST, SYSEX_END] =
+        data
+    {
+        return addressed_to_us(*device).then_some(Request::Identity);
+    }
+
+    let body = data.strip_prefix(&HEADER)?.strip_suffix(&[SYSEX_END])?;
+    let (device, body) = body.split_first()?;
+    if !addressed_to_us(*device) {
+        return None;
+    }
+    match body {
+        [CMD_STATUS_REQUEST] => Some(Request::Status),
+        [CMD_LATENCY_START] => Some(Request::LatencyStart),
+        [CMD_LATENCY_STOP] => Some(Request::LatencyStop),
+        [CMD_LATENCY_PROBE, timestamp @ ..] => read_u32(timestamp).map(Request::LatencyProbe),
+        [CMD_SET_LOG_LEVEL, level] => LogLevel::from_u8(*level).map(Request::SetLogLevel),
+        [CMD_SET_MONITOR, 0] => Some(Request::SetMonitor(false)),
+        [CMD_SET_MONITOR, 1] => Some(Request::SetMonitor(true)),
+        [CMD_CRASH_LOG_REQUEST] => Some(Request::CrashLog),
+        [CMD_CRASH_LOG_CLEAR] => Some(Request::ClearCrashLog),
+        [CMD_TRACE_REQUEST] => Some(Request::Trace),
+        [CMD_CALIBRATE_VELOCITY] => Some(Request::CalibrateVelocity),
+        [CMD_CLEAR_VELOCITY_CALIBRATION] => Some(Request::ClearVelocityCalibration),
+        [CMD_CAPTURE_SNAPSHOT] => Some(Request::CaptureSnapshot),
+        [CMD_RECALL_SNAPSHOT] => Some(Request::RecallSnapshot),
+        [CMD_PROFILE_REQUEST] => Some(Request::Profile),
+        _ => None,
+    }
+}
+
+/// Standard resets that put a sound module into a known state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum ModuleReset {
+    /// General MIDI System On, `F0 7E 7F 09 01 F7`
+    GmOn,
+    /// Roland GS Reset, `F0 41 10 42 12 40 00 7F 00 41 F7`
+    GsReset,
+    /// Yamaha XG System On, `F0 43 10 4C 00 00 7E 00 F7`
+    XgOn,
+}
+
+impl ModuleReset {
+    /// The message as sent by the merger, to the default device number
+    pub fn message(self) -> &'static [u8] {
+        match self {
+            ModuleReset::GmOn => &[
+                0xF0,
+                UNIVERSAL_NON_REALTIME,
+                BROADCAST_ID,
+                GENERAL_MIDI,
+                GM_SYSTEM_ON,
+                SYSEX_END,
+            ],
+            ModuleReset::GsReset => &[
+                0xF0, ROLAND_ID, 0x10, 0x42, 0x12, 0x40, 0x00, 0x7F, 0x00, 0x41, SYSEX_END,
+            ],
+            ModuleReset::XgOn => &[
+                0xF0, YAMAHA_ID, 0x10, 0x4C, 0x00, 0x00, 0x7E, 0x00, SYSEX_END,
+            ],
+        }
+    }
+}
+
+/// Length of a cascade tag
+pub const CASCADE_TAG_LEN: usize = HEADER.len() + 5;
+
+/// Build a cascade tag (see cascade.rs)
+///
+/// `F0 7D 4D 4D <dev> 10 <count LSB> <count MSB> F7`: the merger that made
+/// up the voice messages that follow, and how many there are.
+pub fn cascade_tag(device: u8, count: u16) -> [u8; CASCADE_TAG_LEN] {
+    [
+        HEADER[0],
+        HEADER[1],
+        HEADER[2],
+        HEADER[3],
+        device,
+        CMD_CASCADE_TAG,
+        (count & 0x7F) as u8,
+        (count >> 7 & 0x7F) as u8,
+        SYSEX_END,
+    ]
+}
+
+/// Recognize a captured SysEx message as a cascade tag from any merger
+///
+/// Returns the device ID of the merger and the number of messages covered.
+pub fn parse_cascade_tag(data: &[u8]) -> Option<(u8, u16)> {
+    let body = data.strip_prefix(&HEADER)?.strip_suffix(&[SYSEX_END])?;
+    match body {
+        [device, CMD_CASCADE_TAG, lsb, msb] => Some((*device, (*msb as u16) << 7 | *lsb as u16)),
+        _ => None,
+    }
+}
+
+/// Recognize a captured SysEx message as a GM, GS or XG reset, whatever
+/// device it is addressed to
+pub fn parse_module_reset(data: &[u8]) -> Option<ModuleReset> {
+    match data {
+        [0xF0, UNIVERSAL_NON_REALTIME, _, GENERAL_MIDI, GM_SYSTEM_ON, SYSEX_END] => {
+            Some(ModuleReset::GmOn)
+        }
+        [0xF0, ROLAND_ID, 0x10..=0x1F, rest @ ..] if *rest == GS_RESET => {
+            Some(ModuleReset::GsReset)
+        }
+        [0xF0, YAMAHA_ID, 0x10..=0x1F, rest @ ..] if *rest == XG_SYSTEM_ON => {
+            Some(ModuleReset::XgOn)
+        }
+        _ => None,
+    }
+}
+
+fn addressed_to_us(device: u8) -> bool {
+    device == DEVICE_ID || device == BROADCAST_ID
+}
+
+// Every value is sent as 5 bytes of 7 bits, least significant first
+const U32_ENCODED_LEN: usize = 5;
+
+/// Length of a SysEx message carrying `values` encoded u32 values
+const fn message_len(values: usize) -> usize {
+    HEADER.len() + 2 + values * U32_ENCODED_LEN + 1
+}
+
+// Four counters per input, then the device-wide counters and queue marks
+const STATS_VALUES: usize = 4 * UartChannel::COUNT + 6;
+
+// Uptime and reset reason, the counters, then the tempo
+const STATUS_REPORT_VALUES: usize = 2 + STATS_VALUES + 1;
+
+/// Length of the status report SysEx
+pub const STATUS_REPORT_LEN: usize = message_len(STATUS_REPORT_VALUES);
+
+/// Length of the latency report SysEx (samples, min, max, mean, jitter)
+pub const LATENCY_REPORT_LEN: usize = message_len(5);
+
+/// Length of a latency probe SysEx (one timestamp)
+pub const LATENCY_PROBE_LEN: usize = message_len(1);
+
+// Four values per input, then the histogram
+const PROFILE_VALUES: usize = 4 * UartChannel::COUNT + BUCKETS;
+
+// Uptime and firmware version, then messages and errors of each input
+const TELEMETRY_VALUES: usize = 2 + 2 * UartChannel::COUNT;
+
+/// Length of the telemetry frame SysEx
+pub const TELEMETRY_LEN: usize = message_len(TELEMETRY_VALUES);
+
+/// Length of the processing time profile SysEx
+pub const PROFILE_REPORT_LEN: usize = message_len(PROFILE_VALUES);
+
+/// Maximum length of the crash log SysEx (reason, uptime, counters, message
+/// count and messages, then the panic message text)
+pub const CRASH_REPORT_LEN: usize =
+    message_len(2 + STATS_VALUES + 1 + RECENT_LEN) + PANIC_MESSAGE_LEN;
+
+/// Build the status report reply
+///
+/// Layout after `F0 7D 4D 4D <dev> 41`, each value a 7-bit encoded u32:
+/// 1. Uptime in seconds
+/// 2. Reset reason (0 unknown, 1 power-on/brownout, 2 RUN pin, 3 debug,
+///    4 watchdog, 5 software)
+/// 3. For each input (UART0, UART1, I2C, SPI): messages, transport errors,
+///    parse errors, resyncs
+/// 4. Queue drops, output drops, TX errors
+/// 5. High-water marks of the message channel and the SPI bridge queue
+/// 6. GM/GS/XG resets received
+/// 7. Tempo of the incoming MIDI Clock in hundredths of a BPM, 0 without
+///    a running clock
+///
+/// `midi-sim status-report` decodes it (`tools/midi-sim/src/status.rs`);
+/// a change in the layout goes there too.
+pub fn status_report(
+    stats: &Stats,
+    uptime_secs: u32,
+    reset_reason: ResetReason,
+    bpm_hundredths: u32,
+) -> Vec<u8, STATUS_REPORT_LEN> {
+    let mut values = [0u32; STATUS_REPORT_VALUES];
+    values[0] = uptime_secs;
+    values[1] = reset_reason as u32;
+    values[2..2 + STATS_VALUES].copy_from_slice(&stats_values(stats));
+    values[STATUS_REPORT_VALUES - 1] = bpm_hundredths;
+
+    message(CMD_STATUS_REPLY, &values)
+}
+
+/// Counters in status report order
+fn stats_values(stats: &Stats) -> [u32; STATS_VALUES] {
+    let mut values = [0u32; STATS_VALUES];
+    for (i, input) in stats.inputs.iter().enumerate() {
+        values[4 * i] = input.messages;
+        values[1 + 4 * i] = input.transport_errors;
+        values[2 + 4 * i] = input.parse_errors;
+        values[3 + 4 * i] = input.resyncs;
+    }
+    values[STATS_VALUES - 6] = stats.queue_drops;
+    values[STATS_VALUES - 5] = stats.output_drops;
+    values[STATS_VALUES - 4] = stats.tx_errors;
+    values[STATS_VALUES - 3] = stats.channel_high_water;
+    values[STATS_VALUES - 2] = stats.bridge_high_water;
+    values[STATS_VALUES - 1] = stats.module_resets;
+    values
+}
+
+/// Build the latency test report
+///
+/// Layout after `F0 7D 4D 4D <dev> 43`: sample count, then minimum, maximum, mean
+/// and jitter in microseconds, each a 7-bit encoded u32.
+pub fn latency_report(stats: &LatencyStats) -> Vec<u8, LATENCY_REPORT_LEN> {
+    message(
+        CMD_LATENCY_REPORT,
+        &[
+            stats.samples,
+            stats.min_us,
+            stats.max_us,
+            stats.mean_us,
+            stats.jitter_us,
+        ],
+    )
+}
+
+/// Build the processing time profile reply
+///
+/// Layout after `F0 7D 4D 4D <dev> 4E`, each value a 7-bit encoded u32:
+/// 1. For each input (UART0, UART1, I2C, SPI): messages timed, then minimum,
+///    maximum and mean in microseconds
+/// 2. Messages of all inputs under 64 µs, under 128 µs, and so on doubling
+///    up to under 4096 µs, then 4096 µs and over
+pub fn profile_report(profile: &Profile) -> Vec<u8, PROFILE_REPORT_LEN> {
+    let mut values = [0u32; PROFILE_VALUES];
+    for (i, input) in profile.inputs.iter().enumerate() {
+        values[4 * i] = input.samples;
+        values[1 + 4 * i] = input.min_us;
+        values[2 + 4 * i] = input.max_us;
+        values[3 + 4 * i] = input.mean_us;
+    }
+    values[4 * UartChannel::COUNT..].copy_from_slice(&profile.histogram);
+    message(CMD_PROFILE_REPLY, &values)
+}
+
+/// Build the periodic telemetry frame
+///
+/// Layout after `F0 7D 4D 4D <dev> 60`, each value a 7-bit encoded u32:
+/// 1. Uptime in seconds
+/// 2. Firmware version as major << 16 | minor << 8 | patch
+/// 3. For each input (UART0, UART1, I2C, SPI): messages, then errors
+///    (transport and parse errors together)
+///
+/// The counters are the status report's and wrap around alike.
+pub fn telemetry(stats: &Stats, uptime_secs: u32) -> Vec<u8, TELEMETRY_LEN> {
+    let mut values = [0u32; TELEMETRY_VALUES];
+    values[0] = uptime_secs;
+    values[1] = (parse_version(env!("CARGO_PKG_VERSION_MAJOR")) as u32) << 16
+        | (parse_version(env!("CARGO_PKG_VERSION_MINOR")) as u32) << 8
+        | parse_version(env!("CARGO_PKG_VERSION_PATCH")) as u32;
+    for (i, input) in stats.inputs.iter().enumerate() {
+        values[2 + 2 * i] = input.messages;
+        values[3 + 2 * i] = input.transport_errors.wrapping_add(input.parse_errors);
+    }
+    message(CMD_TELEMETRY, &values)
+}
+
+/// Build a latency probe carrying the time it was sent
+pub fn latency_probe(timestamp_us: u32) -> Vec<u8, LATENCY_PROBE_LEN> {
+    message(CMD_LATENCY_PROBE, &[timestamp_us])
+}
+
+/// Build the crash log reply
+///
+/// `F0 7D 4D 4D <dev> 47 F7` if no crash is logged. Otherwise the layout after
+/// `F0 7D 4D 4D <dev> 47`, each value a 7-bit encoded u32 unless noted:
+/// 1. Reason (1 panic, 2 watchdog)
+/// 2. Uptime in seconds at the crash
+/// 3. The counters, in status report order
+/// 4. Number of recent messages, then each message packed as source index
+///    (bits 28-31), byte count (bits 24-27) and up to three bytes
+/// 5. The panic message as plain ASCII bytes, up to the closing `F7`
+pub fn crash_report(record: Option<&CrashRecord>) -> Vec<u8, CRASH_REPORT_LEN> {
+    let mut message = Vec::new();
+    // Capacity covers the largest record, so none of the pushes can fail
+    message.extend_from_slice(&HEADER).unwrap();
+    message.push(DEVICE_ID).unwrap();
+    message.push(CMD_CRASH_LOG_REPLY).unwrap();
+    if let Some(record) = record {
+        push_u32(&mut message, record.reason as u32);
+        push_u32(&mut message, record.uptime_secs);
+        for value in stats_values(&record.stats) {
+            push_u32(&mut message, value);
+        }
+        push_u32(&mut message, record.recent.len() as u32);
+        for packed in &record.recent {
+            push_u32(&mut message, *packed);
+        }
+        for byte in record.message.bytes() {
+            message.push(byte & 0x7F).unwrap();
+        }
+    }
+    message.push(SYSEX_END).unwrap();
+    message
+}
+
+/// Length of the flight recorder dump (time, trigger flag, event count, then
+/// three values per event)
+pub const TRACE_REPORT_LEN: usize = message_len(4 + 3 * TRACE_LEN);
+
+/// Build the flight recorder dump
+///
+/// Layout after `F0 7D 4D 4D <dev> 49`, each value a 7-bit encoded u32. Times are
+/// microseconds since boot, split into high and low 32 bits.
+/// 1. Current time, high and low
+/// 2. 1 if an error stopped the recording, else 0
+/// 3. Number of events, then for each event (oldest first) its time, high
+///    and low, and the event packed as source index (bits 28-31), byte count
+///    (bits 24-27) and up to three bytes. A byte count of 0 is an error, the
+///    first byte is its kind (1 transport, 2 parse).
+pub fn trace_report(trace: &Trace, now_us: u64) -> Vec<u8, TRACE_REPORT_LEN> {
+    let mut message = Vec::new();
+    // Capacity covers a full buffer, so none of the pushes can fail
+    message.extend_from_slice(&HEADER).unwrap();
+    message.push(DEVICE_ID).unwrap();
+    message.push(CMD_TRACE_REPLY).unwrap();
+    push_u32(&mut message, (now_us >> 32) as u32);
+    push_u32(&mut message, now_us as u32);
+    push_u32(&mut message, trace.triggered as u32);
+    push_u32(&mut message, trace.events.len() as u32);
+    for event in &trace.events {
+        push_u32(&mut message, (event.timestamp_us >> 32) as u32);
+        push_u32(&mut message, event.timestamp_us as u32);
+        push_u32(&mut message, event.packed);
+    }
+    message.push(SYSEX_END).unwrap();
+    message
+}
+
+/// Length of the Identity Reply
+pub const IDENTITY_REPLY_LEN: usize = 15;
+
+/// Build the Universal Identity Reply
+///
+/// `F0 7E <dev> 06 02 7D 4D 4D 00 01 <major> <minor> <patch> 00 F7`: our
+/// manufacturer ID, "MM" as the family code, model 1 and the firmware version.
+pub fn identity_reply() -> Vec<u8, IDENTITY_REPLY_LEN> {
+    Vec::from_slice(&[
+        0xF0,
+        UNIVERSAL_NON_REALTIME,
+        DEVICE_ID,
+        GENERAL_INFORMATION,
+        IDENTITY_REPLY,
+        HEADER[1],
+        HEADER[2],
+        HEADER[3],
+        0x00,
+        0x01,
+        parse_version(env!("CARGO_PKG_VERSION_MAJOR")),
+        parse_version(env!("CARGO_PKG_VERSION_MINOR")),
+        parse_version(env!("CARGO_PKG_VERSION_PATCH")),
+        0x00,
+        SYSEX_END,
+    ])
+    .unwrap()
+}
+
+/// Parse a version component at compile time, clamped to a data byte
+const fn parse_version(component: &str) -> u8 {
+    let bytes = component.as_bytes();
+    let mut value: u32 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        value = value * 10 + (bytes[i] - b'0') as u32;
+        i += 1;
+    }
+    if value > 0x7F {
+        0x7F
+    } else {
+        value as u8
+    }
+}
+
+/// Build a SysEx message from a command byte and encoded u32 values
+///
+/// `N` must equal `message_len(values.len())`.
+fn message<const N: usize>(command: u8, values: &[u32]) -> Vec<u8, N> {
+    let mut message = Vec::new();
+    // Capacity matches the layout exactly, so none of the pushes can fail
+    message.extend_from_slice(&HEADER).unwrap();
+    message.push(DEVICE_ID).unwrap();
+    message.push(command).unwrap();
+    for value in values {
+        push_u32(&mut message, *value);
+    }
+    message.push(SYSEX_END).unwrap();
+    message
+}
+
+/// Append a 7-bit encoded u32; the caller guarantees the capacity
+fn push_u32<const N: usize>(message: &mut Vec<u8, N>, value: u32) {
+    for i in 0..U32_ENCODED_LEN {
+        message.push(((value >> (7 * i)) & 0x7F) as u8).unwrap();
+    }
+}
+
+/// Decode a 7-bit encoded u32, the counterpart of `message()`
+fn read_u32(bytes: &[u8]) -> Option<u32> {
+    if bytes.len() != U32_ENCODED_LEN {
+        return None;
+    }
+    Some(
+        bytes
+            .iter()
+            .enumerate()
+            .fold(0u32, |value, (i, byte)| value | ((*byte as u32) << (7 * i))),
+    )
+}
@@ -0,0 +1,43 @@
+//! Tempo of the incoming MIDI Clock
+//!
+//! Clocks from any input feed one `TempoMeter`, so this is the tempo the
+//! master actually sends. It is logged whenever it moves by a whole BPM and
+//! sent in the status report.
+
+use crate::log;
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicU32, Ordering};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::Instant;
+use midi_core::tempo::TempoMeter;
+
+static METER: Mutex<CriticalSectionRawMutex, RefCell<TempoMeter>> =
+    Mutex::new(RefCell::new(TempoMeter::new()));
+
+// Tempo last logged in hundredths of a BPM, 0 for none
+static LOGGED: AtomicU32 = AtomicU32::new(0);
+
+/// Count a MIDI clock from one of the inputs
+pub fn record_clock() {
+    let now_us = Instant::now().as_micros();
+    let bpm = METER.lock(|meter| {
+        let mut meter = meter.borrow_mut();
+        meter.clock(now_us);
+        meter.bpm_hundredths(now_us)
+    });
+    let Some(bpm) = bpm else {
+        return;
+    };
+    // Single writer: the read tasks share the thread-mode executor
+    if bpm.abs_diff(LOGGED.load(Ordering::Relaxed)) >= 100 {
+        LOGGED.store(bpm, Ordering::Relaxed);
+        log::info!("Tempo {}.{=u32:02} BPM", bpm / 100, bpm % 100);
+    }
+}
+
+/// Current tempo in hundredths of a BPM, `None` while no clock runs
+pub fn bpm_hundredths() -> Option<u32> {
+    let now_us = Instant::now().as_micros();
+    METER.lock(|meter| meter.borrow().bpm_hundredths(now_us))
+}
@@ -0,0 +1,91 @@
+//! Thru-box mode
+//!
+//! With the thru jumper closed at power-up, nothing is merged: input 1 is
+//! mirrored to the UART0 TX and UART1 TX outputs, each behind its own filter
+//! of MIDI channels and message types. Messages go through the same parser
+//! as in merge mode; SysEx is not forwarded.
+//!
+//! As a splitter (`SPLITTER_MODE`), the filters route channel ranges of the
+//! input to the two outputs instead, e.g. channels 1-8 to OUT A and 9-16 to
+//! OUT B; system messages go to both.
+//!
+//! Clock and transport can be kept off either output (`OUTPUT_CLOCK`), for
+//! gear that misbehaves when it follows the clock.
+//!
+//! Each output keeps its own running status with a `Merger` of one input:
+//! when a filter drops a Voice message, the next running status message on
+//! that output gets its status byte back.
+
+use midi_core::event::Kind;
+use midi_core::parser::MidiMessage;
+
+/// Message types, combined into `ThruFilter::types`
+pub mod types {
+    pub const NOTES: u16 = 1 << 0;
+    pub const POLY_PRESSURE: u16 = 1 << 1;
+    pub const CONTROL_CHANGE: u16 = 1 << 2;
+    pub const PROGRAM_CHANGE: u16 = 1 << 3;
+    pub const CHANNEL_PRESSURE: u16 = 1 << 4;
+    pub const PITCH_BEND: u16 = 1 << 5;
+    pub const SYSTEM_COMMON: u16 = 1 << 6;
+    pub const REALTIME: u16 = 1 << 7;
+    pub const ALL: u16 = 0xFF;
+}
+
+/// Which messages an output passes
+#[derive(Debug, Clone, Copy)]
+pub struct ThruFilter {
+    /// MIDI channels 1-16 as bits 0-15, for channel messages
+    pub channels: u16,
+    /// Message types, see `types`
+    pub types: u16,
+}
+
+impl ThruFilter {
+    /// Everything passes
+    pub const ALL: Self = Self {
+        channels: 0xFFFF,
+        types: types::ALL,
+    };
+
+    /// All messages on MIDI channels `first` to `last` (1-16), and all system
+    /// messages
+    pub const fn channels(first: u8, last: u8) -> Self {
+        let mut channels = 0;
+        let mut channel = first;
+        while channel <= last {
+            channels |= 1 << (channel - 1);
+            channel += 1;
+        }
+        Self {
+            channels,
+            types: types::ALL,
+        }
+    }
+
+    /// Whether a message with this status byte passes
+    ///
+    /// Resolve running status messages to their status first.
+    pub fn passes(&self, status: u8) -> bool {
+        let kind = match Kind::of(status) {
+            Some(Kind::NoteOff | Kind::NoteOn) => types::NOTES,
+            Some(Kind::PolyPressure) => types::POLY_PRESSURE,
+            Some(Kind::ControlChange) => types::CONTROL_CHANGE,
+            Some(Kind::ProgramChange) => types::PROGRAM_CHANGE,
+            Some(Kind::ChannelPressure) => types::CHANNEL_PRESSURE,
+            Some(Kind::PitchBend) => types::PITCH_BEND,
+            Some(Kind::SystemRealtime) => types::REALTIME,
+            // 0xF9 and 0xFD, when the parser forwards them
+            None if status >= 0xF8 => types::REALTIME,
+            Some(Kind::SystemCommon) | None => types::SYSTEM_COMMON,
+        };
+        let channel_passes = status >= 0xF0 || self.channels & (1 << (status & 0x0F)) != 0;
+        self.types & kind != 0 && channel_passes
+    }
+}
+
+/// Whether a message is clock or transport: Clock, Start, Continue, Stop or
+/// Song Position Pointer
+pub fn is_clock(message: &MidiMessage) -> bool {
+    matches!(message.bytes(), [0xF2 | 0xF8 | 0xFA..=0xFC, ..])
+}
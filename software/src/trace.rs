@@ -0,0 +1,111 @@
+//! Flight recorder
+//!
+//! Keeps the last `TRACE_LEN` input events (parsed messages, transport and
+//! parse errors) with their timestamps in RAM, to be dumped over SysEx `09`
+//! long after the fact, without a debugger attached.
+//!
+//! The first error after arming acts as a trigger, like on a logic analyzer:
+//! recording continues for `POST_TRIGGER` more events and then stops, so the
+//! events around a glitch from an hour ago are still there when the buffer is
+//! dumped. Dumping re-arms the recorder.
+
+use crate::midi_uart::{UartChannel, UartMidiMessage};
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::Instant;
+use heapless::{HistoryBuffer, Vec};
+
+/// Number of events kept
+pub const TRACE_LEN: usize = 64;
+
+/// Events recorded after the trigger, the rest of the buffer shows the lead-up
+const POST_TRIGGER: usize = TRACE_LEN / 2;
+
+/// Error events, encoded in the first byte of an event without message bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Transport = 1,
+    Parse = 2,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TraceEvent {
+    /// Microseconds since boot
+    pub timestamp_us: u64,
+    /// Source and content, see `pack()`
+    pub packed: u32,
+}
+
+/// Trace contents as returned by `dump()`
+pub struct Trace {
+    /// Whether an error stopped the recording
+    pub triggered: bool,
+    /// Oldest first
+    pub events: Vec<TraceEvent, TRACE_LEN>,
+}
+
+struct Recorder {
+    events: HistoryBuffer<TraceEvent, TRACE_LEN>,
+    /// Events left to record after the trigger
+    remaining: Option<usize>,
+}
+
+impl Recorder {
+    const fn new() -> Self {
+        Self {
+            events: HistoryBuffer::new(),
+            remaining: None,
+        }
+    }
+
+    fn push(&mut self, packed: u32, trigger: bool) {
+        match self.remaining {
+            Some(0) => return,
+            Some(ref mut remaining) => *remaining -= 1,
+            None if trigger => self.remaining = Some(POST_TRIGGER),
+            None => {}
+        }
+        self.events.write(TraceEvent {
+            timestamp_us: Instant::now().as_micros(),
+            packed,
+        });
+    }
+}
+
+static RECORDER: Mutex<CriticalSectionRawMutex, RefCell<Recorder>> =
+    Mutex::new(RefCell::new(Recorder::new()));
+
+/// Pack an event into a u32: source index in bits 28-31, byte count in bits
+/// 24-27, then up to three bytes (longer SysEx is truncated). A byte count of
+/// zero marks an error event with its `ErrorKind` in the first byte.
+pub fn pack(channel: UartChannel, bytes: &[u8]) -> u32 {
+    let mut packed = (channel.index() as u32) << 28 | (bytes.len().min(0xF) as u32) << 24;
+    for (i, byte) in bytes.iter().take(3).enumerate() {
+        packed |= (*byte as u32) << (16 - 8 * i);
+    }
+    packed
+}
+
+pub fn record_message(message: &UartMidiMessage) {
+    let packed = pack(message.uart_channel, message.message.bytes());
+    RECORDER.lock(|recorder| recorder.borrow_mut().push(packed, false));
+}
+
+pub fn record_error(channel: UartChannel, kind: ErrorKind) {
+    let packed = pack(channel, &[]) | (kind as u32) << 16;
+    RECORDER.lock(|recorder| recorder.borrow_mut().push(packed, true));
+}
+
+/// Copy the recorded events and re-arm the trigger
+pub fn dump() -> Trace {
+    RECORDER.lock(|recorder| {
+        let mut recorder = recorder.borrow_mut();
+        let trace = Trace {
+            triggered: recorder.remaining.is_some(),
+            events: recorder.events.oldest_ordered().copied().collect(),
+        };
+        recorder.remaining = None;
+        trace
+    })
+}
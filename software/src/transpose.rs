@@ -0,0 +1,101 @@
+//! Transpose set from a control channel
+//!
+//! With `TRANSPOSE_CONTROL_CHANNEL` set, a Note On on that channel, from any
+//! input, sets the transpose of everything else to its distance from middle
+//! C: playing the D above it shifts the output up two semitones, the B below
+//! it down one. Key changes between songs then take one keystroke. Notes on
+//! the control channel are not forwarded.
+//!
+//! Each Note On remembers the transpose it went out with, so its Note Off
+//! and aftertouch follow it even when the transpose changes while it is
+//! held. Notes transposed beyond 0-127 are dropped.
+
+use crate::log;
+use midi_core::parser::MidiMessage;
+
+/// Middle C, the key that sets no transpose
+const MIDDLE_C: u8 = 60;
+
+pub struct Transposer {
+    /// Control channel, 0-15
+    control_channel: Option<u8>,
+    offset: i8,
+    /// Transpose each note went out with at its Note On, by channel and
+    /// incoming note
+    held: [[i8; 128]; 16],
+}
+
+impl Transposer {
+    /// # Arguments
+    /// * `control_channel` - MIDI channel, 1-16, whose notes set the
+    ///   transpose; `None` disables transposing
+    pub fn new(control_channel: Option<u8>) -> Self {
+        Self {
+            control_channel: control_channel.map(|channel| channel - 1),
+            offset: 0,
+            held: [[0; 128]; 16],
+        }
+    }
+
+    /// Status and data bytes of a note message, resolving running status
+    fn note_data(message: &mut MidiMessage, cached_status: Option<u8>) -> Option<(u8, &mut [u8])> {
+        let (status, data) = match message {
+            MidiMessage::Voice(data) => (data[0], &mut data[1..]),
+            MidiMessage::RunningStatus(data) => (cached_status?, &mut data[..]),
+            _ => return None,
+        };
+        matches!(status & 0xF0, 0x80 | 0x90 | 0xA0).then_some((status, data))
+    }
+
+    /// Take a note on the control channel, returns whether it was one
+    ///
+    /// # Arguments
+    /// * `cached_status` - The input's last status, resolves running status
+    pub fn takes(&mut self, message: &mut MidiMessage, cached_status: Option<u8>) -> bool {
+        let Some(control_channel) = self.control_channel else {
+            return false;
+        };
+        let Some((status, data)) = Self::note_data(message, cached_status) else {
+            return false;
+        };
+        if status & 0x0F != control_channel {
+            return false;
+        }
+        if let (0x90, [note, velocity]) = (status & 0xF0, &*data) {
+            if *velocity > 0 {
+                self.offset = *note as i8 - MIDDLE_C as i8;
+                log::info!("Transpose set to {}", self.offset);
+            }
+        }
+        true
+    }
+
+    /// Transpose the note of a message in place
+    ///
+    /// Returns false if the transposed note is out of range and the message
+    /// should be dropped.
+    ///
+    /// # Arguments
+    /// * `cached_status` - The input's last status, resolves running status
+    pub fn transpose(&mut self, message: &mut MidiMessage, cached_status: Option<u8>) -> bool {
+        if self.control_channel.is_none() {
+            return true;
+        }
+        let Some((status, data)) = Self::note_data(message, cached_status) else {
+            return true;
+        };
+        let [note, rest @ ..] = data else {
+            return true;
+        };
+        let held = &mut self.held[(status & 0x0F) as usize][*note as usize & 0x7F];
+        if status & 0xF0 == 0x90 && rest.first().is_some_and(|velocity| *velocity > 0) {
+            *held = self.offset;
+        }
+        let transposed = *note as i16 + *held as i16;
+        if !(0..=127).contains(&transposed) {
+            return false;
+        }
+        *note = transposed as u8;
+        true
+    }
+}
@@ -0,0 +1,185 @@
+//! Per-input velocity calibration
+//!
+//! Keyboards differ wildly in the velocity range they actually produce: one
+//! never goes below 30, another tops out at 100. Instead of hand-tuning a
+//! curve, SysEx `0A` arms a calibration: the first Note On starts a
+//! `CALIBRATION_TIME` window in which the lowest and highest velocity of each
+//! input are recorded while the player plays softly and hard. Afterwards
+//! Note On velocities of every calibrated input are stretched linearly from
+//! that range to 1-127. Inputs without enough notes in the window keep their
+//! previous calibration.
+//!
+//! Calibrations live in RAM until SysEx `0B` clears them or the board resets.
+//!
+//! After calibration, `VELOCITY_DYNAMICS` can compress or expand the
+//! velocities of an input above a threshold, to tame an over-dynamic player
+//! or give a flat controller more range, with a makeup offset on top.
+
+use crate::log;
+use crate::midi_uart::UartChannel;
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::{Duration, Instant};
+
+/// Length of the recording window, from the first Note On
+pub const CALIBRATION_TIME: Duration = Duration::from_secs(10);
+
+/// Notes an input needs in the window to be calibrated
+const MIN_NOTES: u32 = 8;
+
+/// Velocity dynamics of an input
+///
+/// Velocities above `threshold` are scaled by `ratio`, input to output:
+/// (4, 1) compresses 4:1, (1, 2) expands 1:2. `makeup` is then added to
+/// every velocity, and the result kept within 1-127.
+#[derive(Debug, Clone, Copy)]
+pub struct Dynamics {
+    pub threshold: u8,
+    pub ratio: (u8, u8),
+    pub makeup: i8,
+}
+
+impl Dynamics {
+    /// Processed velocity of a Note On, 1-127
+    pub fn apply(&self, velocity: u8) -> u8 {
+        let velocity = velocity as i32;
+        let threshold = self.threshold as i32;
+        let (input, output) = self.ratio;
+        let shaped = if velocity > threshold {
+            threshold + (velocity - threshold) * output as i32 / (input as i32).max(1)
+        } else {
+            velocity
+        };
+        (shaped + self.makeup as i32).clamp(1, 127) as u8
+    }
+}
+
+/// Velocity range of an input
+#[derive(Debug, Clone, Copy)]
+struct Range {
+    min: u8,
+    max: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Recording {
+    range: Range,
+    notes: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    /// Waiting for the first note
+    Armed,
+    /// Recording until the given instant
+    Recording(Instant),
+}
+
+struct Calibration {
+    state: State,
+    recordings: [Option<Recording>; UartChannel::COUNT],
+    ranges: [Option<Range>; UartChannel::COUNT],
+}
+
+impl Calibration {
+    const fn new() -> Self {
+        Self {
+            state: State::Idle,
+            recordings: [None; UartChannel::COUNT],
+            ranges: [None; UartChannel::COUNT],
+        }
+    }
+
+    fn record(&mut self, channel: UartChannel, velocity: u8) {
+        let recording = self.recordings[channel.index()].get_or_insert(Recording {
+            range: Range {
+                min: velocity,
+                max: velocity,
+            },
+            notes: 0,
+        });
+        recording.range.min = recording.range.min.min(velocity);
+        recording.range.max = recording.range.max.max(velocity);
+        recording.notes += 1;
+    }
+
+    fn finish(&mut self) {
+        self.state = State::Idle;
+        for channel in UartChannel::ALL {
+            let Some(recording) = self.recordings[channel.index()].take() else {
+                continue;
+            };
+            let Range { min, max } = recording.range;
+            if recording.notes < MIN_NOTES || min == max {
+                log::warn!(
+                    "Velocity calibration of {:?} skipped, {} notes ({}-{})",
+                    channel,
+                    recording.notes,
+                    min,
+                    max
+                );
+                continue;
+            }
+            log::info!(
+                "Velocity calibration of {:?}: {}-{} from {} notes",
+                channel,
+                min,
+                max,
+                recording.notes
+            );
+            self.ranges[channel.index()] = Some(recording.range);
+        }
+    }
+}
+
+static CALIBRATION: Mutex<CriticalSectionRawMutex, RefCell<Calibration>> =
+    Mutex::new(RefCell::new(Calibration::new()));
+
+/// Arm a calibration, recording starts with the next Note On
+pub fn start() {
+    CALIBRATION.lock(|calibration| {
+        let mut calibration = calibration.borrow_mut();
+        calibration.state = State::Armed;
+        calibration.recordings = [None; UartChannel::COUNT];
+    });
+}
+
+/// Drop all calibrations and any calibration in progress
+pub fn clear() {
+    CALIBRATION.lock(|calibration| *calibration.borrow_mut() = Calibration::new());
+}
+
+/// Calibrated velocity of a Note On
+///
+/// Velocities pass unchanged while a calibration records them.
+///
+/// # Arguments
+/// * `velocity` - Note On velocity, 1-127 (0 is a Note Off)
+pub fn calibrate(channel: UartChannel, velocity: u8) -> u8 {
+    CALIBRATION.lock(|calibration| {
+        let mut calibration = calibration.borrow_mut();
+        match calibration.state {
+            State::Armed => {
+                log::info!("Velocity calibration recording");
+                calibration.state = State::Recording(Instant::now() + CALIBRATION_TIME);
+            }
+            State::Recording(until) if Instant::now() >= until => calibration.finish(),
+            _ => {}
+        }
+
+        if matches!(calibration.state, State::Recording(_)) {
+            calibration.record(channel, velocity);
+            return velocity;
+        }
+
+        match calibration.ranges[channel.index()] {
+            Some(Range { min, max }) => {
+                let offset = (velocity.clamp(min, max) - min) as u32;
+                (1 + offset * 126 / (max - min) as u32) as u8
+            }
+            None => velocity,
+        }
+    })
+}
@@ -0,0 +1,110 @@
+//! WS2812 LED strip driver on a PIO state machine
+//!
+//! The state machine shifts out 24 bits per LED with the strip's 800 kHz
+//! timing on its own, so the CPU only pushes one word per LED into the FIFO
+//! and never bit-bangs.
+
+use embassy_rp::clocks::clk_sys_freq;
+use embassy_rp::pio::{
+    Common, Config, FifoJoin, Instance, PioPin, ShiftConfig, ShiftDirection, StateMachine,
+};
+use embassy_time::Timer;
+use fixed::types::U24F8;
+
+/// Strip bit rate in kHz
+const BIT_RATE_KHZ: u32 = 800;
+
+/// PIO cycles of the three phases of a bit: low, high for a 0 or 1, then
+/// high only for a 1
+const T1: u8 = 2;
+const T2: u8 = 5;
+const T3: u8 = 3;
+
+/// Low time after a frame that latches the colors
+const LATCH_US: u64 = 60;
+
+/// Time to shift out one LED's 24 bits
+const WORD_US: u64 = 30;
+
+/// Color of one LED
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// The color at a brightness out of 255
+    pub fn dimmed(self, brightness: u8) -> Self {
+        let scale = |c: u8| (c as u16 * brightness as u16 / 255) as u8;
+        Self::new(scale(self.r), scale(self.g), scale(self.b))
+    }
+
+    /// FIFO word, GRB order from the top bit
+    fn word(self) -> u32 {
+        (self.g as u32) << 24 | (self.r as u32) << 16 | (self.b as u32) << 8
+    }
+}
+
+pub struct Ws2812<'d, P: Instance, const S: usize> {
+    sm: StateMachine<'d, P, S>,
+}
+
+impl<'d, P: Instance, const S: usize> Ws2812<'d, P, S> {
+    /// Load the program and start the state machine driving `pin`
+    pub fn new(pio: &mut Common<'d, P>, mut sm: StateMachine<'d, P, S>, pin: impl PioPin) -> Self {
+        let side_set = pio::SideSet::new(false, 1, false);
+        let mut a: pio::Assembler<32> = pio::Assembler::new_with_side_set(side_set);
+        let mut wrap_target = a.label();
+        let mut wrap_source = a.label();
+        let mut do_zero = a.label();
+        a.set_with_side_set(pio::SetDestination::PINDIRS, 1, 0);
+        a.bind(&mut wrap_target);
+        // Low for T3, shifting out the next bit
+        a.out_with_delay_and_side_set(pio::OutDestination::X, 1, T3 - 1, 0);
+        // High for T1
+        a.jmp_with_delay_and_side_set(pio::JmpCondition::XIsZero, &mut do_zero, T1 - 1, 1);
+        // A 1 stays high for T2
+        a.jmp_with_delay_and_side_set(pio::JmpCondition::Always, &mut wrap_target, T2 - 1, 1);
+        a.bind(&mut do_zero);
+        // A 0 goes low for T2
+        a.nop_with_delay_and_side_set(T2 - 1, 0);
+        a.bind(&mut wrap_source);
+        let program = a.assemble_with_wrap(wrap_source, wrap_target);
+
+        let pin = pio.make_pio_pin(pin);
+        let mut config = Config::default();
+        config.set_out_pins(&[&pin]);
+        config.set_set_pins(&[&pin]);
+        config.use_program(&pio.load_program(&program), &[&pin]);
+        let cycles_per_bit = (T1 + T2 + T3) as u32;
+        config.clock_divider =
+            U24F8::from_num(clk_sys_freq() / 1000) / U24F8::from_num(BIT_RATE_KHZ * cycles_per_bit);
+        config.fifo_join = FifoJoin::TxOnly;
+        config.shift_out = ShiftConfig {
+            auto_fill: true,
+            threshold: 24,
+            direction: ShiftDirection::Left,
+        };
+        sm.set_config(&config);
+        sm.set_enable(true);
+        Self { sm }
+    }
+
+    /// Send the colors of the whole strip and wait for them to latch
+    pub async fn write(&mut self, colors: &[Rgb]) {
+        for color in colors {
+            self.sm.tx().wait_push(color.word()).await;
+        }
+        // The last word leaves the FIFO before its bits leave the pin
+        while !self.sm.tx().empty() {
+            Timer::after_micros(WORD_US).await;
+        }
+        Timer::after_micros(WORD_US + LATCH_US).await;
+    }
+}
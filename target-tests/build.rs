@@ -0,0 +1,12 @@
+//! Link against the firmware's memory layout, so the tests run in the same
+//! flash and RAM regions and leave the crash log sector alone
+
+use std::path::PathBuf;
+use std::{env, fs};
+
+fn main() {
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::copy("../software/memory.x", out.join("memory.x")).unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+    println!("cargo:rerun-if-changed=../software/memory.x");
+}
@@ -0,0 +1,42 @@
+//! On-target tests of the MIDI code
+//!
+//! The suites under `tests/` run on an RP2040 with a debug probe attached:
+//!
+//! ```text
+//! cd target-tests
+//! cargo test                 # all suites
+//! cargo test --test parser   # one suite
+//! ```
+//!
+//! The `loopback` suite needs the merger's output patched into input 1 and
+//! input 2, as for the power-on self-test.
+//!
+//! This library holds what the suites share: the RTT logger, the panic
+//! handler and small helpers.
+
+#![no_std]
+
+use defmt_rtt as _;
+use embassy_rp as _;
+use heapless::Vec;
+use midi_core::parser::{MidiMessage, MidiParser};
+use panic_probe as _;
+
+/// Feed bytes to a parser and collect the complete messages
+///
+/// Panics on a parse error or when more than `N` messages come out.
+pub fn parse<const N: usize>(parser: &mut MidiParser, bytes: &[u8]) -> Vec<MidiMessage, N> {
+    let mut messages = Vec::new();
+    for byte in bytes {
+        if let Some(message) = parser.feed_byte(*byte).unwrap() {
+            messages.push(message).unwrap();
+        }
+    }
+    messages
+}
+
+/// Terminate the test run after a panic, with the probe reporting failure
+#[defmt::panic_handler]
+fn panic() -> ! {
+    cortex_m::asm::udf()
+}
@@ -0,0 +1,84 @@
+//! UART loopback through the MIDI circuitry
+//!
+//! Needs the output patched into both inputs, as for the power-on self-test.
+//! Bytes are written to UART0 TX (GPIO12) and must come back on UART0 RX
+//! (GPIO13) and UART1 RX (GPIO5) as the same MIDI messages.
+
+#![no_std]
+#![no_main]
+
+use embassy_rp::pac;
+use embassy_rp::uart::{Blocking, Instance, UartRx};
+use target_tests as _;
+
+/// Drain the RX FIFO of a UART without blocking
+fn drain<T: Instance>(
+    rx: &mut UartRx<'static, T, Blocking>,
+    regs: pac::uart::Uart,
+) -> heapless::Vec<u8, 32> {
+    let mut bytes = heapless::Vec::new();
+    while !regs.uartfr().read().rxfe() {
+        let mut byte = [0u8];
+        rx.blocking_read(&mut byte).unwrap();
+        bytes.push(byte[0]).unwrap();
+    }
+    bytes
+}
+
+#[defmt_test::tests]
+mod tests {
+    use super::drain;
+    use defmt::assert_eq;
+    use embassy_rp::pac;
+    use embassy_rp::peripherals::{UART0, UART1};
+    use embassy_rp::uart::{Blocking, Config, Uart, UartRx};
+    use embassy_time::{block_for, Duration};
+    use midi_core::parser::{MidiMessage, MidiParser};
+    use target_tests::parse;
+
+    /// Note On, the same note with running status, and a clock tick; short
+    /// enough to fit the 32-byte RX FIFOs
+    const MESSAGES: [u8; 6] = [0x90, 0x3C, 0x64, 0x3E, 0x64, 0xF8];
+
+    /// Time for the bytes to arrive at 31250 baud (6 bytes take ~1.9ms)
+    const SETTLE: Duration = Duration::from_millis(5);
+
+    struct State {
+        uart0: Uart<'static, UART0, Blocking>,
+        uart1_rx: UartRx<'static, UART1, Blocking>,
+    }
+
+    #[init]
+    fn init() -> State {
+        let p = embassy_rp::init(Default::default());
+        let mut config = Config::default();
+        config.baudrate = 31250;
+        let uart0 = Uart::new_blocking(p.UART0, p.PIN_12, p.PIN_13, config);
+        let uart1_rx = UartRx::new_blocking(p.UART1, p.PIN_5, config);
+        State { uart0, uart1_rx }
+    }
+
+    #[test]
+    fn messages_return_on_both_inputs(state: &mut State) {
+        let (tx, rx0) = state.uart0.split_ref();
+        // Discard anything left over from power-up
+        drain(rx0, pac::UART0);
+        drain(&mut state.uart1_rx, pac::UART1);
+
+        tx.blocking_write(&MESSAGES).unwrap();
+        tx.blocking_flush().unwrap();
+        block_for(SETTLE);
+
+        for received in [
+            drain(rx0, pac::UART0),
+            drain(&mut state.uart1_rx, pac::UART1),
+        ] {
+            assert_eq!(received.as_slice(), &MESSAGES);
+            let mut parser = MidiParser::default();
+            let messages = parse::<3>(&mut parser, &received);
+            assert!(matches!(&messages[0], MidiMessage::Voice(_)));
+            assert!(matches!(&messages[1], MidiMessage::RunningStatus(_)));
+            assert!(matches!(&messages[2], MidiMessage::SystemRealtime(_)));
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Merger status injection on the target
+
+#![no_std]
+#![no_main]
+
+use midi_core::merge::Merger;
+use midi_core::parser::{MidiMessage, MidiParser, ShortMessage};
+use target_tests::parse;
+
+/// Parse a message of one input and merge it, returning the wire bytes
+fn merge(
+    merger: &mut Merger<2>,
+    parsers: &mut [MidiParser; 2],
+    input: usize,
+    bytes: &[u8],
+) -> ShortMessage {
+    let messages = parse::<1>(&mut parsers[input], bytes);
+    let wire = merger.prepare(input, &messages[0]).unwrap();
+    merger.sent(input, &messages[0]);
+    wire
+}
+
+fn voice(bytes: &[u8]) -> MidiMessage {
+    MidiMessage::Voice(ShortMessage::from_slice(bytes).unwrap())
+}
+
+#[defmt_test::tests]
+mod tests {
+    use super::{merge, voice};
+    use defmt::assert_eq;
+    use midi_core::merge::Merger;
+    use midi_core::parser::{MidiMessage, MidiParser, ShortMessage};
+
+    #[init]
+    fn init() {
+        embassy_rp::init(Default::default());
+    }
+
+    #[test]
+    fn running_status_passes_through_from_same_input() {
+        let mut merger = Merger::<2>::default();
+        let mut parsers = [MidiParser::default(), MidiParser::default()];
+        merge(&mut merger, &mut parsers, 0, &[0x90, 0x3C, 0x64]);
+        assert_eq!(
+            merge(&mut merger, &mut parsers, 0, &[0x3E, 0x64]),
+            [0x3E, 0x64]
+        );
+    }
+
+    #[test]
+    fn status_injected_after_other_input() {
+        let mut merger = Merger::<2>::default();
+        let mut parsers = [MidiParser::default(), MidiParser::default()];
+        merge(&mut merger, &mut parsers, 0, &[0x90, 0x3C, 0x64]);
+        merge(&mut merger, &mut parsers, 1, &[0xB0, 0x07, 0x40]);
+        assert_eq!(
+            merge(&mut merger, &mut parsers, 0, &[0x3E, 0x64]),
+            [0x90, 0x3E, 0x64]
+        );
+    }
+
+    #[test]
+    fn status_injected_after_system_common() {
+        let mut merger = Merger::<2>::default();
+        let mut parsers = [MidiParser::default(), MidiParser::default()];
+        merge(&mut merger, &mut parsers, 0, &[0x90, 0x3C, 0x64]);
+        merge(&mut merger, &mut parsers, 1, &[0xF6]);
+        merge(&mut merger, &mut parsers, 1, &[0xB0, 0x07, 0x40]);
+        assert_eq!(
+            merge(&mut merger, &mut parsers, 1, &[0x07, 0x41]),
+            [0x07, 0x41]
+        );
+        assert_eq!(
+            merge(&mut merger, &mut parsers, 0, &[0x3E, 0x64]),
+            [0x90, 0x3E, 0x64]
+        );
+    }
+
+    #[test]
+    fn invalidated_input_needs_status_byte() {
+        let mut merger = Merger::<2>::default();
+        merger.prepare(0, &voice(&[0x90, 0x3C, 0x64]));
+        merger.sent(0, &voice(&[0x90, 0x3C, 0x64]));
+        merger.invalidate(0);
+        let running = MidiMessage::RunningStatus(ShortMessage::from_slice(&[0x3E, 0x64]).unwrap());
+        assert!(merger.prepare(0, &running).is_none());
+    }
+}
@@ -0,0 +1,71 @@
+//! MidiParser on the target, with the real time driver behind its timeout
+
+#![no_std]
+#![no_main]
+
+use target_tests as _;
+
+#[defmt_test::tests]
+mod tests {
+    use defmt::assert_eq;
+    use midi_core::parser::{MidiMessage, MidiMessageError, MidiParser};
+    use target_tests::parse;
+
+    #[init]
+    fn init() {
+        // Starts the timer the parser's byte timeout reads
+        embassy_rp::init(Default::default());
+    }
+
+    #[test]
+    fn voice_message() {
+        let mut parser = MidiParser::default();
+        let messages = parse::<1>(&mut parser, &[0x90, 0x3C, 0x64]);
+        assert!(matches!(&messages[0], MidiMessage::Voice(data) if data == &[0x90, 0x3C, 0x64]));
+    }
+
+    #[test]
+    fn running_status() {
+        let mut parser = MidiParser::default();
+        let messages = parse::<3>(&mut parser, &[0xC0, 0x05, 0x06, 0x07]);
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(&messages[1], MidiMessage::RunningStatus(data) if data == &[0x06]));
+        assert!(matches!(&messages[2], MidiMessage::RunningStatus(data) if data == &[0x07]));
+    }
+
+    #[test]
+    fn realtime_inside_message() {
+        let mut parser = MidiParser::default();
+        let messages = parse::<2>(&mut parser, &[0x90, 0x3C, 0xF8, 0x64]);
+        assert!(matches!(&messages[0], MidiMessage::SystemRealtime(data) if data == &[0xF8]));
+        assert!(matches!(&messages[1], MidiMessage::Voice(data) if data == &[0x90, 0x3C, 0x64]));
+    }
+
+    #[test]
+    fn system_common_cancels_running_status() {
+        let mut parser = MidiParser::default();
+        parse::<2>(&mut parser, &[0x90, 0x3C, 0x64, 0xF3, 0x01]);
+        assert!(matches!(
+            parser.feed_byte(0x3E),
+            Err(MidiMessageError::UnexpectedDataByte)
+        ));
+    }
+
+    #[test]
+    fn short_sysex_is_captured() {
+        let mut parser = MidiParser::default();
+        let sysex = [0xF0, 0x7D, 0x4D, 0x4D, 0x00, 0x01, 0xF7];
+        let messages = parse::<1>(&mut parser, &sysex);
+        assert!(matches!(&messages[0], MidiMessage::SysEx(data) if data == &sysex));
+    }
+
+    #[test]
+    fn resync_on_next_status_byte() {
+        let mut parser = MidiParser::default();
+        assert!(parser.feed_byte(0xF4).is_err());
+        assert_eq!(parser.resync_count(), 1);
+        // Data bytes are discarded until a status byte arrives
+        let messages = parse::<1>(&mut parser, &[0x3C, 0x64, 0x80, 0x3C, 0x40]);
+        assert!(matches!(&messages[0], MidiMessage::Voice(data) if data == &[0x80, 0x3C, 0x40]));
+    }
+}
@@ -0,0 +1,153 @@
+//! Host simulation of the merge engine
+//!
+//! Feeds timestamped byte streams through the firmware's `MidiParser` and
+//! `Merger`, one parser per input, in timestamp order, and collects the
+//! merged output. This is the path a message takes from the UART readers to
+//! `write_uart`, minus the hardware: no queueing delay, pacing or pair
+//! holding, and the parser's byte timeout runs on the host clock rather than
+//! the stream timestamps.
+//!
+//! Streams are text, one chunk of bytes per line, as they arrived:
+//!
+//! ```text
+//! # time_us bytes
+//! 0 90 3c 64
+//! 850 3e 64
+//! ```
+//!
+//! The output uses the same format with one message per line, so it can be
+//! fed back in or diffed.
+
+pub mod status;
+
+use midi_core::merge::Merger;
+use midi_core::parser::{MidiMessage, MidiMessageError, MidiParser};
+use std::fmt::Write as _;
+use std::io::BufRead;
+
+/// Bytes arriving on one input at one instant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub time_us: u64,
+    pub bytes: Vec<u8>,
+}
+
+/// Something the merge engine did besides writing a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The input's parser rejected a byte and was reset
+    ParseError(MidiMessageError),
+    /// Running status without a status to inject
+    NoRunningStatus,
+    /// SysEx is not merged
+    SysExDropped,
+}
+
+/// What came out of the merger, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Output {
+    Message {
+        time_us: u64,
+        bytes: Vec<u8>,
+    },
+    Event {
+        time_us: u64,
+        input: usize,
+        event: Event,
+    },
+}
+
+/// Read a stream in the text format
+pub fn read_stream(reader: impl BufRead) -> Result<Vec<Chunk>, String> {
+    let mut chunks = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| err.to_string())?;
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let Some(time) = fields.next() else {
+            continue;
+        };
+        let time_us = time
+            .parse()
+            .map_err(|_| format!("line {}: bad timestamp {:?}", number + 1, time))?;
+        let bytes = fields
+            .map(|field| u8::from_str_radix(field, 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("line {}: bad byte", number + 1))?;
+        chunks.push(Chunk { time_us, bytes });
+    }
+    Ok(chunks)
+}
+
+/// Format a message line of the text format
+pub fn format_line(time_us: u64, bytes: &[u8]) -> String {
+    let mut line = time_us.to_string();
+    for byte in bytes {
+        let _ = write!(line, " {:02x}", byte);
+    }
+    line
+}
+
+/// Merge the streams of all inputs
+///
+/// Chunks are processed in timestamp order; at equal timestamps the lower
+/// input goes first.
+pub fn merge<const INPUTS: usize>(streams: &[Vec<Chunk>; INPUTS]) -> Vec<Output> {
+    let mut chunks: Vec<(usize, &Chunk)> = streams
+        .iter()
+        .enumerate()
+        .flat_map(|(input, stream)| stream.iter().map(move |chunk| (input, chunk)))
+        .collect();
+    // Stable, so each input's chunks keep their order
+    chunks.sort_by_key(|(input, chunk)| (chunk.time_us, *input));
+
+    let mut parsers: [MidiParser; INPUTS] = core::array::from_fn(|_| MidiParser::default());
+    let mut merger = Merger::<INPUTS>::default();
+    let mut output = Vec::new();
+
+    for (input, chunk) in chunks {
+        let time_us = chunk.time_us;
+        for byte in &chunk.bytes {
+            let message = match parsers[input].feed_byte(*byte) {
+                Ok(Some(message)) => message,
+                Ok(None) => continue,
+                Err(err) => {
+                    // As the read tasks do: reset the parser and invalidate
+                    // the input's running status
+                    parsers[input].reset();
+                    merger.invalidate(input);
+                    output.push(Output::Event {
+                        time_us,
+                        input,
+                        event: Event::ParseError(err),
+                    });
+                    continue;
+                }
+            };
+
+            if let MidiMessage::SysEx(_) = message {
+                output.push(Output::Event {
+                    time_us,
+                    input,
+                    event: Event::SysExDropped,
+                });
+                continue;
+            }
+            match merger.prepare(input, &message) {
+                Some(bytes) => {
+                    merger.sent(input, &message);
+                    output.push(Output::Message {
+                        time_us,
+                        bytes: bytes.to_vec(),
+                    });
+                }
+                None => output.push(Output::Event {
+                    time_us,
+                    input,
+                    event: Event::NoRunningStatus,
+                }),
+            }
+        }
+    }
+    output
+}
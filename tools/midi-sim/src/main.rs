@@ -0,0 +1,100 @@
+//! Merge two recorded MIDI byte streams like the merger would
+//!
+//! ```text
+//! midi-sim <input0> <input1>
+//! midi-sim status-request [<device>]
+//! midi-sim status-report <reply>
+//! ```
+//!
+//! Either input may be `-` to read it from stdin. The merged stream goes to
+//! stdout, parse errors and dropped messages to stderr. See the library for
+//! the stream format.
+//!
+//! `status-request` prints the status request SysEx for a merger (hex device
+//! ID, all of them by default) and `status-report` decodes the reply, as hex
+//! bytes from a file or `-` for stdin. E.g. with ALSA:
+//!
+//! ```text
+//! amidi -p hw:1 -S "$(midi-sim status-request)" -d -t 1 | midi-sim status-report -
+//! ```
+
+use midi_sim::status::{format_hex, parse_status_report, read_hex, status_request, BROADCAST_ID};
+use midi_sim::{format_line, merge, read_stream, Output};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: midi-sim <input0> <input1>  (- reads stdin)
+       midi-sim status-request [<device>]
+       midi-sim status-report <reply>";
+
+fn read<T>(path: &str, parse: impl Fn(&mut dyn BufRead) -> Result<T, String>) -> Result<T, String> {
+    let result = if path == "-" {
+        parse(&mut io::stdin().lock())
+    } else {
+        let file = File::open(path).map_err(|err| format!("{}: {}", path, err))?;
+        parse(&mut BufReader::new(file))
+    };
+    result.map_err(|err| format!("{}: {}", path, err))
+}
+
+fn open(path: &str) -> Result<Vec<midi_sim::Chunk>, String> {
+    read(path, |reader| read_stream(reader))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.as_slice() {
+        [command, device @ ..] if command == "status-request" && device.len() <= 1 => {
+            print_request(device.first())
+        }
+        [command, reply] if command == "status-report" => print_report(reply),
+        [input0, input1] => run_merge(input0, input1),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn print_request(device: Option<&String>) -> Result<(), String> {
+    let device = match device {
+        Some(device) => u8::from_str_radix(device, 16)
+            .ok()
+            .filter(|device| *device <= 0x7F)
+            .ok_or_else(|| format!("bad device ID {:?}", device))?,
+        None => BROADCAST_ID,
+    };
+    println!("{}", format_hex(&status_request(device)));
+    Ok(())
+}
+
+fn print_report(path: &str) -> Result<(), String> {
+    let bytes = read(path, |reader| read_hex(reader))?;
+    let report = parse_status_report(&bytes).map_err(|err| format!("{}: {}", path, err))?;
+    println!("{}", report);
+    Ok(())
+}
+
+fn run_merge(input0: &str, input1: &str) -> Result<(), String> {
+    if input0 == "-" && input1 == "-" {
+        return Err("only one input can be read from stdin".into());
+    }
+    let streams = [open(input0)?, open(input1)?];
+
+    for output in merge(&streams) {
+        match output {
+            Output::Message { time_us, bytes } => println!("{}", format_line(time_us, &bytes)),
+            Output::Event {
+                time_us,
+                input,
+                event,
+            } => eprintln!("{} input {}: {:?}", time_us, input, event),
+        }
+    }
+    Ok(())
+}
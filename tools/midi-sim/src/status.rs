@@ -0,0 +1,184 @@
+//! Host side of the merger's status report SysEx
+//!
+//! Builds the `01` status request and decodes the `41` status report the
+//! merger answers with, so the report can be read with any tool that sends
+//! and dumps raw SysEx, e.g. `amidi -S <request> -d`. The layout mirrors
+//! `status_report()` in the firmware's `sysex.rs`.
+
+use midi_core::midi_uart::UartChannel;
+use std::fmt;
+use std::io::BufRead;
+
+/// Header of every SysEx message exchanged with the merger
+const HEADER: [u8; 4] = [0xF0, 0x7D, 0x4D, 0x4D];
+const CMD_STATUS_REQUEST: u8 = 0x01;
+const CMD_STATUS_REPLY: u8 = 0x41;
+const SYSEX_END: u8 = 0xF7;
+
+/// Device ID every merger on the chain answers to
+pub const BROADCAST_ID: u8 = 0x7F;
+
+// Every value is sent as 5 bytes of 7 bits, least significant first
+const U32_ENCODED_LEN: usize = 5;
+
+// Uptime and reset reason, four counters per input, the device-wide
+// counters and queue marks, then the tempo
+const VALUES: usize = 2 + 4 * UartChannel::COUNT + 6 + 1;
+
+/// Length of the status report SysEx
+pub const STATUS_REPORT_LEN: usize = HEADER.len() + 2 + VALUES * U32_ENCODED_LEN + 1;
+
+/// Counters of one input
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InputCounters {
+    pub messages: u32,
+    pub transport_errors: u32,
+    pub parse_errors: u32,
+    pub resyncs: u32,
+}
+
+/// A decoded status report
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StatusReport {
+    /// Device ID of the merger that answered
+    pub device: u8,
+    pub uptime_secs: u32,
+    /// 0 unknown, 1 power-on/brownout, 2 RUN pin, 3 debug, 4 watchdog,
+    /// 5 software
+    pub reset_reason: u32,
+    /// In `UartChannel` order
+    pub inputs: [InputCounters; UartChannel::COUNT],
+    pub queue_drops: u32,
+    pub output_drops: u32,
+    pub tx_errors: u32,
+    pub channel_high_water: u32,
+    pub bridge_high_water: u32,
+    pub module_resets: u32,
+    /// Tempo of the incoming MIDI Clock in hundredths of a BPM, 0 without a
+    /// running clock
+    pub bpm_hundredths: u32,
+}
+
+/// The status request for one merger, or all of them with `BROADCAST_ID`
+pub fn status_request(device: u8) -> Vec<u8> {
+    let mut request = HEADER.to_vec();
+    request.extend([device, CMD_STATUS_REQUEST, SYSEX_END]);
+    request
+}
+
+/// Decode a status report
+pub fn parse_status_report(bytes: &[u8]) -> Result<StatusReport, String> {
+    let Some(rest) = bytes.strip_prefix(&HEADER) else {
+        return Err("not a merger SysEx".into());
+    };
+    let [device, command, rest @ ..] = rest else {
+        return Err("truncated header".into());
+    };
+    if *command != CMD_STATUS_REPLY {
+        return Err(format!("command {:02x} is not a status report", command));
+    }
+    if bytes.len() != STATUS_REPORT_LEN || rest.last() != Some(&SYSEX_END) {
+        return Err(format!(
+            "{} bytes, a status report has {}",
+            bytes.len(),
+            STATUS_REPORT_LEN
+        ));
+    }
+    let mut values = rest[..rest.len() - 1].chunks(U32_ENCODED_LEN).map(read_u32);
+    let mut next = || values.next().unwrap_or_default();
+
+    let mut report = StatusReport {
+        device: *device,
+        uptime_secs: next(),
+        reset_reason: next(),
+        ..StatusReport::default()
+    };
+    for input in &mut report.inputs {
+        *input = InputCounters {
+            messages: next(),
+            transport_errors: next(),
+            parse_errors: next(),
+            resyncs: next(),
+        };
+    }
+    report.queue_drops = next();
+    report.output_drops = next();
+    report.tx_errors = next();
+    report.channel_high_water = next();
+    report.bridge_high_water = next();
+    report.module_resets = next();
+    report.bpm_hundredths = next();
+    Ok(report)
+}
+
+/// Decode a 7-bit encoded u32
+fn read_u32(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .enumerate()
+        .fold(0, |value, (i, byte)| value | (u32::from(*byte) << (7 * i)))
+}
+
+/// Read hex bytes separated by whitespace, as `amidi -d` dumps them
+pub fn read_hex(reader: impl BufRead) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| err.to_string())?;
+        for field in line.split_whitespace() {
+            let byte = u8::from_str_radix(field, 16)
+                .map_err(|_| format!("line {}: bad byte {:?}", number + 1, field))?;
+            bytes.push(byte);
+        }
+    }
+    Ok(bytes)
+}
+
+/// Format bytes as `amidi -S` takes them
+pub fn format_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl fmt::Display for StatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "device        {:#04x}", self.device)?;
+        writeln!(f, "uptime        {} s", self.uptime_secs)?;
+        writeln!(f, "reset reason  {}", reset_reason_name(self.reset_reason))?;
+        writeln!(f, "input         messages  transport  parse  resyncs")?;
+        for (channel, input) in UartChannel::ALL.iter().zip(&self.inputs) {
+            writeln!(
+                f,
+                "{:<12} {:>9} {:>10} {:>6} {:>8}",
+                format!("{:?}", channel),
+                input.messages,
+                input.transport_errors,
+                input.parse_errors,
+                input.resyncs
+            )?;
+        }
+        writeln!(f, "queue drops   {}", self.queue_drops)?;
+        writeln!(f, "output drops  {}", self.output_drops)?;
+        writeln!(f, "TX errors     {}", self.tx_errors)?;
+        writeln!(f, "channel peak  {}", self.channel_high_water)?;
+        writeln!(f, "bridge peak   {}", self.bridge_high_water)?;
+        writeln!(f, "GM/GS/XG      {}", self.module_resets)?;
+        match self.bpm_hundredths {
+            0 => write!(f, "tempo         no clock"),
+            bpm => write!(f, "tempo         {}.{:02} BPM", bpm / 100, bpm % 100),
+        }
+    }
+}
+
+fn reset_reason_name(reason: u32) -> &'static str {
+    match reason {
+        1 => "power-on/brownout",
+        2 => "RUN pin",
+        3 => "debug",
+        4 => "watchdog",
+        5 => "software",
+        _ => "unknown",
+    }
+}
@@ -0,0 +1,77 @@
+//! Golden-trace tests of the merge semantics
+//!
+//! Every directory in `tests/golden/` holds a recorded pair of input streams
+//! (`input0.txt`, `input1.txt`) and the expected merge (`expected.txt`): the
+//! output messages in the stream format, with parse errors and dropped
+//! messages as comment lines in between. Any difference fails the test.
+//!
+//! After an intended change in behavior, regenerate the expected files with
+//! `UPDATE_GOLDEN=1 cargo test -p midi-sim --test golden` and review the diff.
+
+use midi_sim::{format_line, merge, read_stream, Chunk, Output};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+fn read(path: &Path) -> Vec<Chunk> {
+    let file = File::open(path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    read_stream(BufReader::new(file)).unwrap_or_else(|err| panic!("{}: {}", path.display(), err))
+}
+
+fn render(outputs: &[Output]) -> String {
+    let mut text = String::new();
+    for output in outputs {
+        let line = match output {
+            Output::Message { time_us, bytes } => format_line(*time_us, bytes),
+            Output::Event {
+                time_us,
+                input,
+                event,
+            } => format!("# {} input {}: {:?}", time_us, input, event),
+        };
+        text.push_str(&line);
+        text.push('\n');
+    }
+    text
+}
+
+#[test]
+fn golden_traces() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+
+    let mut traces: Vec<_> = fs::read_dir(&root)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    traces.sort();
+    assert!(!traces.is_empty(), "no traces in {}", root.display());
+
+    let mut failed = Vec::new();
+    for trace in &traces {
+        let streams = [
+            read(&trace.join("input0.txt")),
+            read(&trace.join("input1.txt")),
+        ];
+        let actual = render(&merge(&streams));
+        let expected_path = trace.join("expected.txt");
+
+        if update {
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&expected_path)
+            .unwrap_or_else(|err| panic!("{}: {}", expected_path.display(), err));
+        if actual != expected {
+            eprintln!(
+                "{}\n--- expected\n{}--- actual\n{}",
+                trace.display(),
+                expected,
+                actual
+            );
+            failed.push(trace.file_name().unwrap().to_string_lossy().into_owned());
+        }
+    }
+    assert!(failed.is_empty(), "traces differ: {:?}", failed);
+}
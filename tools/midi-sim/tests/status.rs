@@ -0,0 +1,88 @@
+//! Status report request and decoding
+
+use midi_sim::status::{
+    parse_status_report, read_hex, status_request, InputCounters, STATUS_REPORT_LEN,
+};
+
+/// A status report as the firmware's `status_report()` builds it
+fn reply(device: u8, values: &[u32]) -> Vec<u8> {
+    let mut bytes = vec![0xF0, 0x7D, 0x4D, 0x4D, device, 0x41];
+    for value in values {
+        bytes.extend((0..5).map(|i| ((value >> (7 * i)) & 0x7F) as u8));
+    }
+    bytes.push(0xF7);
+    bytes
+}
+
+fn values() -> Vec<u32> {
+    let mut values = vec![3600, 4];
+    for input in 0..4 {
+        values.extend([1000 * (input + 1), input, 2 * input, 3 * input]);
+    }
+    values.extend([5, 6, 7, 32, 16, 1, 12050]);
+    values
+}
+
+#[test]
+fn request() {
+    assert_eq!(
+        status_request(0x7F),
+        [0xF0, 0x7D, 0x4D, 0x4D, 0x7F, 0x01, 0xF7]
+    );
+    assert_eq!(status_request(0x03)[4], 0x03);
+}
+
+#[test]
+fn decodes_every_value() {
+    let bytes = reply(0x03, &values());
+    assert_eq!(bytes.len(), STATUS_REPORT_LEN);
+    let report = parse_status_report(&bytes).unwrap();
+    assert_eq!(report.device, 0x03);
+    assert_eq!(report.uptime_secs, 3600);
+    assert_eq!(report.reset_reason, 4);
+    assert_eq!(
+        report.inputs[2],
+        InputCounters {
+            messages: 3000,
+            transport_errors: 2,
+            parse_errors: 4,
+            resyncs: 6
+        }
+    );
+    assert_eq!(report.queue_drops, 5);
+    assert_eq!(report.output_drops, 6);
+    assert_eq!(report.tx_errors, 7);
+    assert_eq!(report.channel_high_water, 32);
+    assert_eq!(report.bridge_high_water, 16);
+    assert_eq!(report.module_resets, 1);
+    assert_eq!(report.bpm_hundredths, 12050);
+    assert!(report.to_string().contains("120.50 BPM"));
+}
+
+#[test]
+fn large_values() {
+    let mut values = values();
+    values[0] = u32::MAX;
+    let report = parse_status_report(&reply(0x7F, &values)).unwrap();
+    assert_eq!(report.uptime_secs, u32::MAX);
+}
+
+#[test]
+fn rejects_other_messages() {
+    assert!(parse_status_report(&[0xF0, 0x7E, 0x7F, 0x06, 0x02, 0xF7]).is_err());
+    // A latency report
+    let mut bytes = reply(0x7F, &values());
+    bytes[5] = 0x43;
+    assert!(parse_status_report(&bytes).is_err());
+    // A report of another firmware version with more values
+    let mut values = values();
+    values.push(0);
+    assert!(parse_status_report(&reply(0x7F, &values)).is_err());
+}
+
+#[test]
+fn reads_amidi_dumps() {
+    let dump = "F0 7D 4D 4D 7F 01 F7\n";
+    assert_eq!(read_hex(dump.as_bytes()).unwrap(), status_request(0x7F));
+    assert!(read_hex("F0 XY".as_bytes()).is_err());
+}